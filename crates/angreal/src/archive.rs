@@ -0,0 +1,254 @@
+//! Templates distributed as archives
+//!
+//! `angreal init` accepts `.tar.gz`, `.tgz` and `.zip` archives as templates,
+//! either as local files or HTTP(S) URLs. An archive is extracted into
+//! `~/.angrealrc/archives/<sha256>`, keyed by the checksum of its contents, so
+//! an archive that hasn't changed is only extracted once. When the expected
+//! checksum is given with `#sha256=<hex>`, a download that doesn't match it is
+//! refused, and an archive already in the cache isn't downloaded again.
+
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::read::GzDecoder;
+use log::debug;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Where extracted archives are cached, under `~/.angrealrc`
+const ARCHIVES_DIR: &str = "archives";
+
+/// The kinds of archive a template can come in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveKind {
+    TarGz,
+    Zip,
+}
+
+impl ArchiveKind {
+    /// The kind of archive a location points to, from its extension
+    pub fn of(location: &str) -> Option<Self> {
+        let path = location.split('?').next().unwrap_or(location);
+        let path = path.to_ascii_lowercase();
+        if path.ends_with(".tar.gz") || path.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if path.ends_with(".zip") {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether a template location is an archive rather than a directory or repository
+pub fn is_archive(location: &str) -> bool {
+    ArchiveKind::of(location).is_some()
+}
+
+/// Check a `#sha256=` checksum is a hex encoded SHA-256
+pub fn check_sha256(sha256: &str) -> Result<String> {
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!(
+            "'{}' isn't a SHA-256 checksum, expected 64 hex digits",
+            sha256
+        );
+    }
+    Ok(sha256.to_ascii_lowercase())
+}
+
+/// Fetch and extract an archive into the cache under `angreal_home`
+///
+/// Returns the directory holding the template. An archive whose contents are
+/// all in a single top-level directory, like the source archives made by git
+/// hosts, has that directory returned.
+pub fn fetch_archive(location: &str, sha256: Option<&str>, angreal_home: &Path) -> Result<PathBuf> {
+    let kind = ArchiveKind::of(location)
+        .ok_or_else(|| anyhow!("{} isn't a .tar.gz, .tgz or .zip archive", location))?;
+    let cache = angreal_home.join(ARCHIVES_DIR);
+
+    if let Some(sha256) = sha256 {
+        let dst = cache.join(sha256);
+        if dst.is_dir() {
+            debug!("Archive {} already extracted to {:?}", sha256, dst);
+            return Ok(template_root(&dst));
+        }
+    }
+
+    let contents = read_archive(location)?;
+    let digest = format!("{:x}", Sha256::digest(&contents));
+    if let Some(sha256) = sha256 {
+        if digest != sha256 {
+            bail!(
+                "Checksum mismatch for {}: expected sha256 {} but got {}",
+                location,
+                sha256,
+                digest
+            );
+        }
+    }
+
+    let dst = cache.join(&digest);
+    if !dst.is_dir() {
+        debug!("Extracting {} to {:?}", location, dst);
+        fs::create_dir_all(&cache)
+            .with_context(|| format!("Failed to create {}", cache.display()))?;
+        // Extract next to the cache entry and move it into place, so a failed
+        // extraction never leaves a partial template behind
+        let staging = TempDir::new_in(&cache).context("Failed to create a staging directory")?;
+        extract(kind, &contents, staging.path())
+            .with_context(|| format!("Failed to extract {}", location))?;
+        let staging = staging.keep();
+        if let Err(e) = fs::rename(&staging, &dst) {
+            let _ = fs::remove_dir_all(&staging);
+            // Another init may have extracted the same archive in the meantime
+            if !dst.is_dir() {
+                return Err(e)
+                    .with_context(|| format!("Failed to move the template to {}", dst.display()));
+            }
+        }
+    }
+    Ok(template_root(&dst))
+}
+
+/// Read an archive from a local file or an HTTP(S) URL
+fn read_archive(location: &str) -> Result<Vec<u8>> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        debug!("Downloading {}", location);
+        let response = reqwest::blocking::get(location)
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Failed to download {}", location))?;
+        let contents = response
+            .bytes()
+            .with_context(|| format!("Failed to download {}", location))?;
+        return Ok(contents.to_vec());
+    }
+    let path = location.strip_prefix("file://").unwrap_or(location);
+    fs::read(path).with_context(|| format!("Failed to read {}", path))
+}
+
+fn extract(kind: ArchiveKind, contents: &[u8], dst: &Path) -> Result<()> {
+    match kind {
+        ArchiveKind::TarGz => {
+            tar::Archive::new(GzDecoder::new(contents)).unpack(dst)?;
+        }
+        ArchiveKind::Zip => {
+            zip::ZipArchive::new(Cursor::new(contents))?.extract(dst)?;
+        }
+    }
+    Ok(())
+}
+
+/// The directory a template was extracted to, looking inside a lone top-level directory
+fn template_root(dir: &Path) -> PathBuf {
+    if dir.join("angreal.toml").is_file() {
+        return dir.to_path_buf();
+    }
+    let entries: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).collect(),
+        Err(_) => return dir.to_path_buf(),
+    };
+    match entries.as_slice() {
+        [entry] if entry.path().is_dir() => entry.path(),
+        _ => dir.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    const FILES: [(&str, &str); 2] = [
+        ("template/angreal.toml", "name = \"demo\"\n"),
+        ("template/{{ name }}/README.md", "# {{ name }}\n"),
+    ];
+
+    fn tar_gz() -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, contents) in FILES {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, contents.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn zip() -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, contents) in FILES {
+            writer
+                .start_file(path, zip::write::FileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_archive_kind() {
+        assert_eq!(ArchiveKind::of("python.tar.gz"), Some(ArchiveKind::TarGz));
+        assert_eq!(
+            ArchiveKind::of("https://host/python.TGZ?token=abc"),
+            Some(ArchiveKind::TarGz)
+        );
+        assert_eq!(ArchiveKind::of("/tmp/python.zip"), Some(ArchiveKind::Zip));
+        assert_eq!(ArchiveKind::of("https://host/python.git"), None);
+    }
+
+    #[test]
+    fn test_check_sha256() {
+        let sha256 = "A".repeat(64);
+        assert_eq!(check_sha256(&sha256).unwrap(), "a".repeat(64));
+        assert!(check_sha256("abc").is_err());
+        assert!(check_sha256(&"g".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn test_fetch_archives() {
+        let tmp = TempDir::new().unwrap();
+        for (name, contents) in [("t.tar.gz", tar_gz()), ("t.zip", zip())] {
+            let archive = tmp.path().join(name);
+            fs::write(&archive, &contents).unwrap();
+            let sha256 = format!("{:x}", Sha256::digest(&contents));
+
+            let home = tmp.path().join(format!("home-{}", name));
+            let location = archive.to_str().unwrap();
+            let template = fetch_archive(location, Some(&sha256), &home).unwrap();
+            assert_eq!(
+                template,
+                home.join(ARCHIVES_DIR).join(&sha256).join("template")
+            );
+            assert_eq!(
+                fs::read_to_string(template.join("{{ name }}/README.md")).unwrap(),
+                "# {{ name }}\n"
+            );
+
+            // The cached copy is used once the checksum is known
+            fs::remove_file(&archive).unwrap();
+            assert_eq!(
+                fetch_archive(location, Some(&sha256), &home).unwrap(),
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn test_fetch_archive_checksum_mismatch() {
+        let tmp = TempDir::new().unwrap();
+        let archive = tmp.path().join("t.tar.gz");
+        fs::write(&archive, tar_gz()).unwrap();
+        let home = tmp.path().join("home");
+
+        let err = fetch_archive(archive.to_str().unwrap(), Some(&"0".repeat(64)), &home)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Checksum mismatch"), "{}", err);
+        assert!(!home.join(ARCHIVES_DIR).exists());
+    }
+}
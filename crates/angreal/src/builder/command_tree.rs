@@ -203,7 +203,7 @@ impl CommandNode {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::task::{AngrealCommand, AngrealGroup};
+    use crate::task::{AngrealCommand, AngrealGroup, CommandOptions};
     use pyo3::Python;
 
     #[test]
@@ -233,6 +233,7 @@ mod tests {
                 func,
                 tool: None,
                 registry_key: None,
+                options: CommandOptions::default(),
             };
 
             let node = CommandNode::new_command(name.clone(), command);
@@ -260,6 +261,7 @@ mod tests {
                 func: py.None(),
                 tool: None,
                 registry_key: None,
+                options: CommandOptions::default(),
             };
 
             root.add_command(command);
@@ -296,6 +298,7 @@ mod tests {
                 func: py.None(),
                 tool: None,
                 registry_key: None,
+                options: CommandOptions::default(),
             };
 
             root.add_command(command);
@@ -334,6 +337,7 @@ mod tests {
                 func: py.None(),
                 tool: None,
                 registry_key: None,
+                options: CommandOptions::default(),
             };
 
             root.add_command(command);
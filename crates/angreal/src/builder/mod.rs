@@ -5,6 +5,8 @@ pub mod tree_output;
 
 use crate::task::{generate_path_key_from_parts, AngrealArg, ANGREAL_ARGS, ANGREAL_TASKS};
 use clap::{App, AppSettings, Arg, ArgAction, Command};
+use log::warn;
+use std::collections::HashSet;
 
 use command_tree::CommandNode;
 
@@ -29,6 +31,43 @@ fn base_app_setup() -> App<'static> {
                 .action(ArgAction::Count)
                 .help("verbose level, (may be used multiple times for more verbosity)"),
         )
+        .arg(
+            Arg::new("dry_run")
+                .long("dry-run")
+                .action(ArgAction::SetTrue)
+                .help("Report the actions tasks and integrations would take without performing them."),
+        )
+        .arg(
+            Arg::new("no_wait")
+                .long("no-wait")
+                .action(ArgAction::SetTrue)
+                .help("Fail instead of waiting if the task's lock is held by another run."),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(["text", "json"])
+                .default_value("text")
+                .help("How to report the task's outcome, 'json' prints its result as a JSON document on stdout."),
+        )
+        .arg(
+            Arg::new("timeout")
+                .long("timeout")
+                .takes_value(true)
+                .value_name("DURATION")
+                .help("Interrupt the task if it runs longer than this, e.g. 90s or 10m (overrides the task's own timeout)."),
+        )
+        .arg(
+            Arg::new("timings")
+                .long("timings")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .value_name("TRACE_FILE")
+                .help("Report where time was spent once the task finishes, optionally writing a Chrome trace to TRACE_FILE."),
+        )
         .version(version!())
 }
 
@@ -66,8 +105,29 @@ fn add_init_subcommand(app: App<'static>) -> App<'static> {
                     .takes_value(true)
                     .help("Provide Values to template, bypassing template toml."),
             )
+            .arg(
+                Arg::new("ref")
+                    .long("--ref")
+                    .takes_value(true)
+                    .value_name("REF")
+                    .help("Check out a git template at this tag, branch or commit."),
+            )
+            .arg(
+                Arg::new("dry_run")
+                    .long("--dry-run")
+                    .takes_value(false)
+                    .help("Show the files and directories the template would create without writing them."),
+            )
+            .arg(
+                Arg::new("diff")
+                    .long("--diff")
+                    .takes_value(false)
+                    .requires("dry_run")
+                    .help("With --dry-run, show a diff for each existing file the template would change."),
+            )
             .arg(Arg::new("template").takes_value(true).required(true).help(
-                "The template to use. Either a pre-downloaded template name, or url to a git repo.",
+                "The template to use. Either a pre-downloaded template name, a url to a git repo \
+                 or gh:org/repo, optionally followed by @ref.",
             )),
     )
 }
@@ -77,6 +137,7 @@ fn add_completion_subcommands(app: App<'static>) -> App<'static> {
         Command::new("_complete")
             .hide(true) // Hidden from help
             .about("Generate shell completions (internal use)")
+            .allow_hyphen_values(true)
             .arg(
                 Arg::new("args")
                     .multiple_values(true)
@@ -175,6 +236,131 @@ fn add_mcp_subcommand(app: App<'static>) -> App<'static> {
     )
 }
 
+fn add_history_subcommand(app: App<'static>) -> App<'static> {
+    app.subcommand(
+        Command::new("history")
+            .about("Show previous task invocations in this project")
+            .arg(
+                Arg::new("limit")
+                    .long("limit")
+                    .short('n')
+                    .takes_value(true)
+                    .default_value("20")
+                    .help("Number of invocations to show"),
+            )
+            .arg(
+                Arg::new("command")
+                    .long("command")
+                    .short('c')
+                    .takes_value(true)
+                    .help("Only show commands matching this glob, e.g. 'docker.*'"),
+            )
+            .arg(
+                Arg::new("failed")
+                    .long("failed")
+                    .action(ArgAction::SetTrue)
+                    .help("Only show invocations that exited with a non-zero code"),
+            ),
+    )
+}
+
+fn add_rerun_subcommand(app: App<'static>) -> App<'static> {
+    app.subcommand(
+        Command::new("rerun")
+            .about("Re-run a previous task invocation with identical arguments")
+            .arg(
+                Arg::new("id")
+                    .takes_value(true)
+                    .help("The history id to re-run, defaults to the most recent invocation"),
+            ),
+    )
+}
+
+fn add_locks_subcommand(app: App<'static>) -> App<'static> {
+    app.subcommand(
+        Command::new("locks").about("Show the task locks currently held in this project"),
+    )
+}
+
+fn add_exec_subcommand(app: App<'static>) -> App<'static> {
+    app.subcommand(
+        Command::new("exec")
+            .about("Run a command from the project root with the project's environment applied")
+            .trailing_var_arg(true)
+            .arg(
+                Arg::new("command")
+                    .required(true)
+                    .multiple_values(true)
+                    .allow_hyphen_values(true)
+                    .value_name("COMMAND")
+                    .help("The command to run, a single argument is run through the shell"),
+            ),
+    )
+}
+
+fn add_info_subcommand(app: App<'static>) -> App<'static> {
+    app.subcommand(
+        Command::new("info").about("Show the template the project was rendered from and when"),
+    )
+}
+
+fn add_update_subcommand(app: App<'static>) -> App<'static> {
+    app.subcommand(
+        Command::new("update")
+            .about("Merge the latest version of the project's template into the project")
+            .arg(
+                Arg::new("ref")
+                    .long("ref")
+                    .takes_value(true)
+                    .value_name("REF")
+                    .help("Move the project to this tag, branch or commit of its template"),
+            ),
+    )
+}
+
+fn add_watch_subcommand(app: App<'static>) -> App<'static> {
+    app.subcommand(
+        Command::new("watch")
+            .about("Run a task and re-run it whenever files in the project change")
+            .trailing_var_arg(true)
+            .arg(
+                Arg::new("paths")
+                    .long("paths")
+                    .takes_value(true)
+                    .multiple_occurrences(true)
+                    .help("Only watch paths matching this glob, e.g. 'src/**' (defaults to the task's inputs)"),
+            )
+            .arg(
+                Arg::new("ignore")
+                    .long("ignore")
+                    .takes_value(true)
+                    .multiple_occurrences(true)
+                    .help("Ignore paths matching this glob, e.g. 'target/**'"),
+            )
+            .arg(
+                Arg::new("debounce")
+                    .long("debounce")
+                    .takes_value(true)
+                    .default_value("200ms")
+                    .help("How long files must stop changing before the task is re-run"),
+            )
+            .arg(
+                Arg::new("clear")
+                    .long("clear")
+                    .action(ArgAction::SetTrue)
+                    .help("Clear the terminal before each run"),
+            )
+            .arg(
+                Arg::new("command")
+                    .required(true)
+                    .multiple_values(true)
+                    .allow_hyphen_values(true)
+                    .value_name("COMMAND")
+                    .help("The task to run followed by its arguments"),
+            ),
+    )
+}
+
 fn add_project_subcommands(mut app: App<'static>) -> App<'static> {
     // Build the command tree
     let mut root = CommandNode::new_group("angreal".to_string(), None);
@@ -250,6 +436,35 @@ fn add_project_subcommands(mut app: App<'static>) -> App<'static> {
     app
 }
 
+/// Adds a built-in subcommand to the app
+type AddSubcommand = fn(App<'static>) -> App<'static>;
+
+/// Built-in subcommands that give way to a project command of the same name
+pub const YIELDING_BUILTINS: [&str; 7] = [
+    "history", "rerun", "watch", "exec", "locks", "update", "info",
+];
+
+/// The built-in subcommands hidden by a top-level project command or group
+///
+/// These were added after projects could already define commands with the
+/// same names, so the project's command is kept rather than made unreachable.
+pub fn shadowed_builtins() -> HashSet<&'static str> {
+    let tasks = ANGREAL_TASKS.lock().unwrap();
+    let top_level: HashSet<&str> = tasks
+        .values()
+        .map(
+            |cmd| match cmd.group.as_ref().and_then(|groups| groups.first()) {
+                Some(group) => group.name.as_str(),
+                None => cmd.name.as_str(),
+            },
+        )
+        .collect();
+    YIELDING_BUILTINS
+        .into_iter()
+        .filter(|name| top_level.contains(name))
+        .collect()
+}
+
 /// Build the final CLI from the registered tasks
 pub fn build_app(in_angreal_project: bool) -> App<'static> {
     // Build the initial App with angreal sub commands
@@ -263,6 +478,28 @@ pub fn build_app(in_angreal_project: bool) -> App<'static> {
     if in_angreal_project {
         app = add_tree_subcommand(app);
         app = add_mcp_subcommand(app);
+
+        // A project command with the same name as one of these takes its place
+        let shadowed = shadowed_builtins();
+        let builtins: [(&str, AddSubcommand); 7] = [
+            ("history", add_history_subcommand),
+            ("rerun", add_rerun_subcommand),
+            ("watch", add_watch_subcommand),
+            ("exec", add_exec_subcommand),
+            ("locks", add_locks_subcommand),
+            ("update", add_update_subcommand),
+            ("info", add_info_subcommand),
+        ];
+        for (name, add) in builtins {
+            if shadowed.contains(name) {
+                warn!(
+                    "The project's '{}' command hides the built-in 'angreal {}'.",
+                    name, name
+                );
+            } else {
+                app = add(app);
+            }
+        }
         app = add_project_subcommands(app);
     } else {
         app = add_init_subcommand(app);
@@ -285,4 +522,36 @@ mod tests {
         let app = build_app(false);
         assert_ne!(None, app.find_subcommand("init"));
     }
+
+    #[test]
+    fn test_project_command_hides_builtin() {
+        use crate::task::{AngrealCommand, CommandOptions};
+        use pyo3::Python;
+
+        let key = "update__builder_test".to_string();
+        Python::attach(|py| {
+            let command = AngrealCommand {
+                name: "update".to_string(),
+                about: Some("The project's own update".to_string()),
+                long_about: None,
+                group: None,
+                func: py.None(),
+                tool: None,
+                registry_key: Some(key.clone()),
+                options: CommandOptions::default(),
+            };
+            ANGREAL_TASKS.lock().unwrap().insert(key.clone(), command);
+        });
+
+        let shadowed = shadowed_builtins();
+        let app = build_app(true);
+        ANGREAL_TASKS.lock().unwrap().remove(&key);
+
+        assert!(shadowed.contains("update"));
+        let update = app.find_subcommand("update").unwrap();
+        assert_eq!(update.get_about(), Some("The project's own update"));
+        assert!(update.get_arguments().all(|a| a.get_id() != "ref"));
+        // Built-ins without a project command of the same name are kept
+        assert!(app.find_subcommand("info").is_some());
+    }
 }
@@ -460,15 +460,73 @@ pub fn show_completion_status() -> Result<()> {
     Ok(())
 }
 
+/// Flags given before the command, and whether they take a value
+const GLOBAL_FLAGS: [(&str, bool); 6] = [
+    ("--verbose", false),
+    ("--dry-run", false),
+    ("--no-wait", false),
+    ("--output", true),
+    ("--timeout", true),
+    ("--timings", false),
+];
+
+/// Flags of the built-in commands that have any
+const HISTORY_FLAGS: [&str; 3] = ["--limit", "--command", "--failed"];
+const UPDATE_FLAGS: [&str; 1] = ["--ref"];
+const WATCH_FLAGS: [&str; 4] = ["--paths", "--ignore", "--debounce", "--clear"];
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|s| s.to_string()).collect()
+}
+
+/// Split the global flags off the front of the command line
+///
+/// Returns the flag still waiting for its value, if the command line ends with
+/// one, and the arguments after the flags.
+fn skip_global_flags(args: &[String]) -> (Option<&'static str>, &[String]) {
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        match GLOBAL_FLAGS.iter().find(|(flag, _)| *flag == arg) {
+            Some((flag, true)) if i + 1 == args.len() => return (Some(flag), &args[i + 1..]),
+            Some((_, true)) => i += 2,
+            Some((_, false)) => i += 1,
+            // `-v`, `-vv`, ... and `--timings=FILE`
+            None if arg.starts_with("--timings=")
+                || (arg.len() > 1
+                    && arg.starts_with('-')
+                    && arg[1..].chars().all(|c| c == 'v')) =>
+            {
+                i += 1
+            }
+            None => break,
+        }
+    }
+    (None, &args[i..])
+}
+
 /// Generate completions for current command line
 pub fn generate_completions(args: &[String]) -> Result<Vec<String>> {
     let mut completions = Vec::new();
 
     // Filter out empty strings from args (shell completion often adds them)
-    let filtered_args: Vec<String> = args.iter().filter(|s| !s.is_empty()).cloned().collect();
+    let all_args: Vec<String> = args.iter().filter(|s| !s.is_empty()).cloned().collect();
+    let (pending, mut filtered_args) = skip_global_flags(&all_args);
+    match pending {
+        Some("--output") => return Ok(strings(&["text", "json"])),
+        // --timeout takes a duration, there's nothing to suggest
+        Some(_) => return Ok(completions),
+        None => {}
+    }
+    // bash passes the word being completed too, a flag being typed is dropped
+    if filtered_args.len() == 1 && filtered_args[0].starts_with('-') {
+        filtered_args = &[];
+    }
 
     // If we're completing the first argument after 'angreal'
     if filtered_args.is_empty() {
+        completions.extend(GLOBAL_FLAGS.iter().map(|(flag, _)| flag.to_string()));
+
         // Always add built-in commands
         completions.push("alias".to_string());
         completions.push("tree".to_string());
@@ -477,8 +535,17 @@ pub fn generate_completions(args: &[String]) -> Result<Vec<String>> {
         if crate::utils::is_angreal_project().is_err() {
             completions.push("init".to_string());
         } else {
-            // Add discovered tasks (top-level commands and groups)
-            completions.extend(get_available_tasks()?);
+            // Add discovered tasks (top-level commands and groups), and the
+            // built-ins they don't hide
+            let tasks = get_available_tasks()?;
+            let shadowed = crate::builder::shadowed_builtins();
+            completions.extend(
+                crate::builder::YIELDING_BUILTINS
+                    .iter()
+                    .filter(|name| !shadowed.contains(*name))
+                    .map(|name| name.to_string()),
+            );
+            completions.extend(tasks);
         }
         return Ok(completions);
     }
@@ -536,26 +603,68 @@ pub fn generate_completions(args: &[String]) -> Result<Vec<String>> {
 
     // Handle nested command completion for angreal projects
     if crate::utils::is_angreal_project().is_ok() {
+        load_project_tasks()?;
+        let builtin = filtered_args[0].as_str();
+        if crate::builder::YIELDING_BUILTINS.contains(&builtin)
+            && !crate::builder::shadowed_builtins().contains(builtin)
+        {
+            return builtin_completions(filtered_args);
+        }
         // For any args, try to get nested completions
         // This will handle cases like "angreal test <TAB>" or "angreal group subgroup <TAB>"
-        completions.extend(get_nested_command_completions(&filtered_args)?);
+        completions.extend(get_nested_command_completions(filtered_args)?);
     }
 
     Ok(completions)
 }
 
-/// Get available tasks in current project
-fn get_available_tasks() -> Result<Vec<String>> {
-    let mut tasks = Vec::new();
+/// Complete the arguments of a built-in command, `args` starting with its name
+fn builtin_completions(args: &[String]) -> Result<Vec<String>> {
+    match args[0].as_str() {
+        "history" => Ok(strings(&HISTORY_FLAGS)),
+        "update" => Ok(strings(&UPDATE_FLAGS)),
+        "watch" => {
+            // Skip the watcher's own options to find the task being watched
+            let mut rest = &args[1..];
+            while let Some(arg) = rest.first() {
+                match arg.as_str() {
+                    "--clear" => rest = &rest[1..],
+                    "--paths" | "--ignore" | "--debounce" if rest.len() == 1 => {
+                        return Ok(Vec::new())
+                    }
+                    "--paths" | "--ignore" | "--debounce" => rest = &rest[2..],
+                    _ => break,
+                }
+            }
+            if rest.is_empty() {
+                let mut completions = strings(&WATCH_FLAGS);
+                completions.extend(get_available_tasks()?);
+                Ok(completions)
+            } else {
+                get_nested_command_completions(rest)
+            }
+        }
+        // rerun, exec, locks and info take nothing that can be completed
+        _ => Ok(Vec::new()),
+    }
+}
 
-    // Load tasks (this triggers the same discovery as normal angreal execution)
+/// Load the project's task files so their commands are registered
+fn load_project_tasks() -> Result<()> {
+    // This triggers the same discovery as normal angreal execution
     let angreal_path = crate::utils::is_angreal_project()?;
     let task_files = crate::utils::get_task_files(angreal_path)?;
 
-    // Load task files to register commands
     for task_file in task_files {
         let _ = crate::utils::load_python(task_file); // Ignore errors for completion
     }
+    Ok(())
+}
+
+/// Get available tasks in current project
+fn get_available_tasks() -> Result<Vec<String>> {
+    let mut tasks = Vec::new();
+    load_project_tasks()?;
 
     // Get registered tasks
     for (_, task) in crate::task::ANGREAL_TASKS.lock().unwrap().iter() {
@@ -589,14 +698,7 @@ fn get_nested_command_completions(args: &[String]) -> Result<Vec<String>> {
     // Build command tree from registered tasks
     let mut root = CommandNode::new_group("root".to_string(), None);
 
-    // Load tasks
-    let angreal_path = crate::utils::is_angreal_project()?;
-    let task_files = crate::utils::get_task_files(angreal_path)?;
-
-    // Load task files to register commands
-    for task_file in task_files {
-        let _ = crate::utils::load_python(task_file); // Ignore errors for completion
-    }
+    load_project_tasks()?;
 
     // Add all registered tasks to the command tree
     for (_, task) in crate::task::ANGREAL_TASKS.lock().unwrap().iter() {
@@ -646,4 +748,50 @@ mod tests {
         // Should not crash
         let _ = should_auto_install();
     }
+
+    #[test]
+    fn test_skip_global_flags() {
+        let args = strings(&[
+            "-vv",
+            "--dry-run",
+            "--output",
+            "json",
+            "--timings=t.json",
+            "test",
+        ]);
+        assert_eq!(skip_global_flags(&args), (None, &args[5..]));
+
+        let args = strings(&["--no-wait", "--timeout"]);
+        assert_eq!(skip_global_flags(&args), (Some("--timeout"), &args[2..]));
+
+        let args = strings(&["docs", "--dry-run"]);
+        assert_eq!(skip_global_flags(&args), (None, &args[..]));
+    }
+
+    #[test]
+    fn test_global_flag_completions() {
+        assert_eq!(
+            generate_completions(&strings(&["--output"])).unwrap(),
+            ["text", "json"]
+        );
+        assert!(generate_completions(&strings(&["--timeout"]))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_builtin_completions() {
+        assert_eq!(
+            builtin_completions(&strings(&["history"])).unwrap(),
+            HISTORY_FLAGS
+        );
+        assert_eq!(
+            builtin_completions(&strings(&["update"])).unwrap(),
+            UPDATE_FLAGS
+        );
+        assert!(builtin_completions(&strings(&["watch", "--paths"]))
+            .unwrap()
+            .is_empty());
+        assert!(builtin_completions(&strings(&["info"])).unwrap().is_empty());
+    }
 }
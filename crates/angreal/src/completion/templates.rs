@@ -154,7 +154,7 @@ mod tests {
         let suggestions = get_template_suggestions().unwrap_or_default();
         // Even if network fails, should have local templates or empty list
         // Not a very useful check, len() on a Vec is always >= 0
-        #[allow(unused_comparisons)]
+        #[allow(unused_comparisons, clippy::absurd_extreme_comparisons)]
         {
             assert!(suggestions.len() >= 0);
         }
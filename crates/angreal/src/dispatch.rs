@@ -0,0 +1,671 @@
+//! Dispatch of parsed command lines to registered python tasks
+//!
+//! Shared by the top level task invocation and `angreal rerun`, so that a
+//! replayed invocation behaves exactly like the original one.
+
+use anyhow::{anyhow, bail};
+use clap::{App, ArgMatches};
+use log::{debug, error, warn};
+use pyo3::exceptions::{PyException, PyKeyboardInterrupt, PySystemExit};
+use pyo3::types::{IntoPyDict, PyDict, PyTypeMethods};
+use pyo3::{prelude::*, IntoPyObjectExt};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::builder;
+use crate::dry_run;
+use crate::env;
+use crate::error_formatter::PythonErrorFormatter;
+use crate::fingerprint::Fingerprint;
+use crate::history;
+use crate::hooks;
+use crate::locks::{self, RunLock};
+use crate::output::{self, OutputFormat, TaskError, TaskOutcome};
+use crate::process;
+use crate::task::{
+    generate_command_path_key, generate_path_key_from_parts, AngrealCommand, CommandOptions,
+    TaskCwd, TaskLock, ANGREAL_TASKS,
+};
+use crate::timeout::{self, Watchdog};
+use crate::timings;
+use crate::utils;
+
+/// Dispatch the task subcommand in `matches`, record it in the project
+/// history and return the exit code for the process
+///
+/// `argv` is the full argument vector (without the binary name) that produced
+/// `matches`, it is what `angreal rerun` replays.
+pub fn dispatch(app: &mut App<'static>, matches: &ArgMatches, argv: &[String]) -> i32 {
+    let (task, sub_m) = match matches.subcommand() {
+        Some(subcommand) => subcommand,
+        None => {
+            app.print_help().unwrap_or(());
+            return 1;
+        }
+    };
+    let (command_path, arg_matches) = resolve_task(task, sub_m);
+
+    // A --timeout on the command line overrides the one set on the command
+    let global_timeout = match matches.value_of("timeout").map(timeout::parse_duration) {
+        None => None,
+        Some(Ok(duration)) => Some(duration),
+        Some(Err(e)) => {
+            error!("{}", e);
+            return 1;
+        }
+    };
+
+    let output = match OutputFormat::parse(matches.value_of("output").unwrap_or("text")) {
+        Ok(output) => output,
+        Err(e) => {
+            error!("{}", e);
+            return 1;
+        }
+    };
+
+    debug!("Looking up command with path: {}", command_path);
+    let (registry_key, command) = match find_command(&command_path) {
+        None => {
+            error!("Command '{}' not found.", command_path);
+            app.print_help().unwrap_or(());
+            return 1;
+        }
+        Some(found) => found,
+    };
+
+    let invocation = history::start();
+    let timer = Instant::now();
+    let settings = RunSettings {
+        timeout: global_timeout.or(command.options.timeout),
+        output,
+        no_wait: matches.get_flag("no_wait"),
+    };
+    let mut outcome = run_task(
+        &registry_key,
+        &command,
+        &command_path,
+        &arg_matches,
+        &settings,
+    );
+    let duration = timer.elapsed();
+    history::record(
+        &command_path,
+        argv,
+        &invocation,
+        duration,
+        outcome.exit_code,
+    );
+
+    if output == OutputFormat::Json {
+        outcome.duration_ms = duration.as_millis() as u64;
+        outcome.print_json();
+    }
+    outcome.exit_code
+}
+
+/// How a single invocation runs, from the global command line options
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RunSettings {
+    /// Interrupt the command if it runs longer than this
+    pub timeout: Option<Duration>,
+    pub output: OutputFormat,
+    /// Fail instead of waiting when the command's lock is held
+    pub no_wait: bool,
+}
+
+/// Walk nested command group subcommands down to the invoked command,
+/// returning its logical path key and the argument matches that belong to it
+pub fn resolve_task(task: &str, sub_m: &ArgMatches) -> (String, ArgMatches) {
+    let mut command_groups: Vec<String> = Vec::new();
+    command_groups.push(task.to_string());
+
+    // iterate matches to get our final command and get our final arg matches
+    // object for applying down stream
+    let mut next = sub_m.subcommand();
+    let mut arg_matches = sub_m.clone();
+    while let Some(cmd) = next {
+        command_groups.push(cmd.0.to_string());
+        next = cmd.1.subcommand();
+        arg_matches = cmd.1.clone();
+    }
+
+    let task = command_groups.pop().unwrap();
+
+    // Generate the logical path key for command lookup
+    (
+        generate_path_key_from_parts(&command_groups, &task),
+        arg_matches,
+    )
+}
+
+/// Find a registered command by its logical path
+///
+/// Registry keys include a unique suffix to prevent collisions during
+/// decoration, so the lookup is by the path generated from the command itself.
+pub fn find_command(command_path: &str) -> Option<(String, AngrealCommand)> {
+    ANGREAL_TASKS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(_, cmd)| generate_command_path_key(cmd) == command_path)
+        .map(|(key, cmd)| (key.clone(), cmd.clone()))
+}
+
+/// Call a command's python function with the parsed arguments and hooks,
+/// returning its outcome and the exit code derived from it
+///
+/// The command is interrupted with `KeyboardInterrupt` if it runs longer than
+/// the timeout. With JSON output anything the task prints goes to stderr so
+/// that stdout is left for the result.
+pub fn run_task(
+    registry_key: &str,
+    command: &AngrealCommand,
+    command_path: &str,
+    arg_matches: &ArgMatches,
+    settings: &RunSettings,
+) -> TaskOutcome {
+    let RunSettings {
+        timeout,
+        output,
+        no_wait,
+    } = *settings;
+    let mut outcome = TaskOutcome {
+        command: command_path.to_string(),
+        ..Default::default()
+    };
+    let report_error = |formatter: PythonErrorFormatter| match output {
+        OutputFormat::Text => println!("{}", formatter),
+        OutputFormat::Json => eprintln!("{}", formatter),
+    };
+
+    debug!(
+        "Executing command: {} (registry key: {})",
+        command_path, registry_key
+    );
+
+    let args = builder::select_args(registry_key);
+    Python::attach(|py| {
+        debug!("Starting Python execution for command: {}", command_path);
+        let mut kwargs: Vec<(&str, Py<PyAny>)> = Vec::new();
+
+        for arg in args.into_iter() {
+            let n = Box::leak(Box::new(arg.name));
+            // unable to find the value of the passed arg with sub_m when its been wrapped
+            // in a command group
+
+            if arg.is_flag.unwrap() {
+                let v = arg_matches.get_flag(&n.clone());
+                kwargs.push((
+                    n.as_str(),
+                    v.into_bound_py_any(py)
+                        .expect("Failed to convert to Python object")
+                        .unbind(),
+                ));
+            } else {
+                let v = arg_matches.value_of(n.clone());
+                match v {
+                    None => {
+                        // We need to handle "boolean flags" that are present w/o a value
+                        // should probably test that the name is a "boolean type also"
+                        kwargs.push((
+                            n.as_str(),
+                            v.into_bound_py_any(py)
+                                .expect("Failed to convert to Python object")
+                                .unbind(),
+                        ));
+                    }
+                    Some(v) => match arg.python_type.unwrap().as_str() {
+                        "str" => kwargs.push((
+                            n.as_str(),
+                            v.into_bound_py_any(py)
+                                .expect("Failed to convert to Python object")
+                                .unbind(),
+                        )),
+                        "int" => kwargs.push((
+                            n.as_str(),
+                            v.parse::<i32>()
+                                .unwrap()
+                                .into_bound_py_any(py)
+                                .expect("Failed to convert to Python object")
+                                .unbind(),
+                        )),
+                        "float" => kwargs.push((
+                            n.as_str(),
+                            v.parse::<f32>()
+                                .unwrap()
+                                .into_bound_py_any(py)
+                                .expect("Failed to convert to Python object")
+                                .unbind(),
+                        )),
+                        _ => kwargs.push((
+                            n.as_str(),
+                            v.into_bound_py_any(py)
+                                .expect("Failed to convert to Python object")
+                                .unbind(),
+                        )),
+                    },
+                }
+            }
+        }
+
+        let kwargs_dict = match kwargs.into_py_dict(py) {
+            Ok(dict) => dict,
+            Err(err) => {
+                error!("Failed to convert kwargs to dict");
+                report_error(PythonErrorFormatter::new(err));
+                outcome.exit_code = 1;
+                return outcome;
+            }
+        };
+
+        // Held until the closure returns, the OS releases it if we crash
+        let _lock = match &command.options.lock {
+            None => None,
+            Some(lock) => {
+                let name = match lock {
+                    TaskLock::Command => command_path,
+                    TaskLock::Named(name) => name.as_str(),
+                };
+                match acquire_lock(py, name, command_path, no_wait) {
+                    Ok(lock) => Some(lock),
+                    Err((exit_code, message)) => {
+                        error!("{}", message);
+                        outcome.exit_code = exit_code;
+                        outcome.error = Some(TaskError {
+                            kind: "LockError".to_string(),
+                            message,
+                        });
+                        return outcome;
+                    }
+                }
+            }
+        };
+
+        // Skip the command if nothing it depends on changed since it last succeeded
+        let fingerprint = if command.options.is_incremental() {
+            let args = describe_args(&kwargs_dict);
+            match Fingerprint::compute(command_path, &command.options, &args) {
+                Ok(fingerprint) => Some(fingerprint),
+                Err(e) => {
+                    warn!("Unable to fingerprint '{}': {}", command_path, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        if fingerprint.as_ref().is_some_and(Fingerprint::is_up_to_date) {
+            if output == OutputFormat::Text {
+                println!("'{}' is up to date.", command_path);
+            }
+            outcome.success = true;
+            outcome.up_to_date = true;
+            return outcome;
+        }
+
+        let task_env = match env::resolve(&command.options)
+            .map_err(|e| e.to_string())
+            .and_then(|vars| env::apply(py, &vars).map_err(|e| e.to_string()))
+        {
+            Ok(applied) => applied,
+            Err(e) => {
+                error!(
+                    "Unable to set up the environment for '{}': {}",
+                    command_path, e
+                );
+                outcome.exit_code = 1;
+                return outcome;
+            }
+        };
+
+        let previous_dir = match change_dir(&command.options.cwd) {
+            Ok(previous_dir) => previous_dir,
+            Err(e) => {
+                error!("Unable to change directory for '{}': {}", command_path, e);
+                if let Err(e) = task_env.restore(py) {
+                    warn!("Unable to restore the environment: {}", e);
+                }
+                outcome.exit_code = 1;
+                return outcome;
+            }
+        };
+
+        let redirect = match output {
+            OutputFormat::Json => Some(output::StdoutRedirect::start(py)),
+            OutputFormat::Text => None,
+        };
+        let previous_handlers = timeout::install_signal_handlers(py);
+        let watchdog = timeout.map(|t| Watchdog::start(command_path, t));
+
+        // Before hooks may veto the command by raising, which is handled
+        // exactly like an exception from the command itself
+        let r_value = hooks::run_before(py, command_path, &kwargs_dict)
+            .and_then(|_| call_with_retries(py, command, command_path, &kwargs_dict));
+
+        // The watchdog is stopped before anything else runs, if it fired as
+        // the task returned on its own the interrupt it sent is still pending
+        // and is cleared here rather than raised in the after hooks
+        let timed_out = watchdog.map(Watchdog::finish).unwrap_or(false);
+        if timed_out {
+            let _ = py.check_signals();
+        }
+        timeout::restore_signal_handlers(py, previous_handlers);
+
+        let exit_code = match r_value {
+            Ok(r_value) => {
+                if !timed_out {
+                    hooks::run_after(py, command_path, &kwargs_dict, &r_value);
+                }
+
+                match output::result_to_json(r_value.bind(py)) {
+                    Ok(result) => outcome.result = result,
+                    Err(e) if output == OutputFormat::Json => {
+                        warn!("The result of '{}' isn't serialisable: {}", command_path, e);
+                    }
+                    Err(_) => {}
+                }
+
+                return_code(py, &r_value)
+            }
+            Err(err) => {
+                hooks::run_on_error(py, command_path, &kwargs_dict, &err);
+                outcome.error = Some(TaskError::from_pyerr(py, &err));
+
+                // SystemExit → exit with the original code, None is a clean exit
+                let is_sys_exit = err
+                    .value(py)
+                    .get_type()
+                    .name()
+                    .map(|n| n == "SystemExit")
+                    .unwrap_or(false);
+                if is_sys_exit {
+                    match err.value(py).getattr("code") {
+                        Ok(code) if code.is_none() => 0,
+                        Ok(code) => code.extract::<i32>().unwrap_or(1),
+                        Err(_) => 1,
+                    }
+                } else if err.is_instance_of::<PyKeyboardInterrupt>(py) {
+                    if !timed_out {
+                        error!("'{}' was interrupted.", command_path);
+                        // Don't leave the integrations' processes running
+                        process::terminate_children(timeout::CHILD_GRACE_PERIOD);
+                    }
+                    timeout::INTERRUPT_EXIT_CODE
+                } else {
+                    error!("Failed to execute Python command: {}", command_path);
+                    report_error(PythonErrorFormatter::new(err));
+                    56
+                }
+            }
+        };
+
+        if let Some(redirect) = redirect {
+            redirect.finish(py);
+        }
+        if let Some(previous_dir) = previous_dir {
+            if let Err(e) = std::env::set_current_dir(&previous_dir) {
+                warn!(
+                    "Unable to return to {} after '{}': {}",
+                    previous_dir.display(),
+                    command_path,
+                    e
+                );
+            }
+        }
+        if let Err(e) = task_env.restore(py) {
+            warn!(
+                "Unable to restore the environment after '{}': {}",
+                command_path, e
+            );
+        }
+
+        if timed_out {
+            let message = format!(
+                "'{}' timed out after {}.",
+                command_path,
+                timeout::format_duration(timeout.unwrap_or_default())
+            );
+            error!("{}", message);
+            outcome.exit_code = timeout::TIMEOUT_EXIT_CODE;
+            outcome.error = Some(TaskError {
+                kind: "TimeoutError".to_string(),
+                message,
+            });
+            return outcome;
+        }
+
+        if exit_code == 0 {
+            debug!("Successfully executed Python command: {}", command_path);
+            if let Some(fingerprint) = fingerprint.filter(|_| !dry_run::is_dry_run()) {
+                fingerprint.record();
+            }
+        }
+        outcome.success = exit_code == 0;
+        outcome.exit_code = exit_code;
+        outcome
+    })
+}
+
+/// Take a command's lock, waiting for it unless `no_wait` is set
+///
+/// Errors carry the exit code and message to report.
+fn acquire_lock(
+    py: Python<'_>,
+    name: &str,
+    command_path: &str,
+    no_wait: bool,
+) -> Result<RunLock, (i32, String)> {
+    let failed = |e: anyhow::Error| (1, format!("Unable to take lock '{}': {}", name, e));
+    let dir = locks::locks_dir().map_err(failed)?;
+
+    if no_wait {
+        return match locks::try_acquire(&dir, name, command_path).map_err(failed)? {
+            Some(lock) => Ok(lock),
+            None => Err((
+                locks::LOCKED_EXIT_CODE,
+                format!(
+                    "Lock '{}' is held by {}.",
+                    name,
+                    locks::describe(&locks::holder(&dir, name))
+                ),
+            )),
+        };
+    }
+
+    let lock = locks::acquire(
+        &dir,
+        name,
+        command_path,
+        |holder| {
+            warn!(
+                "Lock '{}' is held by {}, waiting for it to be released.",
+                name,
+                locks::describe(&holder)
+            )
+        },
+        || py.check_signals().is_err(),
+    )
+    .map_err(failed)?;
+    lock.ok_or_else(|| {
+        (
+            timeout::INTERRUPT_EXIT_CODE,
+            format!("Interrupted while waiting for lock '{}'.", name),
+        )
+    })
+}
+
+/// Move to the directory a command runs in, returning the one to go back to
+///
+/// Returns `None` when the command runs where angreal was invoked.
+fn change_dir(cwd: &TaskCwd) -> anyhow::Result<Option<PathBuf>> {
+    let relative = match cwd {
+        TaskCwd::Invocation => return Ok(None),
+        TaskCwd::Project => "",
+        TaskCwd::Path(path) => path.as_str(),
+    };
+    let root = utils::is_angreal_project()?
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| anyhow!("Unable to find the project root"))?;
+    let dir = root.join(relative);
+    if !dir.is_dir() {
+        bail!("{} is not a directory", dir.display());
+    }
+
+    let previous = std::env::current_dir()?;
+    debug!("Changing directory to {}", dir.display());
+    std::env::set_current_dir(&dir)?;
+    Ok(Some(previous))
+}
+
+/// Call a command's function, calling it again while it fails and it has
+/// retries left
+fn call_with_retries(
+    py: Python<'_>,
+    command: &AngrealCommand,
+    command_path: &str,
+    kwargs: &Bound<'_, PyDict>,
+) -> PyResult<Py<PyAny>> {
+    let options = &command.options;
+    let attempts = options.retries + 1;
+    let mut attempt = 1;
+    loop {
+        let result = timings::time("task", command_path, || {
+            command.func.call(py, (), Some(kwargs))
+        });
+        if attempt == attempts {
+            return result;
+        }
+
+        let failure = match &result {
+            Ok(value) => match return_code(py, value) {
+                0 => return result,
+                code => format!("exited with {}", code),
+            },
+            Err(err) if should_retry(py, err, options) => {
+                let error = TaskError::from_pyerr(py, err);
+                format!("raised {}: {}", error.kind, error.message)
+            }
+            Err(_) => return result,
+        };
+        warn!(
+            "Attempt {}/{} of '{}' failed ({}), retrying in {}.",
+            attempt,
+            attempts,
+            command_path,
+            failure,
+            timeout::format_duration(options.retry_delay)
+        );
+
+        // Sleep in python so that Ctrl-C and timeouts interrupt the wait
+        py.import("time")?
+            .call_method1("sleep", (options.retry_delay.as_secs_f64(),))?;
+        attempt += 1;
+    }
+}
+
+/// Whether an exception raised by a command should be retried
+fn should_retry(py: Python<'_>, err: &PyErr, options: &CommandOptions) -> bool {
+    if err.is_instance_of::<PyKeyboardInterrupt>(py) {
+        return false;
+    }
+    if err.is_instance_of::<PySystemExit>(py) {
+        let code = err.value(py).getattr("code").ok();
+        return code.is_some_and(|c| !c.is_none() && c.extract::<i32>().map_or(true, |c| c != 0));
+    }
+    match &options.retry_on {
+        Some(types) => err.matches(py, types.0.bind(py)).unwrap_or(false),
+        None => err.is_instance_of::<PyException>(py),
+    }
+}
+
+/// The exit code for a value returned by a command
+fn return_code(py: Python<'_>, value: &Py<PyAny>) -> i32 {
+    // Check bool before int — in Python, bool is a subtype of int
+    // (True == 1, False == 0), so extract::<i32> would match bools
+    // A structured result's `success` field decides next, and
+    // None or other → success
+    if let Ok(val) = value.extract::<bool>(py) {
+        i32::from(!val)
+    } else if let Ok(code) = value.extract::<i32>(py) {
+        code
+    } else {
+        output::result_to_json(value.bind(py))
+            .ok()
+            .and_then(|result| output::success_field(&result))
+            .map(|success| i32::from(!success))
+            .unwrap_or_default()
+    }
+}
+
+/// Describe the arguments of an invocation as sorted `name=repr` strings
+fn describe_args(kwargs: &Bound<'_, PyDict>) -> Vec<String> {
+    let mut args: Vec<String> = kwargs
+        .iter()
+        .map(|(name, value)| {
+            let value = value.repr().map(|r| r.to_string()).unwrap_or_default();
+            format!("{}={}", name, value)
+        })
+        .collect();
+    args.sort();
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::ExceptionTypes;
+
+    fn raise(py: Python<'_>, code: &str) -> PyErr {
+        py.run(&std::ffi::CString::new(code).unwrap(), None, None)
+            .unwrap_err()
+    }
+
+    #[test]
+    fn test_should_retry() {
+        Python::attach(|py| {
+            let any = CommandOptions::default();
+            let connection_only = CommandOptions {
+                retry_on: Some(ExceptionTypes(
+                    py.eval(c"(ConnectionError,)", None, None).unwrap().unbind(),
+                )),
+                ..Default::default()
+            };
+
+            let refused = raise(py, "raise ConnectionRefusedError()");
+            let value = raise(py, "raise ValueError()");
+            assert!(should_retry(py, &refused, &any));
+            assert!(should_retry(py, &value, &any));
+            assert!(should_retry(py, &refused, &connection_only));
+            assert!(!should_retry(py, &value, &connection_only));
+
+            assert!(!should_retry(
+                py,
+                &raise(py, "raise KeyboardInterrupt()"),
+                &any
+            ));
+            assert!(should_retry(py, &raise(py, "raise SystemExit(3)"), &any));
+            assert!(!should_retry(py, &raise(py, "raise SystemExit(0)"), &any));
+        });
+    }
+
+    #[test]
+    fn test_return_code() {
+        Python::attach(|py| {
+            let code = |expr: &str| {
+                let value = py
+                    .eval(&std::ffi::CString::new(expr).unwrap(), None, None)
+                    .unwrap()
+                    .unbind();
+                return_code(py, &value)
+            };
+            assert_eq!(code("None"), 0);
+            assert_eq!(code("True"), 0);
+            assert_eq!(code("False"), 1);
+            assert_eq!(code("3"), 3);
+            assert_eq!(code("{'success': False}"), 1);
+            assert_eq!(code("{'success': True, 'count': 2}"), 0);
+            assert_eq!(code("'done'"), 0);
+        });
+    }
+}
@@ -0,0 +1,96 @@
+//! Global dry-run mode
+//!
+//! When enabled via `angreal --dry-run ...` (or `ANGREAL_DRY_RUN=1`), the Rust
+//! integrations and template rendering report the action they would take
+//! instead of performing it. Tasks can check the mode with
+//! `angreal.is_dry_run()` to do the same.
+
+use std::ffi::OsStr;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Environment variable used to propagate dry-run mode to child processes
+pub const DRY_RUN_ENV: &str = "ANGREAL_DRY_RUN";
+
+/// Enable or disable dry-run mode for this process and its children
+pub fn set_dry_run(enabled: bool) {
+    DRY_RUN.store(enabled, Ordering::SeqCst);
+    if enabled {
+        std::env::set_var(DRY_RUN_ENV, "1");
+    }
+}
+
+/// Whether dry-run mode is active
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::SeqCst)
+        || std::env::var(DRY_RUN_ENV)
+            .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false)
+}
+
+/// Run `f` with dry-run mode off, for work that only touches scratch space
+pub fn suspended<T>(f: impl FnOnce() -> T) -> T {
+    let enabled = DRY_RUN.swap(false, Ordering::SeqCst);
+    let env = std::env::var_os(DRY_RUN_ENV);
+    std::env::remove_var(DRY_RUN_ENV);
+    let result = f();
+    DRY_RUN.store(enabled, Ordering::SeqCst);
+    if let Some(value) = env {
+        std::env::set_var(DRY_RUN_ENV, value);
+    }
+    result
+}
+
+/// Report an action that was skipped because of dry-run mode
+pub fn report(action: &str) {
+    eprintln!("[dry-run] {}", action);
+}
+
+/// Report a subprocess that was skipped because of dry-run mode
+pub fn report_command(cmd: &Command) {
+    let mut action = command_line(cmd);
+    if let Some(dir) = cmd.get_current_dir() {
+        action.push_str(&format!(" (in {})", dir.display()));
+    }
+    report(&action);
+}
+
+/// Render a command as a copy-pasteable shell line
+pub fn command_line(cmd: &Command) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(shell_quote)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Quote a single argument for display in a shell command line
+pub fn shell_quote(arg: &OsStr) -> String {
+    let arg = arg.to_string_lossy();
+    let is_plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@+,%".contains(c));
+    if is_plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_line_quotes_arguments() {
+        let mut cmd = Command::new("docker");
+        cmd.args(["compose", "-f", "my compose.yml", "up", "-d", "it's"]);
+        assert_eq!(
+            command_line(&cmd),
+            r"docker compose -f 'my compose.yml' up -d 'it'\''s'"
+        );
+    }
+}
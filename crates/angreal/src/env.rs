@@ -0,0 +1,343 @@
+//! Environment variables applied while a task runs
+//!
+//! Variables come from dotenv files and explicit declarations, either for the
+//! whole project in the `[tasks]` table of `.angreal/angreal.toml`:
+//!
+//! ```toml
+//! [tasks]
+//! env_files = [".env", ".env.local"]
+//!
+//! [tasks.env]
+//! RUST_LOG = "debug"
+//! DATABASE_URL = "postgres://localhost/{{ project_name }}"
+//! ```
+//!
+//! or for a single command with `@command(env_files=[...], env={...})`. They
+//! are set in `os.environ`, so both python code and the processes started by
+//! the integrations see them, and the previous values are restored once the
+//! task finishes.
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use log::debug;
+use pyo3::prelude::*;
+use std::fs;
+use std::path::Path;
+use tera::{Context, Tera};
+use toml::{Table, Value};
+
+use crate::task::CommandOptions;
+use crate::utils::is_angreal_project;
+
+/// The table of `angreal.toml` holding task settings, kept apart from the
+/// template answers at the top level
+pub const TASKS_KEY: &str = "tasks";
+
+/// Resolve the variables to set for a command, in the order they apply
+///
+/// Later entries win: project files, project `[tasks.env]`, command files, then
+/// command `env`. Values declared with `env` are rendered as templates
+/// against the project's `angreal.toml`.
+pub fn resolve(options: &CommandOptions) -> Result<Vec<(String, String)>> {
+    let angreal_dir = is_angreal_project()?;
+    let root = angreal_dir
+        .parent()
+        .ok_or_else(|| anyhow!("Unable to find the project root"))?;
+    let config = load_config(&angreal_dir.join("angreal.toml"))?;
+    resolve_in(root, &config, options)
+}
+
+/// Resolve variables for a project rooted at `root` with the given config
+pub fn resolve_in(
+    root: &Path,
+    config: &Table,
+    options: &CommandOptions,
+) -> Result<Vec<(String, String)>> {
+    let context = Context::from_serialize(config)?;
+    let tasks = tasks_config(config)?;
+    let mut vars = Vec::new();
+
+    if let Some(files) = tasks.get("env_files") {
+        let files = match files {
+            Value::String(file) => vec![file.clone()],
+            Value::Array(files) => files
+                .iter()
+                .map(|f| f.as_str().map(String::from))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| {
+                    anyhow!("tasks.env_files in angreal.toml must be a list of paths")
+                })?,
+            _ => bail!("tasks.env_files in angreal.toml must be a list of paths"),
+        };
+        vars.extend(load_env_files(root, &files)?);
+    }
+    if let Some(env) = tasks.get("env") {
+        let env = env
+            .as_table()
+            .ok_or_else(|| anyhow!("[tasks.env] in angreal.toml must be a table"))?;
+        for (name, value) in env {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            vars.push((name.clone(), interpolate(name, &value, &context)?));
+        }
+    }
+
+    vars.extend(load_env_files(root, &options.env_files)?);
+    for (name, value) in &options.env {
+        vars.push((name.clone(), interpolate(name, value, &context)?));
+    }
+    Ok(vars)
+}
+
+/// Variables set for a running task, restored with [`AppliedEnv::restore`]
+pub struct AppliedEnv {
+    previous: Vec<(String, Option<String>)>,
+}
+
+/// Set variables in `os.environ`, remembering the values they replace
+pub fn apply(py: Python<'_>, vars: &[(String, String)]) -> PyResult<AppliedEnv> {
+    let environ = py.import("os")?.getattr("environ")?;
+    let mut previous: Vec<(String, Option<String>)> = Vec::new();
+    for (name, value) in vars {
+        if !previous.iter().any(|(n, _)| n == name) {
+            let old = environ
+                .call_method1("get", (name,))?
+                .extract::<Option<String>>()?;
+            previous.push((name.clone(), old));
+        }
+        debug!("Setting {} for the task", name);
+        environ.set_item(name, value)?;
+    }
+    Ok(AppliedEnv { previous })
+}
+
+impl AppliedEnv {
+    /// Put back the values the task's variables replaced
+    pub fn restore(self, py: Python<'_>) -> PyResult<()> {
+        let environ = py.import("os")?.getattr("environ")?;
+        for (name, old) in self.previous.into_iter().rev() {
+            match old {
+                Some(value) => environ.set_item(&name, value)?,
+                None => {
+                    if environ.contains(&name)? {
+                        environ.del_item(&name)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The `[tasks]` table of `angreal.toml`, empty when there isn't one
+///
+/// A `tasks` key that isn't a table is a template answer, not settings.
+pub fn tasks_config(config: &Table) -> Result<Table> {
+    match config.get(TASKS_KEY) {
+        Some(Value::Table(tasks)) => Ok(tasks.clone()),
+        _ => Ok(Table::new()),
+    }
+}
+
+/// Read `angreal.toml`, a missing file is treated as empty
+pub fn load_config(path: &Path) -> Result<Table> {
+    if !path.exists() {
+        return Ok(Table::new());
+    }
+    fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .parse::<Table>()
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Load dotenv files relative to `root`, skipping those that don't exist
+fn load_env_files(root: &Path, files: &[String]) -> Result<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+    for file in files {
+        let path = root.join(file);
+        if !path.is_file() {
+            debug!("Skipping missing env file {}", path.display());
+            continue;
+        }
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        vars.extend(parse_dotenv(&contents).map_err(|e| anyhow!("{}: {}", file, e))?);
+    }
+    Ok(vars)
+}
+
+/// Render a declared value against the project context
+fn interpolate(name: &str, value: &str, context: &Context) -> Result<String> {
+    if !value.contains("{{") && !value.contains("{%") {
+        return Ok(value.to_string());
+    }
+    Tera::one_off(value, context, false).map_err(|e| anyhow!("Unable to render {}: {}", name, e))
+}
+
+/// Parse the contents of a dotenv file
+///
+/// Supports `KEY=value` lines with an optional `export ` prefix, comments,
+/// single quoted literal values and double quoted values with `\n`, `\"` and
+/// `\\` escapes.
+pub fn parse_dotenv(contents: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected KEY=value", number + 1))?;
+
+        let name = name.trim();
+        let valid_name = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+        if !valid_name {
+            return Err(format!("line {}: invalid name '{}'", number + 1, name));
+        }
+
+        let value = value.trim();
+        let value = if let Some(quoted) = value.strip_prefix('\'') {
+            let end = quoted
+                .find('\'')
+                .ok_or_else(|| format!("line {}: unterminated quote", number + 1))?;
+            quoted[..end].to_string()
+        } else if let Some(quoted) = value.strip_prefix('"') {
+            unescape(quoted).ok_or_else(|| format!("line {}: unterminated quote", number + 1))?
+        } else {
+            match value.find(" #") {
+                Some(comment) => value[..comment].trim_end().to_string(),
+                None => value.to_string(),
+            }
+        };
+        vars.push((name.to_string(), value));
+    }
+    Ok(vars)
+}
+
+/// Unescape a double quoted value up to its closing quote
+fn unescape(quoted: &str) -> Option<String> {
+    let mut value = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                other => value.push(other),
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_dotenv() {
+        let contents = r#"
+# database settings
+export DATABASE_URL=postgres://localhost/app
+EMPTY=
+PLAIN = value # trailing comment
+SINGLE='literal $HOME # not a comment'
+DOUBLE="line one\nline \"two\""
+"#;
+        let vars = parse_dotenv(contents).unwrap();
+        assert_eq!(
+            vars,
+            vec![
+                ("DATABASE_URL".into(), "postgres://localhost/app".into()),
+                ("EMPTY".into(), "".into()),
+                ("PLAIN".into(), "value".into()),
+                ("SINGLE".into(), "literal $HOME # not a comment".into()),
+                ("DOUBLE".into(), "line one\nline \"two\"".into()),
+            ]
+        );
+
+        assert!(parse_dotenv("NO_EQUALS").is_err());
+        assert!(parse_dotenv("1BAD=x").is_err());
+        assert!(parse_dotenv("OPEN=\"never closed").is_err());
+    }
+
+    #[test]
+    fn test_resolve_precedence_and_interpolation() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join(".env"),
+            "A=from-project-file\nB=from-project-file\n",
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("task.env"),
+            "B=from-task-file\nC=from-task-file\n",
+        )
+        .unwrap();
+
+        let config: Table = r#"
+project_name = "demo"
+env = "prod"
+
+[tasks]
+env_files = [".env", ".env.missing"]
+
+[tasks.env]
+A = "db-{{ project_name }}-{{ env }}"
+"#
+        .parse()
+        .unwrap();
+        let options = CommandOptions {
+            env_files: vec!["task.env".into()],
+            env: vec![("C".into(), "{{ project_name | upper }}".into())],
+            ..Default::default()
+        };
+
+        let vars = resolve_in(tmp.path(), &config, &options).unwrap();
+        let value = |name: &str| {
+            vars.iter()
+                .rev()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.clone())
+                .unwrap()
+        };
+        assert_eq!(value("A"), "db-demo-prod");
+        assert_eq!(value("B"), "from-task-file");
+        assert_eq!(value("C"), "DEMO");
+    }
+
+    #[test]
+    fn test_apply_and_restore() {
+        Python::attach(|py| {
+            let environ = py.import("os").unwrap().getattr("environ").unwrap();
+            environ.set_item("ANGREAL_ENV_TEST_KEEP", "before").unwrap();
+
+            let applied = apply(
+                py,
+                &[
+                    ("ANGREAL_ENV_TEST_KEEP".into(), "during".into()),
+                    ("ANGREAL_ENV_TEST_NEW".into(), "during".into()),
+                ],
+            )
+            .unwrap();
+            assert_eq!(std::env::var("ANGREAL_ENV_TEST_KEEP").unwrap(), "during");
+            assert_eq!(std::env::var("ANGREAL_ENV_TEST_NEW").unwrap(), "during");
+
+            applied.restore(py).unwrap();
+            assert_eq!(std::env::var("ANGREAL_ENV_TEST_KEEP").unwrap(), "before");
+            assert!(std::env::var("ANGREAL_ENV_TEST_NEW").is_err());
+        });
+    }
+}
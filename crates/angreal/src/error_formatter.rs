@@ -1,4 +1,4 @@
-use pyo3::types::PyTypeMethods;
+use pyo3::types::{PyTracebackMethods, PyTypeMethods};
 use pyo3::{PyErr, Python};
 use std::fmt;
 
@@ -38,9 +38,13 @@ impl PythonErrorFormatter {
             if let Some(traceback) = self.error.traceback(py) {
                 output.push_str("\nTraceback:\n");
 
-                // Just extract the traceback as a string
-                let tb_str = format!("  {}", traceback);
-                for line in tb_str.lines() {
+                // Render the frames with file names and line numbers, dropping
+                // python's own "Traceback (most recent call last):" header
+                let tb_str = traceback.format().unwrap_or_else(|_| traceback.to_string());
+                for line in tb_str
+                    .lines()
+                    .filter(|l| !l.starts_with("Traceback (most recent call last)"))
+                {
                     output.push_str(&format!("  {}\n", line));
                 }
             }
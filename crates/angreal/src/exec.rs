@@ -0,0 +1,244 @@
+//! Running arbitrary commands inside the project environment
+//!
+//! `angreal exec -- <cmd>` runs a command from the project root with the
+//! project's environment applied, whichever way the project provides it:
+//!
+//! - a Flox environment, when the project has a `.flox` directory
+//! - a virtual environment, `.venv` by default or the `venv` key in the
+//!   `[tasks]` table of `angreal.toml`
+//! - the variables declared with `env_files` and `[tasks.env]` in the `[tasks]`
+//!   table of `angreal.toml`
+//!
+//! Later sources win, so a variable from `[tasks.env]` overrides one set by Flox.
+
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use toml::Table;
+
+use crate::dry_run;
+use crate::env;
+use crate::integrations::flox::FloxEnvironment;
+use crate::integrations::uv::UvVirtualEnv;
+use crate::process;
+use crate::task::CommandOptions;
+use crate::utils::is_angreal_project;
+
+/// The environment commands run in
+#[derive(Debug, Default)]
+pub struct ProjectEnvironment {
+    pub root: PathBuf,
+    /// The virtual environment that was activated, if any
+    pub venv: Option<PathBuf>,
+    /// Whether a Flox environment was activated
+    pub flox: bool,
+    /// Variables to set, later entries win
+    pub vars: Vec<(String, String)>,
+}
+
+impl ProjectEnvironment {
+    /// Build the environment for the current project
+    pub fn load() -> Result<Self> {
+        let angreal_dir = is_angreal_project()?;
+        let root = angreal_dir
+            .parent()
+            .ok_or_else(|| anyhow!("Unable to find the project root"))?
+            .to_path_buf();
+        let config = env::load_config(&angreal_dir.join("angreal.toml"))?;
+
+        let mut project = Self {
+            root,
+            ..Default::default()
+        };
+
+        let flox = FloxEnvironment::new(&project.root);
+        if flox.exists() {
+            debug!(
+                "Activating the Flox environment in {}",
+                project.root.display()
+            );
+            let mut vars: Vec<_> = flox
+                .get_activation_env()
+                .context("Failed to activate the Flox environment")?
+                .into_iter()
+                .collect();
+            vars.sort();
+            project.vars.extend(vars);
+            project.flox = true;
+        }
+
+        if let Some(venv) = venv_path(&project.root, &config)? {
+            debug!("Activating the virtual environment {}", venv.display());
+            let path = project.var("PATH").or_else(|| std::env::var("PATH").ok());
+            project.vars.extend(venv_vars(&venv, path.as_deref())?);
+            project.venv = Some(venv);
+        }
+
+        project.vars.extend(env::resolve_in(
+            &project.root,
+            &config,
+            &CommandOptions::default(),
+        )?);
+        Ok(project)
+    }
+
+    /// The value a variable will have, if the environment sets it
+    pub fn var(&self, name: &str) -> Option<String> {
+        self.vars
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+    }
+
+    /// Build the process for a command line
+    ///
+    /// A single argument is run through the shell so that quoting, pipes and
+    /// `&&` work, several arguments are run directly.
+    pub fn command(&self, argv: &[String]) -> Command {
+        let mut cmd = match argv {
+            [line] if cfg!(windows) => {
+                let mut cmd = Command::new("cmd");
+                cmd.arg("/C").arg(line);
+                cmd
+            }
+            [line] => {
+                let mut cmd = Command::new("sh");
+                cmd.arg("-c").arg(line);
+                cmd
+            }
+            _ => {
+                let mut cmd = Command::new(&argv[0]);
+                cmd.args(&argv[1..]);
+                cmd
+            }
+        };
+        cmd.current_dir(&self.root);
+        if self.venv.is_some() {
+            cmd.env_remove("PYTHONHOME");
+        }
+        for (name, value) in &self.vars {
+            cmd.env(name, value);
+        }
+        cmd
+    }
+}
+
+/// Run a command in the project environment, returning its exit code
+pub fn run(argv: &[String]) -> Result<i32> {
+    if argv.is_empty() {
+        return Err(anyhow!("No command given to run"));
+    }
+    let project = ProjectEnvironment::load()?;
+    let mut cmd = project.command(argv);
+
+    if dry_run::is_dry_run() {
+        dry_run::report_command(&cmd);
+        return Ok(0);
+    }
+
+    let status = process::status(&mut cmd)
+        .with_context(|| format!("Failed to run {}", dry_run::command_line(&cmd)))?;
+    Ok(status.code().unwrap_or(1))
+}
+
+/// The project's virtual environment, if it has one
+///
+/// Uses `venv` from the `[tasks]` table of `angreal.toml` when set, otherwise
+/// `.venv` in the project root if it exists.
+fn venv_path(root: &Path, config: &Table) -> Result<Option<PathBuf>> {
+    match env::tasks_config(config)?.get("venv") {
+        Some(venv) => {
+            let venv = venv
+                .as_str()
+                .ok_or_else(|| anyhow!("tasks.venv in angreal.toml must be a path"))?;
+            let path = root.join(venv);
+            if !path.is_dir() {
+                return Err(anyhow!(
+                    "The virtual environment {} doesn't exist",
+                    path.display()
+                ));
+            }
+            Ok(Some(path))
+        }
+        None => {
+            let path = root.join(".venv");
+            let venv = UvVirtualEnv { path: path.clone() };
+            Ok(venv.python_executable().exists().then_some(path))
+        }
+    }
+}
+
+/// The variables that activate a virtual environment
+fn venv_vars(venv: &Path, path: Option<&str>) -> Result<Vec<(String, String)>> {
+    let info = UvVirtualEnv {
+        path: venv.to_path_buf(),
+    }
+    .get_activation_info()?;
+
+    let bin = PathBuf::from(&info.python_executable)
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| venv.join("bin"));
+    let mut paths = vec![bin];
+    if let Some(path) = path {
+        paths.extend(std::env::split_paths(&OsString::from(path)));
+    }
+    let path = std::env::join_paths(paths)?.to_string_lossy().to_string();
+
+    Ok(vec![
+        ("VIRTUAL_ENV".to_string(), info.venv_prefix),
+        ("PATH".to_string(), path),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_command_line_and_environment() {
+        let project = ProjectEnvironment {
+            root: PathBuf::from("/project"),
+            vars: vec![
+                ("A".to_string(), "first".to_string()),
+                ("A".to_string(), "second".to_string()),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(project.var("A").as_deref(), Some("second"));
+        assert_eq!(project.var("B"), None);
+
+        let cmd = project.command(&["pytest".to_string(), "-x".to_string()]);
+        assert_eq!(dry_run::command_line(&cmd), "pytest -x");
+        assert_eq!(cmd.get_current_dir(), Some(Path::new("/project")));
+
+        #[cfg(unix)]
+        {
+            let cmd = project.command(&["make test && echo ok".to_string()]);
+            assert_eq!(dry_run::command_line(&cmd), "sh -c 'make test && echo ok'");
+        }
+    }
+
+    #[test]
+    fn test_venv_path() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+        assert_eq!(venv_path(root, &Table::new()).unwrap(), None);
+
+        // A template answer named venv isn't mistaken for the setting
+        let answers: Table = "venv = \"env\"".parse().unwrap();
+        assert_eq!(venv_path(root, &answers).unwrap(), None);
+
+        let configured: Table = "[tasks]\nvenv = \"env\"".parse().unwrap();
+        assert!(venv_path(root, &configured).is_err());
+        std::fs::create_dir(root.join("env")).unwrap();
+        assert_eq!(
+            venv_path(root, &configured).unwrap(),
+            Some(root.join("env"))
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Fingerprints for incremental task execution
+//!
+//! A command declared with `inputs`/`outputs` is skipped when all of its
+//! outputs exist and the hash of its input files and arguments matches the
+//! one recorded after its last successful run. Patterns are globs relative to
+//! the project root, and fingerprints are kept in
+//! `.angreal/.cache/fingerprints.json`.
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, warn};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::task::CommandOptions;
+use crate::utils::{is_angreal_project, project_cache_dir};
+
+/// The fingerprint of a command invocation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fingerprint {
+    /// The dotted command path the fingerprint is recorded under
+    pub command_path: String,
+    /// Hex encoded SHA-256 of the inputs and arguments
+    pub hash: String,
+    /// Whether every output pattern matched at least one existing path
+    pub outputs_exist: bool,
+}
+
+impl Fingerprint {
+    /// Fingerprint a command in the current project
+    ///
+    /// `args` describes the arguments the command was invoked with, in a
+    /// stable order.
+    pub fn compute(command_path: &str, options: &CommandOptions, args: &[String]) -> Result<Self> {
+        let angreal_dir = is_angreal_project()?;
+        let root = angreal_dir
+            .parent()
+            .ok_or_else(|| anyhow!("Unable to find the project root"))?;
+        Self::compute_in(root, command_path, options, args)
+    }
+
+    /// Fingerprint a command with its patterns resolved against `root`
+    pub fn compute_in(
+        root: &Path,
+        command_path: &str,
+        options: &CommandOptions,
+        args: &[String],
+    ) -> Result<Self> {
+        let mut hasher = Sha256::new();
+        for pattern in &options.inputs {
+            hasher.update(pattern.as_bytes());
+            hasher.update([0]);
+        }
+        for file in expand(root, &options.inputs)? {
+            let contents =
+                fs::read(&file).with_context(|| format!("Failed to read {}", file.display()))?;
+            let relative = file.strip_prefix(root).unwrap_or(&file);
+            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update([0]);
+            hasher.update((contents.len() as u64).to_le_bytes());
+            hasher.update(&contents);
+        }
+        for arg in args {
+            hasher.update(arg.as_bytes());
+            hasher.update([0]);
+        }
+
+        let mut outputs_exist = true;
+        for pattern in &options.outputs {
+            if expand(root, std::slice::from_ref(pattern))?.is_empty() {
+                debug!("Output '{}' of {} is missing", pattern, command_path);
+                outputs_exist = false;
+            }
+        }
+
+        Ok(Self {
+            command_path: command_path.to_string(),
+            hash: format!("{:x}", hasher.finalize()),
+            outputs_exist,
+        })
+    }
+
+    /// Whether the command can be skipped
+    pub fn is_up_to_date(&self) -> bool {
+        self.outputs_exist
+            && fingerprints_file()
+                .map(|path| self.is_recorded_in(&path))
+                .unwrap_or(false)
+    }
+
+    /// Record the fingerprint after a successful run
+    ///
+    /// Failing to record never fails the task, problems are only logged.
+    pub fn record(&self) {
+        if let Err(e) = fingerprints_file().and_then(|path| self.record_in(&path)) {
+            warn!(
+                "Unable to record the fingerprint of {}: {}",
+                self.command_path, e
+            );
+        }
+    }
+
+    fn is_recorded_in(&self, path: &Path) -> bool {
+        load(path).get(&self.command_path) == Some(&self.hash)
+    }
+
+    fn record_in(&self, path: &Path) -> Result<()> {
+        let mut fingerprints = load(path);
+        fingerprints.insert(self.command_path.clone(), self.hash.clone());
+        fs::write(path, serde_json::to_string_pretty(&fingerprints)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Get the fingerprints file for the current project
+pub fn fingerprints_file() -> Result<PathBuf> {
+    Ok(project_cache_dir()?.join("fingerprints.json"))
+}
+
+/// Load recorded fingerprints, an unreadable file is treated as empty
+fn load(path: &Path) -> BTreeMap<String, String> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Expand glob patterns relative to `root` into a sorted list of files
+fn expand(root: &Path, patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for pattern in patterns {
+        let full = root.join(pattern);
+        let matches = glob::glob(&full.to_string_lossy())
+            .map_err(|e| anyhow!("Invalid pattern '{}': {}", pattern, e))?;
+        files.extend(matches.filter_map(|m| m.ok()).filter(|p| p.is_file()));
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn options(inputs: &[&str], outputs: &[&str]) -> CommandOptions {
+        CommandOptions {
+            inputs: inputs.iter().map(|s| s.to_string()).collect(),
+            outputs: outputs.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_tracks_inputs_and_args() {
+        let tmp = TempDir::new().unwrap();
+        fs::create_dir(tmp.path().join("src")).unwrap();
+        fs::write(tmp.path().join("src/lib.rs"), "fn a() {}").unwrap();
+        let opts = options(&["src/**/*.rs"], &[]);
+        let args = vec!["release=False".to_string()];
+
+        let first = Fingerprint::compute_in(tmp.path(), "build", &opts, &args).unwrap();
+        let again = Fingerprint::compute_in(tmp.path(), "build", &opts, &args).unwrap();
+        assert_eq!(first, again);
+
+        let other_args = vec!["release=True".to_string()];
+        let changed_args =
+            Fingerprint::compute_in(tmp.path(), "build", &opts, &other_args).unwrap();
+        assert_ne!(first.hash, changed_args.hash);
+
+        fs::write(tmp.path().join("src/lib.rs"), "fn b() {}").unwrap();
+        let changed_input = Fingerprint::compute_in(tmp.path(), "build", &opts, &args).unwrap();
+        assert_ne!(first.hash, changed_input.hash);
+
+        fs::write(tmp.path().join("src/main.rs"), "fn main() {}").unwrap();
+        let added_input = Fingerprint::compute_in(tmp.path(), "build", &opts, &args).unwrap();
+        assert_ne!(changed_input.hash, added_input.hash);
+    }
+
+    #[test]
+    fn test_outputs_must_exist() {
+        let tmp = TempDir::new().unwrap();
+        let opts = options(&[], &["dist/*.whl"]);
+
+        let missing = Fingerprint::compute_in(tmp.path(), "build", &opts, &[]).unwrap();
+        assert!(!missing.outputs_exist);
+
+        fs::create_dir(tmp.path().join("dist")).unwrap();
+        fs::write(tmp.path().join("dist/pkg-0.1-py3-none-any.whl"), "").unwrap();
+        let present = Fingerprint::compute_in(tmp.path(), "build", &opts, &[]).unwrap();
+        assert!(present.outputs_exist);
+    }
+
+    #[test]
+    fn test_record_and_compare() {
+        let tmp = TempDir::new().unwrap();
+        let file = tmp.path().join("fingerprints.json");
+        let fingerprint =
+            Fingerprint::compute_in(tmp.path(), "docs", &options(&[], &[]), &[]).unwrap();
+
+        assert!(!fingerprint.is_recorded_in(&file));
+        fingerprint.record_in(&file).unwrap();
+        assert!(fingerprint.is_recorded_in(&file));
+
+        let other = Fingerprint {
+            hash: "different".to_string(),
+            ..fingerprint.clone()
+        };
+        assert!(!other.is_recorded_in(&file));
+    }
+}
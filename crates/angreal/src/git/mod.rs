@@ -1,8 +1,9 @@
 //! Git operations for Angreal
-use git2::{FetchOptions, RemoteCallbacks, Repository};
+use anyhow::{anyhow, Context, Result};
+use git2::{FetchOptions, Oid, RemoteCallbacks, Repository};
 use git2_credentials::CredentialHandler;
 use log::info;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tempfile::tempdir;
 
 /// Check if a remote repository exists
@@ -204,3 +205,102 @@ pub fn git_pull_ff(path: &str) -> PathBuf {
 
     PathBuf::from(path)
 }
+
+/// Resolve a tag, branch or commit to the commit it names
+///
+/// Branches are looked up on `origin` too, since a fresh clone only has a
+/// local branch for the default one.
+pub fn git_resolve_ref(repo: &Repository, reference: &str) -> Result<Oid> {
+    let candidates = [
+        reference.to_string(),
+        format!("refs/tags/{}", reference),
+        format!("origin/{}", reference),
+    ];
+    candidates
+        .iter()
+        .find_map(|name| repo.revparse_single(name).ok()?.peel_to_commit().ok())
+        .map(|commit| commit.id())
+        .ok_or_else(|| anyhow!("'{}' isn't a tag, branch or commit", reference))
+}
+
+/// Check out a tag, branch or commit as a detached HEAD, returning its commit
+pub fn git_checkout_ref(path: &Path, reference: &str) -> Result<Oid> {
+    let repo =
+        Repository::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let oid = git_resolve_ref(&repo, reference)?;
+    checkout_detached(&repo, oid)?;
+    info!("Checked out {} at {}", reference, oid);
+    Ok(oid)
+}
+
+fn checkout_detached(repo: &Repository, oid: Oid) -> Result<()> {
+    let commit = repo.find_commit(oid)?;
+    repo.checkout_tree(
+        commit.as_object(),
+        Some(git2::build::CheckoutBuilder::default().force()),
+    )?;
+    repo.set_head_detached(oid)?;
+    Ok(())
+}
+
+/// Whether a reference names a branch on `origin` rather than a tag or commit
+pub fn git_is_branch(repo: &Repository, reference: &str) -> bool {
+    repo.find_reference(&format!("refs/tags/{}", reference))
+        .is_err()
+        && repo
+            .find_reference(&format!("refs/remotes/origin/{}", reference))
+            .is_ok()
+}
+
+/// Bring a pinned checkout up to date with the reference it's pinned to
+///
+/// A branch is fetched from `origin` and its latest commit checked out. Tags
+/// and commits never move, so the checkout is only checked to still be at the
+/// commit they name.
+pub fn git_refresh_ref(path: &Path, reference: &str) -> Result<Oid> {
+    let repo =
+        Repository::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    if !git_is_branch(&repo, reference) {
+        return git_verify_ref(path, reference);
+    }
+
+    let mut callbacks = RemoteCallbacks::new();
+    let git_config = git2::Config::open_default()?;
+    let mut handler = CredentialHandler::new(git_config);
+    callbacks.credentials(move |url, username, allowed| {
+        handler.try_next_credential(url, username, allowed)
+    });
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.remote_callbacks(callbacks);
+
+    let tracking = format!("refs/remotes/origin/{}", reference);
+    repo.find_remote("origin")?
+        .fetch(
+            &[format!("+refs/heads/{}:{}", reference, tracking)],
+            Some(&mut fetch_options),
+            None,
+        )
+        .with_context(|| format!("Failed to fetch {} from origin", reference))?;
+    let oid = repo.refname_to_id(&tracking)?;
+    checkout_detached(&repo, oid)?;
+    info!("Updated {} to {}", reference, oid);
+    Ok(oid)
+}
+
+/// Check that a checkout is still at the commit a reference names
+pub fn git_verify_ref(path: &Path, reference: &str) -> Result<Oid> {
+    let repo =
+        Repository::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let expected = git_resolve_ref(&repo, reference)?;
+    let head = repo.head()?.peel_to_commit()?.id();
+    if head != expected {
+        return Err(anyhow!(
+            "{} is checked out at {} but {} is {}",
+            path.display(),
+            head,
+            reference,
+            expected
+        ));
+    }
+    Ok(head)
+}
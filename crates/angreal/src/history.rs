@@ -0,0 +1,312 @@
+//! Task execution history
+//!
+//! Every task invocation is appended as a JSON line to
+//! `.angreal/.cache/history.jsonl`, which backs `angreal history` and
+//! `angreal rerun`.
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Local, SecondsFormat};
+use git2::Repository;
+use glob::Pattern;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::utils::project_cache_dir;
+
+/// The number of entries kept in the history file
+const MAX_ENTRIES: usize = 1000;
+
+/// A single recorded task invocation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Sequential id, used to address the entry with `angreal rerun`
+    pub id: u64,
+    /// The dotted command path, e.g. `docker.build`
+    pub command: String,
+    /// The full argument vector passed to angreal
+    pub args: Vec<String>,
+    /// The directory angreal was invoked from
+    pub cwd: String,
+    /// RFC 3339 start time
+    pub started_at: String,
+    pub duration_ms: u64,
+    pub exit_code: i32,
+    /// The commit checked out when the task ran, if in a git repository
+    pub git_head: Option<String>,
+}
+
+/// Where and when a task started, captured before it runs
+///
+/// A task may change directory or commit, so these are taken up front and
+/// passed to [`record`] once it has finished.
+#[derive(Debug, Clone)]
+pub struct Invocation {
+    pub started: DateTime<Local>,
+    pub cwd: PathBuf,
+    pub git_head: Option<String>,
+}
+
+/// Capture the time, directory and commit a task is starting from
+pub fn start() -> Invocation {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    Invocation {
+        started: Local::now(),
+        git_head: git_head(&cwd),
+        cwd,
+    }
+}
+
+/// Get the history file for the current project
+pub fn history_file() -> Result<PathBuf> {
+    Ok(project_cache_dir()?.join("history.jsonl"))
+}
+
+/// Record a finished task invocation in the project history
+///
+/// Failing to write history never fails the task, problems are only logged.
+pub fn record(
+    command: &str,
+    args: &[String],
+    invocation: &Invocation,
+    duration: Duration,
+    exit_code: i32,
+) {
+    let entry = HistoryEntry {
+        id: 0,
+        command: command.to_string(),
+        args: args.to_vec(),
+        cwd: invocation.cwd.display().to_string(),
+        started_at: invocation
+            .started
+            .to_rfc3339_opts(SecondsFormat::Secs, false),
+        duration_ms: duration.as_millis() as u64,
+        exit_code,
+        git_head: invocation.git_head.clone(),
+    };
+
+    match history_file().and_then(|path| append(&path, entry)) {
+        Ok(entry) => debug!("Recorded invocation #{} of {}", entry.id, command),
+        Err(e) => warn!("Unable to record task history: {}", e),
+    }
+}
+
+/// Append an entry to a history file, assigning it the next id
+pub fn append(path: &Path, mut entry: HistoryEntry) -> Result<HistoryEntry> {
+    let mut entries = load(path)?;
+    entry.id = entries.last().map(|e| e.id + 1).unwrap_or(1);
+
+    if entries.len() >= MAX_ENTRIES {
+        // Rewrite the file keeping only the most recent entries
+        entries.drain(..=entries.len() - MAX_ENTRIES);
+        entries.push(entry.clone());
+        let mut contents = String::new();
+        for e in &entries {
+            contents.push_str(&serde_json::to_string(e)?);
+            contents.push('\n');
+        }
+        fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+    } else {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    }
+
+    Ok(entry)
+}
+
+/// Load all entries from a history file, oldest first
+///
+/// Lines that can't be parsed are skipped.
+pub fn load(path: &Path) -> Result<Vec<HistoryEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Select the most recent `limit` entries, optionally restricted to commands
+/// matching a glob and to failed invocations, oldest first
+pub fn filter(
+    entries: &[HistoryEntry],
+    command: Option<&str>,
+    failed: bool,
+    limit: usize,
+) -> Result<Vec<HistoryEntry>> {
+    let pattern = command
+        .map(Pattern::new)
+        .transpose()
+        .map_err(|e| anyhow!("Invalid command pattern: {}", e))?;
+
+    let mut selected: Vec<HistoryEntry> = entries
+        .iter()
+        .rev()
+        .filter(|e| pattern.as_ref().is_none_or(|p| p.matches(&e.command)))
+        .filter(|e| !failed || e.exit_code != 0)
+        .take(limit)
+        .cloned()
+        .collect();
+    selected.reverse();
+    Ok(selected)
+}
+
+/// Find an entry by id, or the most recent one
+pub fn find(entries: &[HistoryEntry], id: Option<u64>) -> Result<HistoryEntry> {
+    match id {
+        None => entries
+            .last()
+            .cloned()
+            .ok_or_else(|| anyhow!("No tasks have been run in this project yet.")),
+        Some(id) => match entries.iter().find(|e| e.id == id) {
+            Some(entry) => Ok(entry.clone()),
+            None => bail!("No history entry #{}.", id),
+        },
+    }
+}
+
+/// Print entries as a table
+pub fn print_history(entries: &[HistoryEntry]) {
+    if entries.is_empty() {
+        println!("No task history.");
+        return;
+    }
+
+    println!(
+        "{:>5}  {:<19}  {:>4}  {:>9}  {:<8}  COMMAND",
+        "ID", "STARTED", "EXIT", "DURATION", "HEAD"
+    );
+    for entry in entries {
+        let started = DateTime::parse_from_rfc3339(&entry.started_at)
+            .map(|t| {
+                t.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|_| entry.started_at.clone());
+        let head = entry
+            .git_head
+            .as_deref()
+            .map(|h| &h[..h.len().min(8)])
+            .unwrap_or("-");
+        println!(
+            "{:>5}  {:<19}  {:>4}  {:>9}  {:<8}  angreal {}",
+            entry.id,
+            started,
+            entry.exit_code,
+            format_duration(entry.duration_ms),
+            head,
+            command_line(&entry.args)
+        );
+    }
+}
+
+/// Render an argument vector as a shell command line
+pub fn command_line(args: &[String]) -> String {
+    args.iter()
+        .map(|a| crate::dry_run::shell_quote(a.as_ref()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn format_duration(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else if ms < 60_000 {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    } else {
+        format!("{}m{:02}s", ms / 60_000, (ms % 60_000) / 1000)
+    }
+}
+
+/// The commit id checked out in the repository containing `path`
+fn git_head(path: &Path) -> Option<String> {
+    let repo = Repository::discover(path).ok()?;
+    let head = repo.head().ok()?;
+    head.target().map(|oid| oid.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(command: &str, exit_code: i32) -> HistoryEntry {
+        HistoryEntry {
+            id: 0,
+            command: command.to_string(),
+            args: vec![command.to_string()],
+            cwd: "/tmp".to_string(),
+            started_at: "2024-01-01T00:00:00+00:00".to_string(),
+            duration_ms: 10,
+            exit_code,
+            git_head: None,
+        }
+    }
+
+    #[test]
+    fn test_append_assigns_sequential_ids() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("history.jsonl");
+
+        assert_eq!(append(&path, entry("build", 0)).unwrap().id, 1);
+        assert_eq!(append(&path, entry("test", 1)).unwrap().id, 2);
+
+        let entries = load(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].command, "test");
+        assert_eq!(find(&entries, None).unwrap().id, 2);
+        assert_eq!(find(&entries, Some(1)).unwrap().command, "build");
+        assert!(find(&entries, Some(3)).is_err());
+    }
+
+    #[test]
+    fn test_append_trims_old_entries() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("history.jsonl");
+
+        for _ in 0..MAX_ENTRIES + 5 {
+            append(&path, entry("build", 0)).unwrap();
+        }
+
+        let entries = load(&path).unwrap();
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert_eq!(entries.last().unwrap().id, (MAX_ENTRIES + 5) as u64);
+    }
+
+    #[test]
+    fn test_filter() {
+        let entries: Vec<HistoryEntry> = [
+            entry("docker.build", 0),
+            entry("docker.up", 1),
+            entry("test", 1),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut e)| {
+            e.id = i as u64 + 1;
+            e
+        })
+        .collect();
+
+        let docker = filter(&entries, Some("docker.*"), false, 10).unwrap();
+        assert_eq!(docker.len(), 2);
+
+        let failed = filter(&entries, None, true, 10).unwrap();
+        assert_eq!(failed.iter().map(|e| e.id).collect::<Vec<_>>(), vec![2, 3]);
+
+        let last = filter(&entries, None, false, 1).unwrap();
+        assert_eq!(last[0].id, 3);
+    }
+}
@@ -0,0 +1,199 @@
+//! Lifecycle hooks invoked around task execution
+//!
+//! Hooks are registered from python with `@angreal.before(...)`,
+//! `@angreal.after(...)` and `@angreal.on_error(...)`. Each hook carries a glob
+//! pattern that is matched against the dotted command path (e.g. `docker.build`)
+//! of the task being dispatched.
+
+use glob::Pattern;
+use log::{debug, warn};
+use once_cell::sync::Lazy;
+use pyo3::exceptions::PySystemExit;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::sync::Mutex;
+
+use crate::error_formatter::PythonErrorFormatter;
+use crate::timings::{self, SpanGuard};
+
+/// The point in a task's lifecycle that a hook runs at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HookKind {
+    /// Runs before the task, receives `(command_path, kwargs)`
+    Before,
+    /// Runs after the task returns, receives `(command_path, kwargs, result)`
+    After,
+    /// Runs when the task raises, receives `(command_path, kwargs, exception)`
+    OnError,
+}
+
+/// A registered hook
+pub struct Hook {
+    pub kind: HookKind,
+    pub pattern: Pattern,
+    pub func: Py<PyAny>,
+}
+
+impl Clone for Hook {
+    fn clone(&self) -> Self {
+        Python::attach(|py| Self {
+            kind: self.kind,
+            pattern: self.pattern.clone(),
+            func: self.func.clone_ref(py),
+        })
+    }
+}
+
+/// A long lived structure that stores hooks in the order they were registered
+pub static ANGREAL_HOOKS: Lazy<Mutex<Vec<Hook>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Register a hook for command paths matching `pattern`
+pub fn register_hook(kind: HookKind, pattern: &str, func: Py<PyAny>) -> Result<(), String> {
+    let pattern =
+        Pattern::new(pattern).map_err(|e| format!("Invalid hook pattern '{}': {}", pattern, e))?;
+    debug!("Registering {:?} hook for pattern '{}'", kind, pattern);
+    ANGREAL_HOOKS.lock().unwrap().push(Hook {
+        kind,
+        pattern,
+        func,
+    });
+    Ok(())
+}
+
+/// Get the hooks of a given kind that apply to a command path
+pub fn hooks_for(kind: HookKind, command_path: &str) -> Vec<Hook> {
+    ANGREAL_HOOKS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|h| h.kind == kind && h.pattern.matches(command_path))
+        .cloned()
+        .collect()
+}
+
+/// Run the `before` hooks for a command, stopping at the first one that raises
+///
+/// An exception from a `before` hook aborts the task and is handled by the
+/// caller exactly as if the task itself had raised.
+pub fn run_before(py: Python<'_>, command_path: &str, kwargs: &Bound<'_, PyDict>) -> PyResult<()> {
+    for hook in hooks_for(HookKind::Before, command_path) {
+        let _span = hook_span(py, &hook);
+        hook.func.call1(py, (command_path, kwargs.copy()?))?;
+    }
+    Ok(())
+}
+
+/// Run the `after` hooks for a command with the task's return value
+///
+/// Failures are reported but never change the outcome of the task.
+pub fn run_after(
+    py: Python<'_>,
+    command_path: &str,
+    kwargs: &Bound<'_, PyDict>,
+    result: &Py<PyAny>,
+) {
+    for hook in hooks_for(HookKind::After, command_path) {
+        let _span = hook_span(py, &hook);
+        let called = kwargs.copy().and_then(|kw| {
+            hook.func
+                .call1(py, (command_path, kw, result.clone_ref(py)))
+        });
+        if let Err(err) = called {
+            warn!("after hook for '{}' failed", command_path);
+            eprintln!("{}", PythonErrorFormatter::new(err));
+        }
+    }
+}
+
+/// Run the `on_error` hooks for a command with the exception it raised
+///
+/// Failures are reported but never change the outcome of the task. A task
+/// that exits cleanly with `sys.exit()` or `sys.exit(0)` hasn't failed, so
+/// the hooks aren't run for it.
+pub fn run_on_error(py: Python<'_>, command_path: &str, kwargs: &Bound<'_, PyDict>, err: &PyErr) {
+    if is_clean_exit(py, err) {
+        return;
+    }
+    for hook in hooks_for(HookKind::OnError, command_path) {
+        let _span = hook_span(py, &hook);
+        let called = kwargs.copy().and_then(|kw| {
+            hook.func
+                .call1(py, (command_path, kw, err.value(py).clone()))
+        });
+        if let Err(hook_err) = called {
+            warn!("on_error hook for '{}' failed", command_path);
+            eprintln!("{}", PythonErrorFormatter::new(hook_err));
+        }
+    }
+}
+
+/// Whether an error is a `SystemExit` with a code of 0 or None
+fn is_clean_exit(py: Python<'_>, err: &PyErr) -> bool {
+    if !err.is_instance_of::<PySystemExit>(py) {
+        return false;
+    }
+    match err.value(py).getattr("code") {
+        Ok(code) => code.is_none() || code.extract::<i64>().map(|c| c == 0).unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
+/// Time a hook call, named after the hook's function
+fn hook_span(py: Python<'_>, hook: &Hook) -> SpanGuard {
+    let name = hook
+        .func
+        .getattr(py, "__qualname__")
+        .and_then(|n| n.extract::<String>(py))
+        .unwrap_or_else(|_| hook.pattern.to_string());
+    let kind = match hook.kind {
+        HookKind::Before => "before",
+        HookKind::After => "after",
+        HookKind::OnError => "on_error",
+    };
+    timings::span("hook", format!("{} {}", kind, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hooks_match_command_path_globs() {
+        Python::attach(|py| {
+            register_hook(HookKind::Before, "hooktest.deploy*", py.None()).unwrap();
+            register_hook(HookKind::After, "hooktest.*", py.None()).unwrap();
+
+            assert_eq!(hooks_for(HookKind::Before, "hooktest.deploy").len(), 1);
+            assert_eq!(hooks_for(HookKind::Before, "hooktest.deploy-prod").len(), 1);
+            assert!(hooks_for(HookKind::Before, "hooktest.build").is_empty());
+            assert_eq!(hooks_for(HookKind::After, "hooktest.build").len(), 1);
+            assert!(hooks_for(HookKind::OnError, "hooktest.nothing-registered").is_empty());
+        });
+    }
+
+    #[test]
+    fn test_invalid_hook_pattern() {
+        Python::attach(|py| {
+            assert!(register_hook(HookKind::Before, "[", py.None()).is_err());
+        });
+    }
+
+    #[test]
+    fn test_before_hook_error_propagates() {
+        Python::attach(|py| {
+            let module = PyModule::from_code(
+                py,
+                c"def hook(path, kwargs):\n    raise RuntimeError('blocked ' + path)\n",
+                c"hook_test.py",
+                c"hook_test",
+            )
+            .unwrap();
+            let func = module.getattr("hook").unwrap().unbind();
+            register_hook(HookKind::Before, "hooktest.blocked", func).unwrap();
+
+            let kwargs = PyDict::new(py);
+            let err = run_before(py, "hooktest.blocked", &kwargs).unwrap_err();
+            assert!(err.to_string().contains("blocked hooktest.blocked"));
+        });
+    }
+}
@@ -1,20 +1,20 @@
 //! The angreal `init` command.
 //!
 use crate::{
-    git::{git_clone, git_pull_ff, remote_exists},
-    utils::{context_to_map, render_dir, repl_context_from_toml},
+    archive::{check_sha256, fetch_archive, is_archive},
+    git::{git_checkout_ref, git_clone, git_pull_ff, git_refresh_ref, remote_exists},
+    provenance::Provenance,
+    utils::{context_to_map, plan_dir, render_dir_into, repl_context_from_toml},
 };
 
+use anyhow::{anyhow, Context as _};
 use git_url_parse::{GitUrl, Scheme};
 use home::home_dir;
 
 use pyo3::prelude::*;
-use pyo3::types::PyModule;
 
 use std::{
-    env,
-    fs::{self, File},
-    io::Write,
+    env, fs,
     ops::Not,
     path::{Path, PathBuf},
     process::exit,
@@ -30,20 +30,218 @@ pub fn init(
     take_inputs: bool,
     values_file: Option<&str>,
     in_place: bool,
+    reference: Option<&str>,
+    diff: bool,
 ) {
+    let mut source = TemplateSource::parse(template).unwrap_or_else(|e| {
+        error!("{:#}", e);
+        exit(1);
+    });
+    match (reference, &source.reference) {
+        (Some(requested), Some(pinned)) if requested != pinned => {
+            error!(
+                "{} is pinned to {} but --ref asks for {}.",
+                source.location, pinned, requested
+            );
+            exit(1);
+        }
+        (Some(requested), _) => source.reference = Some(requested.to_string()),
+        (None, _) => {}
+    }
+    let template_path = resolve_template(&source);
+
+    // A dry run only shows what would be rendered, init() isn't run either
+    if crate::dry_run::is_dry_run() {
+        match preview_template(
+            &template_path,
+            take_inputs,
+            force,
+            values_file,
+            in_place,
+            diff,
+        ) {
+            Ok(preview) => print!("{}", preview),
+            Err(e) => {
+                error!("{:#}", e);
+                exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Err(e) = render_project(
+        &source,
+        &template_path,
+        take_inputs,
+        force,
+        values_file,
+        in_place,
+    ) {
+        error!("{:#}", e);
+        exit(1);
+    }
+
+    println!(
+        "Angreal template ({}) successfully rendered !",
+        template_path.to_string_lossy()
+    );
+}
+
+/// Where to find a template, as given to `angreal init`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TemplateSource {
+    /// A local path, git URL, `gh:org/repo`, template name or archive
+    pub location: String,
+    /// The tag, branch or commit a git template is pinned to
+    pub reference: Option<String>,
+    /// The template's directory within the repository
+    pub subdir: Option<String>,
+    /// The checksum an archive template must have
+    pub sha256: Option<String>,
+}
+
+impl TemplateSource {
+    /// Parse a template argument such as
+    /// `https://host/org/templates.git@v2#subdir=python-lib` or
+    /// `https://host/templates/python.tar.gz#sha256=<hex>`
+    pub fn parse(template: &str) -> anyhow::Result<Self> {
+        if Path::new(template).is_dir() {
+            return Ok(Self {
+                location: template.to_string(),
+                ..Default::default()
+            });
+        }
+        let (template_ref, fragment) = match template.split_once('#') {
+            Some((template_ref, fragment)) => (template_ref, Some(fragment)),
+            None => (template, None),
+        };
+        let (location, reference) = if is_archive(template_ref) {
+            (template_ref.to_string(), None)
+        } else {
+            split_ref(template_ref)
+        };
+        let mut source = Self {
+            location,
+            reference,
+            ..Default::default()
+        };
+        for option in fragment.into_iter().flat_map(|f| f.split('&')) {
+            match option.split_once('=') {
+                Some(("subdir", subdir)) => source.subdir = Some(check_subdir(subdir)?),
+                Some(("sha256", sha256)) => source.sha256 = Some(check_sha256(sha256)?),
+                _ => anyhow::bail!(
+                    "Unknown template option '{}' in {}, expected #subdir=<dir> or #sha256=<hex>",
+                    option,
+                    template
+                ),
+            }
+        }
+        if source.sha256.is_some() && !source.is_archive() {
+            anyhow::bail!(
+                "Only archive templates can be verified with #sha256=, {} isn't an archive",
+                source.location
+            );
+        }
+        Ok(source)
+    }
+
+    /// Whether the template is a `.tar.gz`, `.tgz` or `.zip` archive
+    pub fn is_archive(&self) -> bool {
+        is_archive(&self.location)
+    }
+}
+
+/// Check a template sub-directory stays inside its repository
+fn check_subdir(subdir: &str) -> anyhow::Result<String> {
+    let subdir = subdir.trim_matches('/');
+    let inside = Path::new(subdir)
+        .components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)));
+    if subdir.is_empty() || !inside {
+        anyhow::bail!("'{}' isn't a directory inside the template", subdir);
+    }
+    Ok(subdir.to_string())
+}
+
+/// Find a template on disk, cloning or updating it under `~/.angrealrc` as needed
+///
+/// Git templates can be pinned to a tag, branch or commit, archives are
+/// extracted into the cache, and a template can live in a sub-directory of its
+/// repository or archive.
+pub fn resolve_template(source: &TemplateSource) -> PathBuf {
+    let root = resolve_template_root(source);
+    let subdir = match &source.subdir {
+        Some(subdir) => subdir,
+        None => return root,
+    };
+    let template = root.join(subdir);
+    if template.join("angreal.toml").is_file().not() {
+        error!(
+            "There is no template in {} of {}, `angreal.toml` not found at {}.",
+            subdir,
+            source.location,
+            template.display()
+        );
+        exit(1);
+    }
+    template
+}
+
+/// Find the directory or repository holding a template
+fn resolve_template_root(source: &TemplateSource) -> PathBuf {
     let angreal_home = create_home_dot_angreal();
+    let template = expand_shorthand(&source.location);
+    let template = template.as_str();
+    let reference = source.reference.as_deref();
+
+    if source.is_archive() {
+        if let Some(reference) = reference {
+            error!(
+                "Only git templates can be pinned to a version, {} can't be checked out at {}.",
+                template, reference
+            );
+            exit(1);
+        }
+        return fetch_archive(template, source.sha256.as_deref(), &angreal_home).unwrap_or_else(
+            |e| {
+                error!("Unable to use the template archive: {:#}", e);
+                exit(1);
+            },
+        );
+    }
+
     let template_type = get_scheme(template).unwrap();
 
     debug!("Got template type {:?} for {:?}.", template_type, template);
 
     debug!("Template is of type {:?}", template_type.as_str());
-    let template = match template_type.as_str() {
+    match template_type.as_str() {
         "https" | "gitssh" | "ssh" | "git" => {
             // If we get a git url , go get it either by a clone if it doesn't
             // already exist, or as a ff pull if it does
-            handle_git_template(template, angreal_home)
+            handle_git_template(template, angreal_home, reference)
+        }
+        "file" if template.starts_with("file://") => {
+            handle_git_template(template, angreal_home, reference)
+        }
+        "file" => {
+            if let Some(reference) = reference {
+                // A template cloned into ~/.angrealrc can be pinned like its remote
+                if let Some((remote, cache)) = local_git_template(template, &angreal_home) {
+                    return handle_pinned_template(&remote, &cache, reference);
+                }
+                error!(
+                    "Only git templates can be pinned to a version, {} can't be checked out at {}.",
+                    template, reference
+                );
+                exit(1);
+            }
+            // A local collection of templates has no angreal.toml of its own
+            if source.subdir.is_some() && Path::new(template).is_dir() {
+                return PathBuf::from(template);
+            }
+            PathBuf::from(handle_file_template(template, &angreal_home))
         }
-        "file" => PathBuf::from(handle_file_template(template, &angreal_home)),
         &_ => {
             error!(
                 "Unhandled template type {} from {}, exiting.",
@@ -52,63 +250,33 @@ pub fn init(
             );
             exit(1);
         }
-    };
-
-    let rendered_dot_angreal_path = render_template(
-        Path::new(&template),
-        take_inputs,
-        force,
-        values_file,
-        in_place,
-    );
-
-    let mut rendered_angreal_init = Path::new(&rendered_dot_angreal_path).to_path_buf();
-    rendered_angreal_init.push("init.py");
-
-    if rendered_angreal_init.is_file() {
-        let init_contents = fs::read_to_string(rendered_angreal_init).unwrap();
-        // Get our init function
-        Python::attach(|py| {
-            // Change to the rendered directory before executing Python code
-            let current_dir = env::current_dir().unwrap();
-            if let Err(e) = env::set_current_dir(&rendered_dot_angreal_path) {
-                error!("Failed to change to rendered directory: {}", e);
-                exit(1);
-            }
-
-            use std::ffi::CString;
-            let init_cstr = CString::new(init_contents).unwrap();
-            let function: Py<PyAny> = PyModule::from_code(py, init_cstr.as_c_str(), c"", c"")
-                .unwrap()
-                .getattr("init")
-                .unwrap()
-                .unbind();
-
-            match function.call0(py) {
-                Ok(_) => debug!("Successfully executed init.py"),
-                Err(err) => {
-                    use crate::error_formatter::PythonErrorFormatter;
-                    error!("Failed to execute init.py");
-                    let formatter = PythonErrorFormatter::new(err);
-                    println!("{}", formatter);
-                    // Change back to original directory before exiting
-                    let _ = env::set_current_dir(current_dir);
-                    std::process::exit(1);
-                }
-            };
+    }
+}
 
-            // Change back to original directory after successful execution
-            if let Err(e) = env::set_current_dir(current_dir) {
-                error!("Failed to change back to original directory: {}", e);
-                exit(1);
-            }
-        });
+/// Split a `@ref` suffix off a template, e.g. `gh:org/template@v2.1.0`
+///
+/// A suffix containing `/` or `:` is part of the URL, such as the user in
+/// `git@github.com:org/template.git`, so refs with slashes need `--ref`.
+fn split_ref(template: &str) -> (String, Option<String>) {
+    match template.rsplit_once('@') {
+        Some((source, reference))
+            if !source.is_empty() && !reference.is_empty() && !reference.contains(['/', ':']) =>
+        {
+            (source.to_string(), Some(reference.to_string()))
+        }
+        _ => (template.to_string(), None),
     }
+}
 
-    println!(
-        "Angreal template ({}) successfully rendered !",
-        template.to_string_lossy()
-    );
+/// Expand `gh:org/repo` to the repository's GitHub URL
+pub fn expand_shorthand(template: &str) -> String {
+    match template.strip_prefix("gh:") {
+        Some(repo) => format!(
+            "https://github.com/{}.git",
+            repo.trim_end_matches('/').trim_end_matches(".git")
+        ),
+        None => template.to_string(),
+    }
 }
 
 /// get the schema for the provided template
@@ -138,7 +306,9 @@ fn handle_file_template(template: &str, angreal_home: &Path) -> String {
     let mut try_template = angreal_home.to_path_buf();
     try_template.push(Path::new(template));
 
-    if try_template.is_dir() {
+    // An absolute path replaces the cache directory when joined, only names
+    // relative to it are cached templates
+    if Path::new(template).is_relative() && try_template.is_dir() {
         let mut git_location = try_template.clone();
         git_location.push(Path::new(".git"));
 
@@ -228,7 +398,40 @@ fn handle_file_template(template: &str, angreal_home: &Path) -> String {
     }
 }
 
-fn handle_git_template(template: &str, angreal_home: PathBuf) -> PathBuf {
+/// The remote and cache directory of a local template that is a git repository
+///
+/// The directory is looked for the same way as by `handle_file_template`. It's
+/// pinned from its `origin` when it has one, so a pinned branch follows the
+/// upstream template rather than the local clone.
+fn local_git_template(template: &str, angreal_home: &Path) -> Option<(String, PathBuf)> {
+    let mut candidates = Vec::new();
+    if Path::new(template).is_relative() {
+        candidates.push(angreal_home.join(template));
+    }
+    candidates.push(PathBuf::from(template));
+    candidates.push(angreal_home.join("angreal").join(template));
+
+    let dir = candidates
+        .into_iter()
+        .find(|dir| dir.is_dir() && dir.join(".git").exists())?;
+    let dir = fs::canonicalize(&dir).ok()?;
+    let repo = git2::Repository::open(&dir).ok()?;
+    let remote = repo
+        .find_remote("origin")
+        .ok()
+        .and_then(|origin| origin.url().map(String::from))
+        .unwrap_or_else(|| dir.to_string_lossy().to_string());
+    // Pinned checkouts of templates outside the cache still go in it
+    let home = fs::canonicalize(angreal_home).unwrap_or_else(|_| angreal_home.to_path_buf());
+    let cache = if dir.starts_with(&home) {
+        dir
+    } else {
+        angreal_home.join(dir.file_name()?)
+    };
+    Some((remote, cache))
+}
+
+fn handle_git_template(template: &str, angreal_home: PathBuf, reference: Option<&str>) -> PathBuf {
     let remote = GitUrl::parse(template).expect("Failed to parse Git URL");
     // Compute destination path with the necessary adjustments
     let path = Path::new(&remote.path)
@@ -237,6 +440,10 @@ fn handle_git_template(template: &str, angreal_home: PathBuf) -> PathBuf {
         .with_extension("");
     let dst = angreal_home.join(path);
 
+    if let Some(reference) = reference {
+        return handle_pinned_template(template, &dst, reference);
+    }
+
     if dst.exists() {
         debug!("Template exists, attempting ff-pull at {:?}", dst);
         git_pull_ff(dst.to_str().unwrap());
@@ -248,6 +455,48 @@ fn handle_git_template(template: &str, angreal_home: PathBuf) -> PathBuf {
     dst
 }
 
+/// Check out a git template at a tag, branch or commit
+///
+/// Pinned checkouts live next to the template's usual cache directory, as
+/// `<name>@<ref>`. A branch is fetched each time it's used, so it picks up new
+/// commits, while tags and commits stay where they are.
+fn handle_pinned_template(template: &str, cache: &Path, reference: &str) -> PathBuf {
+    let name = cache.file_name().unwrap_or_default().to_string_lossy();
+    let dst = cache.with_file_name(format!("{}@{}", name, reference.replace('/', "_")));
+
+    if dst.exists() {
+        debug!(
+            "Pinned template exists at {:?}, bringing it up to {}",
+            dst, reference
+        );
+        if let Err(e) = git_refresh_ref(&dst, reference) {
+            error!(
+                "Refusing to use the cached template: {:#}. Remove {} to check out {} again.",
+                e,
+                dst.display(),
+                reference
+            );
+            exit(1);
+        }
+    } else {
+        debug!(
+            "Cloning {} to {:?} to check out {}",
+            template, dst, reference
+        );
+        git_clone(template, dst.to_str().unwrap());
+        if let Err(e) = git_checkout_ref(&dst, reference) {
+            let _ = fs::remove_dir_all(&dst);
+            error!(
+                "Unable to check out {} from {}: {:#}",
+                reference, template, e
+            );
+            exit(1);
+        }
+    }
+
+    dst
+}
+
 /// create the angreal caching directory for storing cloned templates
 pub fn create_home_dot_angreal() -> PathBuf {
     let mut home_dir = home_dir().unwrap();
@@ -260,6 +509,231 @@ pub fn create_home_dot_angreal() -> PathBuf {
     home_dir
 }
 
+/// Render a template into the current directory, all or nothing
+///
+/// The project is rendered into a staging directory inside the current
+/// directory and the template's `init()` runs there. Only once both succeed is
+/// it moved into place, otherwise the staging directory is removed and the
+/// current directory is left as it was.
+fn render_project(
+    source: &TemplateSource,
+    template_path: &Path,
+    take_input: bool,
+    force: bool,
+    values_file: Option<&str>,
+    in_place: bool,
+) -> anyhow::Result<()> {
+    let toml = template_toml(template_path)?;
+    let context = template_context(&toml, take_input, values_file);
+    let dst = env::current_dir()?;
+
+    // Staged next to its destination so moving it into place is a rename
+    let staging = tempfile::Builder::new()
+        .prefix(".angreal-init-")
+        .tempdir_in(&dst)
+        .context("Failed to create a staging directory")?;
+    debug!("Staging the project in {:?}", staging.path());
+
+    let dot_angreal = write_template(
+        template_path,
+        context,
+        &dst,
+        staging.path(),
+        force,
+        in_place,
+    )?;
+    if let Some(dot_angreal) = dot_angreal {
+        // Remember where the project came from so `angreal update` can find it
+        let provenance = Provenance::new(source, template_path);
+        if let Err(e) = provenance.write(&dot_angreal.join("angreal.toml")) {
+            error!("Failed to record the template in the project: {:#}", e);
+        }
+        run_init(&dot_angreal)?;
+    }
+
+    move_into_place(staging.path(), &dst)
+}
+
+/// Run the `init()` function from a rendered project's `.angreal/init.py`, if it has one
+fn run_init(dot_angreal: &Path) -> anyhow::Result<()> {
+    let init_py = dot_angreal.join("init.py");
+    if init_py.is_file().not() {
+        return Ok(());
+    }
+
+    Python::attach(|py| {
+        // Change to the rendered directory before executing Python code
+        let current_dir = env::current_dir()?;
+        env::set_current_dir(dot_angreal).context("Failed to change to rendered directory")?;
+
+        let result = crate::utils::import_python_file(py, &init_py)
+            .and_then(|module| module.getattr("init"))
+            .and_then(|function| function.call0());
+
+        // Change back to original directory whether init() worked or not
+        env::set_current_dir(current_dir).context("Failed to change back to original directory")?;
+
+        match result {
+            Ok(_) => {
+                debug!("Successfully executed init.py");
+                Ok(())
+            }
+            Err(err) => {
+                use crate::error_formatter::PythonErrorFormatter;
+                let formatter = PythonErrorFormatter::new(err);
+                println!("{}", formatter);
+                Err(anyhow!(
+                    "Failed to execute init.py, the project wasn't created"
+                ))
+            }
+        }
+    })
+}
+
+/// Move everything rendered in `staging` into `dst`
+///
+/// New files and directories are moved with a single rename each, so a project
+/// rendered into a directory that doesn't have it yet arrives with one rename.
+/// Directories that already exist, when rendering with `--in-place` or
+/// `--force`, are merged into. A file where the project has a directory, or
+/// the other way around, can't be moved over, so those are looked for before
+/// anything is moved. If a move still fails, the ones before it are undone.
+fn move_into_place(staging: &Path, dst: &Path) -> anyhow::Result<()> {
+    if let Some(conflict) = find_type_conflict(staging, dst)? {
+        return Err(anyhow!(
+            "{} exists and isn't a {}, not moving the project into place",
+            conflict.display(),
+            if conflict.is_dir() {
+                "file"
+            } else {
+                "directory"
+            }
+        ));
+    }
+    let mut mover = Mover::new(dst);
+    if let Err(e) = mover.move_entries(staging, dst) {
+        mover.undo();
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// The first path in `dst` that is a file where `staging` has a directory,
+/// or a directory where it has a file
+fn find_type_conflict(staging: &Path, dst: &Path) -> anyhow::Result<Option<PathBuf>> {
+    for entry in fs::read_dir(staging)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        let target_is_dir = match fs::symlink_metadata(&target) {
+            Ok(metadata) => metadata.is_dir(),
+            Err(_) => continue,
+        };
+        if entry.file_type()?.is_dir() {
+            if !target.is_dir() {
+                return Ok(Some(target));
+            }
+            if let Some(conflict) = find_type_conflict(&entry.path(), &target)? {
+                return Ok(Some(conflict));
+            }
+        } else if target_is_dir {
+            return Ok(Some(target));
+        }
+    }
+    Ok(None)
+}
+
+/// A rename done while moving a project into place
+struct Move {
+    from: PathBuf,
+    to: PathBuf,
+    /// Where the file it replaced was put aside
+    replaced: Option<PathBuf>,
+}
+
+/// Moves a staged project into place, remembering each move so they can be undone
+struct Mover {
+    root: PathBuf,
+    moves: Vec<Move>,
+    /// Holds the files replaced with `--force` until the project is in place
+    aside: Option<tempfile::TempDir>,
+}
+
+impl Mover {
+    fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            moves: Vec::new(),
+            aside: None,
+        }
+    }
+
+    fn move_entries(&mut self, staging: &Path, dst: &Path) -> anyhow::Result<()> {
+        for entry in fs::read_dir(staging)? {
+            let entry = entry?;
+            let target = dst.join(entry.file_name());
+            if entry.file_type()?.is_dir() && target.is_dir() {
+                self.move_entries(&entry.path(), &target)?;
+                continue;
+            }
+            let replaced = if fs::symlink_metadata(&target).is_ok() {
+                Some(self.put_aside(&target)?)
+            } else {
+                None
+            };
+            let moved = fs::rename(entry.path(), &target);
+            // If the rename failed, only the file put aside needs restoring
+            let from = match moved {
+                Ok(_) => entry.path(),
+                Err(_) => target.clone(),
+            };
+            self.moves.push(Move {
+                from,
+                to: target.clone(),
+                replaced,
+            });
+            moved.with_context(|| format!("Failed to move {} into place", target.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Move an existing file out of the way of the one replacing it
+    fn put_aside(&mut self, path: &Path) -> anyhow::Result<PathBuf> {
+        if self.aside.is_none() {
+            let dir = tempfile::Builder::new()
+                .prefix(".angreal-replaced-")
+                .tempdir_in(&self.root)
+                .context("Failed to create a directory for replaced files")?;
+            self.aside = Some(dir);
+        }
+        let aside = self
+            .aside
+            .as_ref()
+            .unwrap()
+            .path()
+            .join(self.moves.len().to_string());
+        fs::rename(path, &aside)
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+        Ok(aside)
+    }
+
+    /// Put everything back where it was before the project was moved
+    fn undo(&mut self) {
+        while let Some(done) = self.moves.pop() {
+            if done.to != done.from {
+                if let Err(e) = fs::rename(&done.to, &done.from) {
+                    error!("Failed to move {} back: {}", done.to.display(), e);
+                    continue;
+                }
+            }
+            if let Some(replaced) = done.replaced {
+                if let Err(e) = fs::rename(&replaced, &done.to) {
+                    error!("Failed to restore {}: {}", done.to.display(), e);
+                }
+            }
+        }
+    }
+}
+
 /// render the provided angreal template path
 pub fn render_template(
     path: &Path,
@@ -267,51 +741,254 @@ pub fn render_template(
     force: bool,
     values_file: Option<&str>,
     in_place: bool,
-) -> String {
-    // Verify the provided template path is minimially compliant.
-    let mut toml = path.to_path_buf();
-    toml.push(Path::new("angreal.toml"));
+) -> anyhow::Result<String> {
+    let toml = template_toml(path)?;
+    let context = template_context(&toml, take_input, values_file);
+    let dst = env::current_dir()?;
+    let dot_angreal = write_template(path, context, &dst, &dst, force, in_place)?;
+    Ok(dot_angreal
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default())
+}
+
+/// Render a template for `dst` into `target` and store the values it was
+/// rendered with, returning the rendered `.angreal` directory
+fn write_template(
+    path: &Path,
+    context: tera::Context,
+    dst: &Path,
+    target: &Path,
+    force: bool,
+    in_place: bool,
+) -> anyhow::Result<Option<PathBuf>> {
+    let ctx = context.clone();
+
+    // render the provided template directory
+    let rendered_files = render_dir_into(path, context, dst, target, force, in_place)?;
+
+    let toml_values = context_to_map(ctx);
+    let toml_string = toml::to_string(&Value::Table(toml_values))?;
+
+    let dot_angreal = match rendered_files.iter().find(|f| f.ends_with(".angreal")) {
+        Some(f) => PathBuf::from(f),
+        None => return Ok(None),
+    };
+    let value_path = dot_angreal.join("angreal.toml");
+    if crate::dry_run::is_dry_run() {
+        crate::dry_run::report(&format!(
+            "write initialization values to {}",
+            value_path.display()
+        ));
+        return Ok(Some(dot_angreal));
+    }
+    fs::write(&value_path, toml_string)
+        .with_context(|| format!("Failed to write {}", value_path.display()))?;
+    debug!("Storing initialization values to {}", &value_path.display());
+    Ok(Some(dot_angreal))
+}
+
+/// The template's `angreal.toml`, checking the template is minimally compliant
+fn template_toml(path: &Path) -> anyhow::Result<PathBuf> {
+    let toml = path.join("angreal.toml");
     debug!("angreal.toml should be at {:?}", toml);
     if toml.is_file().not() {
-        error!(
+        return Err(anyhow!(
             "`angreal.toml` not found where expected {:}",
             toml.display()
-        );
+        ));
     }
+    Ok(toml)
+}
 
+/// Describe what rendering a template into the current directory would do
+///
+/// The template is rendered in memory, nothing is written. Paths that already
+/// exist are flagged, and with `diff` files that would change are shown as a
+/// unified diff against what's there now.
+pub fn preview_template(
+    path: &Path,
+    take_input: bool,
+    force: bool,
+    values_file: Option<&str>,
+    in_place: bool,
+    diff: bool,
+) -> anyhow::Result<String> {
+    let toml = template_toml(path)?;
+    let context = template_context(&toml, take_input, values_file);
+    let dst = env::current_dir()?;
+    let plan = plan_dir(path, &context, &dst, in_place)?;
+
+    let mut preview = format!(
+        "Rendering {} into {} would write:\n",
+        path.display(),
+        dst.display()
+    );
+    preview.push_str(&plan.format_text(force, diff));
+    let blocked = !force && !plan.collisions().is_empty();
+    if let Some(init) = plan
+        .paths
+        .iter()
+        .find(|p| p.path.ends_with(".angreal/init.py"))
+        .filter(|_| !blocked)
+    {
+        preview.push_str(&format!(
+            "and then run init() from {}\n",
+            init.path.display()
+        ));
+    }
+    Ok(preview)
+}
+
+/// Build the template's context from its `angreal.toml`, or from a values file
+fn template_context(toml: &Path, take_input: bool, values_file: Option<&str>) -> tera::Context {
     // This is a replacement for a defensive check and is the closest thing to a ternary I've seen so far.
     // Evaluates to : if values file is None, do the first closure, other wise do the second closure
-    let context = values_file.map_or_else(
+    values_file.map_or_else(
         || repl_context_from_toml(toml.to_path_buf(), take_input),
         |file| repl_context_from_toml(Path::new(&file).to_path_buf(), false),
-    );
+    )
+}
 
-    // create a tera context from the toml file interactively.
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let ctx = context.clone();
+    #[test]
+    fn test_split_ref() {
+        assert_eq!(
+            split_ref("gh:org/template@v2.1.0"),
+            ("gh:org/template".to_string(), Some("v2.1.0".to_string()))
+        );
+        assert_eq!(
+            split_ref("https://github.com/org/template.git@main"),
+            (
+                "https://github.com/org/template.git".to_string(),
+                Some("main".to_string())
+            )
+        );
+        // The user in an ssh url isn't a ref
+        assert_eq!(
+            split_ref("git@github.com:org/template.git"),
+            ("git@github.com:org/template.git".to_string(), None)
+        );
+        assert_eq!(
+            split_ref("git@github.com:org/template.git@3f1c9a2"),
+            (
+                "git@github.com:org/template.git".to_string(),
+                Some("3f1c9a2".to_string())
+            )
+        );
+        assert_eq!(split_ref("python"), ("python".to_string(), None));
+    }
 
-    // render the provided template directory
-    let rendered_files = render_dir(path, context, &env::current_dir().unwrap(), force, in_place);
+    #[test]
+    fn test_parse_template_source() {
+        assert_eq!(
+            TemplateSource::parse("https://host/org/templates.git@v2#subdir=python-lib/").unwrap(),
+            TemplateSource {
+                location: "https://host/org/templates.git".to_string(),
+                reference: Some("v2".to_string()),
+                subdir: Some("python-lib".to_string()),
+                sha256: None,
+            }
+        );
+        assert_eq!(
+            TemplateSource::parse("gh:org/templates#subdir=python/lib").unwrap(),
+            TemplateSource {
+                location: "gh:org/templates".to_string(),
+                reference: None,
+                subdir: Some("python/lib".to_string()),
+                sha256: None,
+            }
+        );
+        let sha256 = "ab".repeat(32);
+        assert_eq!(
+            TemplateSource::parse(&format!(
+                "https://user@host/python@2.tar.gz#sha256={}&subdir=lib",
+                sha256.to_uppercase()
+            ))
+            .unwrap(),
+            TemplateSource {
+                location: "https://user@host/python@2.tar.gz".to_string(),
+                reference: None,
+                subdir: Some("lib".to_string()),
+                sha256: Some(sha256.clone()),
+            }
+        );
+        assert!(TemplateSource::parse(&format!("gh:org/templates#sha256={}", sha256)).is_err());
+        assert!(TemplateSource::parse("gh:org/templates#subdir=../etc").is_err());
+        assert!(TemplateSource::parse("gh:org/templates#subdir=").is_err());
+        assert!(TemplateSource::parse("gh:org/templates#branch=main").is_err());
+    }
 
-    let toml_values = context_to_map(ctx);
-    let toml_string = toml::to_string(&Value::Table(toml_values)).unwrap();
-
-    for f in rendered_files {
-        if f.ends_with(".angreal") {
-            let mut value_path = PathBuf::new();
-            value_path.push(f.as_str());
-            value_path.push("angreal.toml");
-            let mut output = File::create(&value_path).unwrap();
-            write!(output, "{}", toml_string.as_str()).unwrap();
-            debug!("Storing initialization values to {}", &value_path.display());
-            return f;
-        }
+    #[test]
+    fn test_expand_shorthand() {
+        assert_eq!(
+            expand_shorthand("gh:angreal/python"),
+            "https://github.com/angreal/python.git"
+        );
+        assert_eq!(
+            expand_shorthand("gh:angreal/python.git"),
+            "https://github.com/angreal/python.git"
+        );
+        assert_eq!(expand_shorthand("python"), "python");
+    }
+
+    #[test]
+    fn test_move_into_place_checks_types_first() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let staging = tmp.path().join("staging");
+        let dst = tmp.path().join("dst");
+        fs::create_dir_all(staging.join("a")).unwrap();
+        fs::create_dir_all(staging.join("z")).unwrap();
+        fs::write(staging.join("a/new.txt"), "new").unwrap();
+        fs::write(staging.join("z/new.txt"), "new").unwrap();
+        fs::create_dir_all(dst.join("a")).unwrap();
+        fs::write(dst.join("z"), "a file where the template has a directory").unwrap();
+
+        let err = move_into_place(&staging, &dst).unwrap_err().to_string();
+        assert!(err.contains("isn't a directory"), "{}", err);
+        // Nothing was moved, even the entries that came before the conflict
+        assert!(!dst.join("a/new.txt").exists());
+        assert!(staging.join("a/new.txt").exists());
+
+        fs::remove_file(dst.join("z")).unwrap();
+        fs::create_dir_all(dst.join("a/new.txt")).unwrap();
+        let err = move_into_place(&staging, &dst).unwrap_err().to_string();
+        assert!(err.contains("isn't a file"), "{}", err);
+
+        fs::remove_dir(dst.join("a/new.txt")).unwrap();
+        move_into_place(&staging, &dst).unwrap();
+        assert_eq!(fs::read_to_string(dst.join("a/new.txt")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(dst.join("z/new.txt")).unwrap(), "new");
     }
 
-    // let mut output = File::create(&value_path).unwrap();
-    // write!(output, "{}", toml_string.as_str()).unwrap();
-    // debug!("Storing initialization values to {}", &value_path.display());
-    // angreal_path
-    String::new()
-    // return path to .angreal
+    #[test]
+    fn test_mover_undo_restores_both_sides() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let staging = tmp.path().join("staging");
+        let dst = tmp.path().join("dst");
+        fs::create_dir_all(staging.join("src")).unwrap();
+        fs::write(staging.join("README.md"), "new").unwrap();
+        fs::write(staging.join("src/lib.rs"), "new").unwrap();
+        fs::create_dir_all(dst.join("src")).unwrap();
+        fs::write(dst.join("README.md"), "old").unwrap();
+
+        let mut mover = Mover::new(&dst);
+        mover.move_entries(&staging, &dst).unwrap();
+        assert_eq!(fs::read_to_string(dst.join("README.md")).unwrap(), "new");
+        assert!(dst.join("src/lib.rs").is_file());
+
+        mover.undo();
+        assert_eq!(fs::read_to_string(dst.join("README.md")).unwrap(), "old");
+        assert!(!dst.join("src/lib.rs").exists());
+        assert_eq!(
+            fs::read_to_string(staging.join("README.md")).unwrap(),
+            "new"
+        );
+        assert_eq!(
+            fs::read_to_string(staging.join("src/lib.rs")).unwrap(),
+            "new"
+        );
+    }
 }
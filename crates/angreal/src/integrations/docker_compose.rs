@@ -3,6 +3,7 @@
 //! This module provides a high-level interface to Docker Compose commands,
 //! using subprocess execution to ensure compatibility with all Docker Compose versions.
 
+use crate::dry_run;
 use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -89,8 +90,8 @@ impl DockerCompose {
         self.project_name.as_deref()
     }
 
-    /// Execute a docker-compose command
-    fn execute_command(&self, args: &[&str]) -> Result<ComposeOutput> {
+    /// Build a docker-compose command
+    fn build_command(&self, args: &[&str]) -> Command {
         let mut cmd = if Command::new("docker")
             .args(["compose", "version"])
             .stdout(Stdio::null())
@@ -121,11 +122,16 @@ impl DockerCompose {
 
         // Set working directory
         cmd.current_dir(&self.working_dir);
+        cmd
+    }
+
+    /// Execute a docker-compose command
+    fn execute_command(&self, args: &[&str]) -> Result<ComposeOutput> {
+        let mut cmd = self.build_command(args);
 
         // Execute the command
-        let output = cmd
-            .output()
-            .context("Failed to execute docker-compose command")?;
+        let output =
+            crate::process::output(&mut cmd).context("Failed to execute docker-compose command")?;
 
         Ok(ComposeOutput {
             success: output.status.success(),
@@ -134,6 +140,21 @@ impl DockerCompose {
             stderr: String::from_utf8_lossy(&output.stderr).to_string(),
         })
     }
+
+    /// Execute a docker-compose command that changes state, or only report it
+    /// when running in dry-run mode
+    fn execute_mutating_command(&self, args: &[&str]) -> Result<ComposeOutput> {
+        if dry_run::is_dry_run() {
+            dry_run::report_command(&self.build_command(args));
+            return Ok(ComposeOutput {
+                success: true,
+                exit_code: 0,
+                stdout: String::new(),
+                stderr: String::new(),
+            });
+        }
+        self.execute_command(args)
+    }
 }
 
 /// High-level Docker Compose operations
@@ -163,7 +184,7 @@ impl DockerCompose {
             args.push(service);
         }
 
-        self.execute_command(&args)
+        self.execute_mutating_command(&args)
     }
 
     /// Stop and remove services (docker-compose down)
@@ -181,7 +202,7 @@ impl DockerCompose {
             args.push(timeout);
         }
 
-        self.execute_command(&args)
+        self.execute_mutating_command(&args)
     }
 
     /// Restart services (docker-compose restart)
@@ -198,7 +219,7 @@ impl DockerCompose {
             args.push(service);
         }
 
-        self.execute_command(&args)
+        self.execute_mutating_command(&args)
     }
 
     /// View service logs (docker-compose logs)
@@ -269,7 +290,7 @@ impl DockerCompose {
             args.push(service);
         }
 
-        self.execute_command(&args)
+        self.execute_mutating_command(&args)
     }
 
     /// Start services (docker-compose start)
@@ -278,7 +299,7 @@ impl DockerCompose {
         for service in services {
             args.push(service);
         }
-        self.execute_command(&args)
+        self.execute_mutating_command(&args)
     }
 
     /// Stop services (docker-compose stop)
@@ -295,7 +316,7 @@ impl DockerCompose {
             args.push(service);
         }
 
-        self.execute_command(&args)
+        self.execute_mutating_command(&args)
     }
 
     /// Execute a command in a service container (docker-compose exec)
@@ -346,7 +367,7 @@ impl DockerCompose {
         for service in services {
             args.push(service);
         }
-        self.execute_command(&args)
+        self.execute_mutating_command(&args)
     }
 
     /// Validate and view the compose configuration (docker-compose config)
@@ -453,6 +474,7 @@ mod tests {
     use tempfile::TempDir;
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_docker_compose_available() {
         // This test will only pass if docker-compose is installed
         if DockerCompose::is_available() {
@@ -148,7 +148,7 @@ impl FloxEnvironment {
             cmd.arg(arg);
         }
 
-        cmd.output()
+        crate::process::output(&mut cmd)
             .context(format!("Failed to run '{}' in Flox environment", command))
     }
 
@@ -164,7 +164,7 @@ impl FloxEnvironment {
             cmd.arg(service);
         }
 
-        let output = cmd.output().context("Failed to start Flox services")?;
+        let output = crate::process::output(&mut cmd).context("Failed to start Flox services")?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -186,7 +186,7 @@ impl FloxEnvironment {
             cmd.arg(service);
         }
 
-        let output = cmd.output().context("Failed to stop Flox services")?;
+        let output = crate::process::output(&mut cmd).context("Failed to stop Flox services")?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -274,7 +274,7 @@ impl FloxEnvironment {
 
         cmd.arg(service);
 
-        let output = cmd.output().context("Failed to get Flox service logs")?;
+        let output = crate::process::output(&mut cmd).context("Failed to get Flox service logs")?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -293,7 +293,7 @@ impl FloxEnvironment {
             cmd.arg(service);
         }
 
-        let output = cmd.output().context("Failed to restart Flox services")?;
+        let output = crate::process::output(&mut cmd).context("Failed to restart Flox services")?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -1,8 +1,14 @@
+use crate::dry_run;
 use crate::git::git_pull_ff;
+use crate::timings;
 use anyhow::{bail, Context, Result};
 use git2::{Repository, Signature, StatusOptions};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Raw subcommands that only read the repository, still run under dry-run
+const READ_ONLY_SUBCOMMANDS: [&str; 5] = ["status", "log", "rev-parse", "diff", "show"];
 
 /// Git integration using git2/libgit2 for reliability and self-contained operation
 pub struct Git {
@@ -61,6 +67,11 @@ impl Git {
 
         Signature::now(&name, &email).context("Failed to create git signature")
     }
+
+    /// Report a git operation skipped by dry-run mode
+    fn report(&self, action: &str) {
+        dry_run::report(&format!("{} (in {})", action, self.working_dir.display()));
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -74,6 +85,11 @@ pub struct GitOutput {
 /// High-level git operations using git2
 impl Git {
     pub fn init(&self, bare: bool) -> Result<()> {
+        let _span = timings::span("integration", "git init");
+        if dry_run::is_dry_run() {
+            self.report(&format!("git init{}", if bare { " --bare" } else { "" }));
+            return Ok(());
+        }
         if bare {
             Repository::init_bare(&self.working_dir)?;
         } else {
@@ -83,6 +99,7 @@ impl Git {
     }
 
     pub fn clone(remote: &str, destination: Option<&Path>) -> Result<PathBuf> {
+        let _span = timings::span("integration", format!("git clone {}", remote));
         let dest_path = if let Some(dest) = destination {
             dest.to_path_buf()
         } else {
@@ -91,6 +108,11 @@ impl Git {
             PathBuf::from(repo_name)
         };
 
+        if dry_run::is_dry_run() {
+            dry_run::report(&format!("git clone {} {}", remote, dest_path.display()));
+            return Ok(dest_path);
+        }
+
         Repository::clone(remote, &dest_path)
             .with_context(|| format!("Failed to clone repository from {}", remote))?;
 
@@ -98,6 +120,11 @@ impl Git {
     }
 
     pub fn add(&self, paths: &[&str]) -> Result<()> {
+        let _span = timings::span("integration", "git add");
+        if dry_run::is_dry_run() {
+            self.report(&format!("git add {}", paths.join(" ")));
+            return Ok(());
+        }
         let repo = self.get_repo()?;
         let mut index = repo.index()?;
 
@@ -115,6 +142,15 @@ impl Git {
     }
 
     pub fn commit(&self, message: &str, all: bool) -> Result<()> {
+        let _span = timings::span("integration", "git commit");
+        if dry_run::is_dry_run() {
+            self.report(&format!(
+                "git commit{} -m {:?}",
+                if all { " -a" } else { "" },
+                message
+            ));
+            return Ok(());
+        }
         let repo = self.get_repo()?;
         let signature = self.get_signature()?;
         let mut index = repo.index()?;
@@ -157,25 +193,37 @@ impl Git {
     }
 
     pub fn push(&self, remote: Option<&str>, branch: Option<&str>) -> Result<()> {
-        let repo = self.get_repo()?;
+        let _span = timings::span("integration", "git push");
         let remote_name = remote.unwrap_or("origin");
-        let mut remote = repo.find_remote(remote_name)?;
-
         let branch_name = branch.unwrap_or("HEAD");
         let refspec = format!("refs/heads/{}:refs/heads/{}", branch_name, branch_name);
 
+        if dry_run::is_dry_run() {
+            self.report(&format!("git push {} {}", remote_name, refspec));
+            return Ok(());
+        }
+
+        let repo = self.get_repo()?;
+        let mut remote = repo.find_remote(remote_name)?;
+
         remote.push(&[&refspec], None)?;
         Ok(())
     }
 
     pub fn pull(&self, _remote: Option<&str>, _branch: Option<&str>) -> Result<()> {
+        let _span = timings::span("integration", "git pull");
         // Use the existing git_pull_ff implementation (fast-forward only)
         // Note: This ignores remote/branch parameters and uses "origin/main"
+        if dry_run::is_dry_run() {
+            self.report("git pull --ff-only origin main");
+            return Ok(());
+        }
         git_pull_ff(&self.working_dir.to_string_lossy());
         Ok(())
     }
 
     pub fn status(&self, short: bool) -> Result<String> {
+        let _span = timings::span("integration", "git status");
         let repo = self.get_repo()?;
 
         // Create status options with workaround for 32-bit systems
@@ -266,9 +314,18 @@ impl Git {
     }
 
     pub fn branch(&self, name: Option<&str>, delete: bool) -> Result<String> {
+        let _span = timings::span("integration", "git branch");
         let repo = self.get_repo()?;
 
         if let Some(branch_name) = name {
+            if dry_run::is_dry_run() {
+                self.report(&format!(
+                    "git branch{} {}",
+                    if delete { " -d" } else { "" },
+                    branch_name
+                ));
+                return Ok(String::new());
+            }
             if delete {
                 let mut branch = repo.find_branch(branch_name, git2::BranchType::Local)?;
                 branch.delete()?;
@@ -301,6 +358,16 @@ impl Git {
     }
 
     pub fn checkout(&self, branch: &str, create: bool) -> Result<()> {
+        let _span = timings::span("integration", "git checkout");
+        if dry_run::is_dry_run() {
+            self.report(&format!(
+                "git checkout{} {}",
+                if create { " -b" } else { "" },
+                branch
+            ));
+            return Ok(());
+        }
+
         let repo = self.get_repo()?;
 
         if create {
@@ -322,18 +389,36 @@ impl Git {
     }
 
     pub fn remote_add(&self, name: &str, url: &str) -> Result<()> {
+        let _span = timings::span("integration", "git remote add");
+        if dry_run::is_dry_run() {
+            self.report(&format!("git remote add {} {}", name, url));
+            return Ok(());
+        }
         let repo = self.get_repo()?;
         repo.remote(name, url)?;
         Ok(())
     }
 
     pub fn remote_remove(&self, name: &str) -> Result<()> {
+        let _span = timings::span("integration", "git remote remove");
+        if dry_run::is_dry_run() {
+            self.report(&format!("git remote remove {}", name));
+            return Ok(());
+        }
         let repo = self.get_repo()?;
         repo.remote_delete(name)?;
         Ok(())
     }
 
     pub fn tag(&self, name: &str, message: Option<&str>) -> Result<()> {
+        let _span = timings::span("integration", "git tag");
+        if dry_run::is_dry_run() {
+            match message {
+                Some(msg) => self.report(&format!("git tag -a {} -m {:?}", name, msg)),
+                None => self.report(&format!("git tag {}", name)),
+            }
+            return Ok(());
+        }
         let repo = self.get_repo()?;
         let head = repo.head()?;
         let commit = head.peel_to_commit()?;
@@ -350,7 +435,22 @@ impl Git {
 
     /// Compatibility method for subprocess-style interface
     /// This maps git commands to git2 operations for backwards compatibility
+    ///
+    /// Under dry-run only read-only subcommands run, anything else is reported.
     pub fn execute(&self, subcommand: &str, args: &[&str]) -> Result<GitOutput> {
+        if dry_run::is_dry_run() && !READ_ONLY_SUBCOMMANDS.contains(&subcommand) {
+            let mut cmd = Command::new("git");
+            cmd.arg(subcommand)
+                .args(args)
+                .current_dir(&self.working_dir);
+            dry_run::report_command(&cmd);
+            return Ok(GitOutput {
+                success: true,
+                exit_code: 0,
+                stdout: String::new(),
+                stderr: String::new(),
+            });
+        }
         match subcommand {
             "init" => {
                 let bare = args.contains(&"--bare");
@@ -506,6 +606,7 @@ mod tests {
     use tempfile::TempDir;
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_git_available() {
         // This test will only pass if git is installed
         if Git::is_available() {
@@ -551,6 +652,12 @@ mod tests {
         let status = git.status(true).unwrap();
         // Status should be empty or indicate clean working tree
         println!("Status: {}", status);
+
+        // Each operation is timed for --timings
+        let spans = timings::spans();
+        for name in ["git init", "git add", "git commit", "git status"] {
+            assert!(spans.iter().any(|s| s.name == name), "no span for {}", name);
+        }
     }
 
     #[test]
@@ -1,3 +1,4 @@
+use crate::dry_run;
 use anyhow::{bail, Context, Result};
 use pyo3::prelude::*;
 use std::path::{Path, PathBuf};
@@ -88,8 +89,6 @@ pub struct UvVirtualEnv {
 
 impl UvVirtualEnv {
     pub fn create(path: &Path, python_version: Option<&str>) -> Result<Self> {
-        UvIntegration::ensure_installed()?;
-
         let mut cmd = Command::new("uv");
         cmd.arg("venv").arg(path);
 
@@ -97,7 +96,16 @@ impl UvVirtualEnv {
             cmd.arg("--python").arg(version);
         }
 
-        let output = cmd.output().context("Failed to execute UV")?;
+        if dry_run::is_dry_run() {
+            dry_run::report_command(&cmd);
+            return Ok(Self {
+                path: path.to_path_buf(),
+            });
+        }
+
+        UvIntegration::ensure_installed()?;
+
+        let output = crate::process::output(&mut cmd).context("Failed to execute UV")?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -126,7 +134,13 @@ impl UvVirtualEnv {
             cmd.arg(package);
         }
 
-        let output = cmd.output().context("Failed to execute UV pip install")?;
+        if dry_run::is_dry_run() {
+            dry_run::report_command(&cmd);
+            return Ok(());
+        }
+
+        let output =
+            crate::process::output(&mut cmd).context("Failed to execute UV pip install")?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -139,15 +153,21 @@ impl UvVirtualEnv {
     pub fn install_requirements(&self, requirements_file: &Path) -> Result<()> {
         let python_path = self.python_executable();
 
-        let output = Command::new("uv")
-            .arg("pip")
+        let mut cmd = Command::new("uv");
+        cmd.arg("pip")
             .arg("install")
             .arg("--python")
             .arg(&python_path)
             .arg("-r")
-            .arg(requirements_file)
-            .output()
-            .context("Failed to execute UV pip install")?;
+            .arg(requirements_file);
+
+        if dry_run::is_dry_run() {
+            dry_run::report_command(&cmd);
+            return Ok(());
+        }
+
+        let output =
+            crate::process::output(&mut cmd).context("Failed to execute UV pip install")?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
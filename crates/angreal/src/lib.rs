@@ -10,40 +10,57 @@ extern crate version;
 #[macro_use]
 pub mod macros;
 
+pub mod archive;
 pub mod builder;
 pub mod completion;
+pub mod dispatch;
+pub mod dry_run;
+pub mod env;
 pub mod error_formatter;
+pub mod exec;
+pub mod fingerprint;
 pub mod git;
+pub mod history;
+pub mod hooks;
 pub mod init;
 pub mod integrations;
+pub mod locks;
 pub mod logger;
 pub mod mcp;
+pub mod output;
+pub mod picker;
+pub mod process;
+pub mod provenance;
 pub mod py_logger;
 pub mod python_bindings;
 pub mod task;
+pub mod template;
+pub mod timeout;
+pub mod timings;
+pub mod update;
 pub mod utils;
 pub mod validation;
+pub mod watch;
 
 use builder::{build_app, command_tree, tree_output};
-use error_formatter::PythonErrorFormatter;
+use clap::{App, ArgMatches};
 use integrations::uv::{UvIntegration, UvVirtualEnv};
 use task::ANGREAL_TASKS;
 
-use pyo3::types::{IntoPyDict, PyDict};
+use pyo3::types::PyDict;
 use std::ops::Not;
 use std::path::{Path, PathBuf};
 use std::vec::Vec;
 
 use std::process::exit;
 
-use pyo3::{prelude::*, wrap_pymodule, IntoPyObjectExt};
+use pyo3::{prelude::*, wrap_pymodule};
 use std::collections::HashMap;
 use std::fs;
 
 use log::{debug, error, warn};
 
 use crate::integrations::git::Git;
-use crate::task::{generate_command_path_key, generate_path_key_from_parts};
 
 #[pyclass]
 struct PyGit {
@@ -165,6 +182,12 @@ fn git_clone(remote: &str, destination: Option<&str>) -> PyResult<String> {
     Ok(dest.display().to_string())
 }
 
+/// Whether angreal was invoked with `--dry-run`
+#[pyfunction]
+fn is_dry_run() -> bool {
+    dry_run::is_dry_run()
+}
+
 #[pyfunction]
 fn ensure_uv_installed() -> PyResult<()> {
     UvIntegration::ensure_installed()
@@ -472,6 +495,7 @@ fn cleanup_entrypoints() -> PyResult<()> {
 /// The main function is just an entry point to be called from the core angreal library.
 #[pyfunction]
 fn main(py: Python<'_>) -> PyResult<()> {
+    timings::start();
     let handle = logger::init_logger();
     if std::env::var("ANGREAL_DEBUG").unwrap_or_default() == "true" {
         logger::update_verbosity(&handle, 2);
@@ -489,15 +513,18 @@ fn main(py: Python<'_>) -> PyResult<()> {
     // so no synthetic argv[0] is needed.
     let sys = py.import("sys")?;
     let py_argv: Vec<String> = sys.getattr("argv")?.extract()?;
-    let argvs: Vec<String> = py_argv.into_iter().skip(1).collect();
+    let mut argvs: Vec<String> = py_argv.into_iter().skip(1).collect();
 
     // Auto-install shell completion on first run (before other operations)
+    let completion_span = timings::span("startup", "install shell completion");
     if let Err(e) = completion::auto_install_completion() {
         warn!("Failed to auto-install shell completion: {}", e);
     }
 
+    drop(completion_span);
+
     debug!("Checking if binary is up to date...");
-    match utils::check_up_to_date() {
+    match timings::time("startup", "check for updates", utils::check_up_to_date) {
         Ok(()) => (),
         Err(e) => warn!(
             "An error occurred while checking if our binary is up to date. {}",
@@ -513,7 +540,9 @@ fn main(py: Python<'_>) -> PyResult<()> {
         debug!("Angreal project detected, loading found tasks.");
         let angreal_path = angreal_project_result.expect("Expected angreal project path");
         // get a list of files
-        let angreal_tasks_to_load = utils::get_task_files(angreal_path);
+        let angreal_tasks_to_load = timings::time("discover", "find task files", || {
+            utils::get_task_files(angreal_path)
+        });
 
         // Explicitly capture error with exit
         let _angreal_tasks_to_load = match angreal_tasks_to_load {
@@ -526,15 +555,31 @@ fn main(py: Python<'_>) -> PyResult<()> {
 
         // load the files , IF a file has command or task decorators - they'll register themselves now
         for task in _angreal_tasks_to_load.iter() {
+            let name = task
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let _span = timings::span("load", format!("load_python {}", name));
             if let Err(e) = utils::load_python(task.clone()) {
                 error!("Failed to load Python task: {}", e);
             }
         }
     }
 
-    let app = build_app(in_angreal_project);
+    // A bare `angreal` in a terminal offers the task picker instead of help
+    if argvs.is_empty() && in_angreal_project && picker::is_enabled() {
+        match picker::pick() {
+            Ok(Some(picked)) => argvs = picked,
+            Ok(None) => return Ok(()),
+            Err(e) => warn!("Unable to show the task picker: {}", e),
+        }
+    }
+
+    let app = timings::time("startup", "build cli", || build_app(in_angreal_project));
     let mut app_copy = app.clone();
-    let sub_command = app.get_matches_from(&argvs);
+    let sub_command = timings::time("startup", "parse arguments", || {
+        app.get_matches_from(&argvs)
+    });
 
     // Get our asked for verbosity and set the logger up. TODO: find a way to initialize earlier and reset after.
     let verbosity = sub_command.get_count("verbose");
@@ -545,7 +590,26 @@ fn main(py: Python<'_>) -> PyResult<()> {
         debug!("Log verbosity set to level: {}", verbosity);
     }
 
+    if sub_command.get_flag("dry_run") {
+        debug!("Dry-run mode enabled");
+        dry_run::set_dry_run(true);
+    }
+
+    if let Some(("init", init_matches)) = sub_command.subcommand() {
+        if init_matches.is_present("dry_run") {
+            dry_run::set_dry_run(true);
+        }
+    }
+
+    // A project command hiding a built-in is dispatched like any other task
+    let shadowed = builder::shadowed_builtins();
+    let is_task = match sub_command.subcommand() {
+        Some((name, _)) => shadowed.contains(name),
+        None => false,
+    };
+
     match sub_command.subcommand() {
+        Some(_) if is_task => run_task(&mut app_copy, &sub_command, &argvs, in_angreal_project),
         Some(("init", _sub_matches)) => init::init(
             _sub_matches.value_of("template").unwrap(),
             _sub_matches.is_present("force"),
@@ -556,6 +620,8 @@ fn main(py: Python<'_>) -> PyResult<()> {
                 None
             },
             _sub_matches.is_present("in_place"),
+            _sub_matches.value_of("ref"),
+            _sub_matches.is_present("diff"),
         ),
         Some(("_complete", _sub_matches)) => {
             // Hidden command for shell completion
@@ -692,177 +758,259 @@ fn main(py: Python<'_>) -> PyResult<()> {
 
             return Ok(());
         }
-        Some(("mcp", _)) => {
+        Some(("history", sub_matches)) => {
             if !in_angreal_project {
                 error!("This doesn't appear to be an angreal project.");
                 exit(1);
             }
 
-            mcp::serve();
+            let limit = match sub_matches.value_of("limit").unwrap().parse::<usize>() {
+                Ok(limit) => limit,
+                Err(_) => {
+                    error!("--limit must be a positive integer.");
+                    exit(1);
+                }
+            };
+            let entries = history::history_file()
+                .and_then(|path| history::load(&path))
+                .and_then(|entries| {
+                    history::filter(
+                        &entries,
+                        sub_matches.value_of("command"),
+                        sub_matches.get_flag("failed"),
+                        limit,
+                    )
+                });
+            match entries {
+                Ok(entries) => history::print_history(&entries),
+                Err(e) => {
+                    error!("Failed to read task history: {}", e);
+                    exit(1);
+                }
+            }
             return Ok(());
         }
-        Some((task, sub_m)) => {
+        Some(("rerun", sub_matches)) => {
             if !in_angreal_project {
                 error!("This doesn't appear to be an angreal project.");
-                exit(1)
+                exit(1);
             }
 
-            let mut command_groups: Vec<String> = Vec::new();
-            command_groups.push(task.to_string());
-
-            // iterate matches to get our final command and get our final arg matches
-            // object for applying down stream
-            let mut next = sub_m.subcommand();
-            let mut arg_matches = sub_m.clone();
-            while next.is_some() {
-                let cmd = next.unwrap();
-                command_groups.push(cmd.0.to_string());
-                next = cmd.1.subcommand();
-                arg_matches = cmd.1.clone();
+            let id = match sub_matches.value_of("id").map(|id| id.parse::<u64>()) {
+                None => None,
+                Some(Ok(id)) => Some(id),
+                Some(Err(_)) => {
+                    error!("The history id must be a positive integer.");
+                    exit(1);
+                }
+            };
+            let entry = match history::history_file()
+                .and_then(|path| history::load(&path))
+                .and_then(|entries| history::find(&entries, id))
+            {
+                Ok(entry) => entry,
+                Err(e) => {
+                    error!("{}", e);
+                    exit(1);
+                }
+            };
+
+            eprintln!(
+                "Re-running #{}: angreal {}",
+                entry.id,
+                history::command_line(&entry.args)
+            );
+
+            let app = build_app(true);
+            let mut app_copy = app.clone();
+            let replayed = match app.try_get_matches_from(&entry.args) {
+                Ok(matches) => matches,
+                Err(e) => {
+                    error!("Unable to replay #{}: {}", entry.id, e);
+                    exit(1);
+                }
+            };
+            if replayed.get_flag("dry_run") {
+                dry_run::set_dry_run(true);
             }
 
-            let task = command_groups.pop().unwrap();
+            // Run from the directory the original invocation was made in
+            if let Err(e) = std::env::set_current_dir(&entry.cwd) {
+                error!(
+                    "Unable to change to {}, where #{} was run: {}",
+                    entry.cwd, entry.id, e
+                );
+                exit(1);
+            }
 
-            // Generate the logical path key for command lookup
-            let command_path = generate_path_key_from_parts(&command_groups, &task);
-            let tasks_registry = ANGREAL_TASKS.lock().unwrap();
+            let exit_code = dispatch::dispatch(&mut app_copy, &replayed, &entry.args);
+            // --timings may be given to rerun or recorded with the invocation
+            if let Some(m) = [&sub_command, &replayed]
+                .into_iter()
+                .find(|m| m.is_present("timings"))
+            {
+                timings::finish(m.value_of("timings").map(Path::new));
+            }
+            if exit_code != 0 {
+                exit(exit_code);
+            }
+            return Ok(());
+        }
+        Some(("watch", sub_matches)) => {
+            if !in_angreal_project {
+                error!("This doesn't appear to be an angreal project.");
+                exit(1);
+            }
 
-            debug!("Looking up command with path: {}", command_path);
-            // Find the command by its logical path (registry keys include a
-            // unique suffix to prevent collisions during decoration).
-            let (registry_key, command) = match tasks_registry
-                .iter()
-                .find(|(_, cmd)| generate_command_path_key(cmd) == command_path)
+            let debounce = match timeout::parse_duration(sub_matches.value_of("debounce").unwrap())
             {
-                None => {
-                    error!("Command '{}' not found.", task);
-                    app_copy.print_help().unwrap_or(());
-                    exit(1)
+                Ok(debounce) => debounce,
+                Err(e) => {
+                    error!("{}", e);
+                    exit(1);
                 }
-                Some((key, found_command)) => (key.clone(), found_command),
             };
 
-            debug!(
-                "Executing command: {} (registry key: {})",
-                task, registry_key
-            );
+            // The command is parsed exactly as if it had been invoked directly
+            let argv: Vec<String> = sub_matches
+                .values_of("command")
+                .unwrap()
+                .map(String::from)
+                .collect();
+            let app = build_app(true);
+            let mut app_copy = app.clone();
+            let watched = app.try_get_matches_from(&argv).unwrap_or_else(|e| e.exit());
+            // Spans pile up across runs, so there's no single run to report on
+            if sub_command.is_present("timings") || watched.is_present("timings") {
+                error!("--timings can't be used with watch, time a single run instead.");
+                exit(1);
+            }
+            if watched.get_flag("dry_run") {
+                dry_run::set_dry_run(true);
+            }
 
-            let args = builder::select_args(&registry_key);
-            Python::attach(|py| {
-                debug!("Starting Python execution for command: {}", task);
-                let mut kwargs: Vec<(&str, Py<PyAny>)> = Vec::new();
-
-                for arg in args.into_iter() {
-                    let n = Box::leak(Box::new(arg.name));
-                    // unable to find the value of the passed arg with sub_m when its been wrapped
-                    // in a command group
-
-                    if arg.is_flag.unwrap() {
-                        let v = arg_matches.get_flag(&n.clone());
-                        kwargs.push((
-                            n.as_str(),
-                            v.into_bound_py_any(py)
-                                .expect("Failed to convert to Python object")
-                                .unbind(),
-                        ));
-                    } else {
-                        let v = arg_matches.value_of(n.clone());
-                        match v {
-                            None => {
-                                // We need to handle "boolean flags" that are present w/o a value
-                                // should probably test that the name is a "boolean type also"
-                                kwargs.push((
-                                    n.as_str(),
-                                    v.into_bound_py_any(py)
-                                        .expect("Failed to convert to Python object")
-                                        .unbind(),
-                                ));
-                            }
-                            Some(v) => match arg.python_type.unwrap().as_str() {
-                                "str" => kwargs.push((
-                                    n.as_str(),
-                                    v.into_bound_py_any(py)
-                                        .expect("Failed to convert to Python object")
-                                        .unbind(),
-                                )),
-                                "int" => kwargs.push((
-                                    n.as_str(),
-                                    v.parse::<i32>()
-                                        .unwrap()
-                                        .into_bound_py_any(py)
-                                        .expect("Failed to convert to Python object")
-                                        .unbind(),
-                                )),
-                                "float" => kwargs.push((
-                                    n.as_str(),
-                                    v.parse::<f32>()
-                                        .unwrap()
-                                        .into_bound_py_any(py)
-                                        .expect("Failed to convert to Python object")
-                                        .unbind(),
-                                )),
-                                _ => kwargs.push((
-                                    n.as_str(),
-                                    v.into_bound_py_any(py)
-                                        .expect("Failed to convert to Python object")
-                                        .unbind(),
-                                )),
-                            },
-                        }
+            let mut paths: Vec<String> = sub_matches
+                .values_of("paths")
+                .map(|values| values.map(String::from).collect())
+                .unwrap_or_default();
+            if paths.is_empty() {
+                if let Some((task, task_m)) = watched.subcommand() {
+                    let (command_path, _) = dispatch::resolve_task(task, task_m);
+                    if let Some((_, command)) = dispatch::find_command(&command_path) {
+                        paths = command.options.inputs;
                     }
                 }
+            }
+            let options = watch::WatchOptions {
+                paths,
+                ignore: sub_matches
+                    .values_of("ignore")
+                    .map(|values| values.map(String::from).collect())
+                    .unwrap_or_default(),
+                debounce,
+                clear: sub_matches.get_flag("clear"),
+            };
+
+            let root = match utils::is_angreal_project() {
+                Ok(angreal_dir) => angreal_dir
+                    .parent()
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default(),
+                Err(e) => {
+                    error!("{}", e);
+                    exit(1);
+                }
+            };
+            if let Err(e) = watch::watch(&root, &options, || {
+                dispatch::dispatch(&mut app_copy, &watched, &argv)
+            }) {
+                error!("{}", e);
+                exit(1);
+            }
+            return Ok(());
+        }
+        Some(("exec", exec_args)) => {
+            if !in_angreal_project {
+                error!("This doesn't appear to be an angreal project.");
+                exit(1);
+            }
+
+            let argv: Vec<String> = exec_args
+                .values_of("command")
+                .map(|values| values.map(String::from).collect())
+                .unwrap_or_default();
+            match exec::run(&argv) {
+                Ok(0) => return Ok(()),
+                Ok(code) => exit(code),
+                Err(e) => {
+                    error!("{:#}", e);
+                    exit(1);
+                }
+            }
+        }
+        Some(("locks", _)) => {
+            if !in_angreal_project {
+                error!("This doesn't appear to be an angreal project.");
+                exit(1);
+            }
+
+            match locks::locks_dir().and_then(|dir| locks::held(&dir)) {
+                Ok(held) => locks::print_locks(&held),
+                Err(e) => {
+                    error!("Failed to read locks: {}", e);
+                    exit(1);
+                }
+            }
+            return Ok(());
+        }
+        Some(("update", update_args)) => {
+            if !in_angreal_project {
+                error!("This doesn't appear to be an angreal project.");
+                exit(1);
+            }
 
-                let kwargs_dict = match kwargs.into_py_dict(py) {
-                    Ok(dict) => dict,
-                    Err(err) => {
-                        error!("Failed to convert kwargs to dict");
-                        let formatter = PythonErrorFormatter::new(err);
-                        println!("{}", formatter);
+            match update::update(update_args.value_of("ref")) {
+                Ok(report) => {
+                    report.print();
+                    if report.has_conflicts() {
                         exit(1);
                     }
-                };
-                let r_value = command.func.call(py, (), Some(&kwargs_dict));
-
-                match r_value {
-                    Ok(r_value) => {
-                        // Check bool before int — in Python, bool is a subtype of int
-                        // (True == 1, False == 0), so extract::<i32> would match bools
-                        if let Ok(val) = r_value.extract::<bool>(py) {
-                            if !val {
-                                exit(1);
-                            }
-                        } else if let Ok(code) = r_value.extract::<i32>(py) {
-                            if code != 0 {
-                                exit(code);
-                            }
-                        }
-                        // None, True, or other → success
-                        debug!("Successfully executed Python command: {}", task);
-                    }
-                    Err(err) => {
-                        // SystemExit → exit with the original code
-                        let is_sys_exit = err
-                            .value(py)
-                            .get_type()
-                            .name()
-                            .map(|n| n == "SystemExit")
-                            .unwrap_or(false);
-                        if is_sys_exit {
-                            let code = err
-                                .value(py)
-                                .getattr("code")
-                                .and_then(|c| c.extract::<i32>())
-                                .unwrap_or(1);
-                            exit(code);
-                        }
-                        error!("Failed to execute Python command: {}", task);
-                        let formatter = PythonErrorFormatter::new(err);
-                        println!("{}", formatter);
-                        exit(56);
-                    }
                 }
-            });
+                Err(e) => {
+                    error!("{:#}", e);
+                    exit(1);
+                }
+            }
+            return Ok(());
+        }
+        Some(("info", _)) => {
+            if !in_angreal_project {
+                error!("This doesn't appear to be an angreal project.");
+                exit(1);
+            }
+
+            match provenance::ProjectInfo::load() {
+                Ok(info) if sub_command.value_of("output") == Some("json") => {
+                    println!("{}", serde_json::to_string_pretty(&info).unwrap());
+                }
+                Ok(info) => print!("{}", info.format_text()),
+                Err(e) => {
+                    error!("{:#}", e);
+                    exit(1);
+                }
+            }
+            return Ok(());
         }
+        Some(("mcp", _)) => {
+            if !in_angreal_project {
+                error!("This doesn't appear to be an angreal project.");
+                exit(1);
+            }
+
+            mcp::serve();
+            return Ok(());
+        }
+        Some(_) => run_task(&mut app_copy, &sub_command, &argvs, in_angreal_project),
         _ => {
             println!("process for current context")
         }
@@ -872,6 +1020,27 @@ fn main(py: Python<'_>) -> PyResult<()> {
     Ok(())
 }
 
+/// Dispatch a project task from the parsed command line, exiting on failure
+fn run_task(
+    app: &mut App<'static>,
+    matches: &ArgMatches,
+    argv: &[String],
+    in_angreal_project: bool,
+) {
+    if !in_angreal_project {
+        error!("This doesn't appear to be an angreal project.");
+        exit(1)
+    }
+
+    let exit_code = dispatch::dispatch(app, matches, argv);
+    if matches.is_present("timings") {
+        timings::finish(matches.value_of("timings").map(Path::new));
+    }
+    if exit_code != 0 {
+        exit(exit_code);
+    }
+}
+
 /// Initialize Python bindings and load angreal tasks for external tools
 /// This function should be called by any external tool that needs to discover angreal commands
 pub fn initialize_python_tasks() -> Result<(), Box<dyn std::error::Error>> {
@@ -900,6 +1069,7 @@ pub fn initialize_python_tasks() -> Result<(), Box<dyn std::error::Error>> {
             py_logger::register();
 
             // Register core components
+            angreal_module.add_function(wrap_pyfunction!(is_dry_run, &angreal_module)?)?;
             task::register(py, &angreal_module)?;
             utils::register(py, &angreal_module)?;
             python_bindings::decorators::register_decorators(py, &angreal_module)?;
@@ -982,6 +1152,7 @@ fn angreal(m: &Bound<'_, PyModule>) -> PyResult<()> {
 
     py_logger::register();
     m.add_function(wrap_pyfunction!(main, m)?)?;
+    m.add_function(wrap_pyfunction!(is_dry_run, m)?)?;
     task::register(m.py(), m)?;
     utils::register(m.py(), m)?;
 
@@ -0,0 +1,265 @@
+//! Project-level run locks
+//!
+//! A command declared with `@command(lock="db")` holds an advisory lock on
+//! `.angreal/.cache/locks/db.lock` while it runs, so two invocations sharing
+//! a lock never run at the same time. The operating system releases the lock
+//! when the process exits, even if it crashes. The file records who holds the
+//! lock, which is what `angreal locks` shows.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::utils::project_cache_dir;
+
+/// Exit code reported when a lock is held and `--no-wait` was given
+pub const LOCKED_EXIT_CODE: i32 = 75;
+
+/// How often a waiting command checks whether the lock was released
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The process holding a lock
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    /// The dotted command path holding the lock
+    pub command: String,
+    /// RFC 3339 time the lock was taken
+    pub since: String,
+}
+
+/// A held lock, released when dropped
+#[derive(Debug)]
+pub struct RunLock {
+    pub name: String,
+    file: File,
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        let _ = self.file.set_len(0);
+        let _ = self.file.unlock();
+    }
+}
+
+/// Get the directory lock files are kept in for the current project
+pub fn locks_dir() -> Result<PathBuf> {
+    let dir = project_cache_dir()?.join("locks");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Take a lock if it is free, returning `None` if another process holds it
+pub fn try_acquire(dir: &Path, name: &str, command_path: &str) -> Result<Option<RunLock>> {
+    let path = lock_file(dir, name);
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => return Ok(None),
+        Err(TryLockError::Error(e)) => {
+            return Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+        }
+    }
+
+    let holder = LockHolder {
+        pid: std::process::id(),
+        command: command_path.to_string(),
+        since: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+    };
+    file.set_len(0)?;
+    file.rewind()?;
+    file.write_all(serde_json::to_string(&holder)?.as_bytes())?;
+    file.flush()?;
+
+    Ok(Some(RunLock {
+        name: name.to_string(),
+        file,
+    }))
+}
+
+/// Take a lock, waiting for it to be released if it is held
+///
+/// `interrupted` is called while waiting, returning true stops the wait and
+/// yields `None`. `on_wait` is called once with the current holder if the
+/// lock wasn't free straight away.
+pub fn acquire(
+    dir: &Path,
+    name: &str,
+    command_path: &str,
+    mut on_wait: impl FnMut(Option<LockHolder>),
+    mut interrupted: impl FnMut() -> bool,
+) -> Result<Option<RunLock>> {
+    let mut waiting = false;
+    loop {
+        if let Some(lock) = try_acquire(dir, name, command_path)? {
+            return Ok(Some(lock));
+        }
+        if !waiting {
+            on_wait(holder(dir, name));
+            waiting = true;
+        }
+        if interrupted() {
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// The process holding a lock, if it is held
+pub fn holder(dir: &Path, name: &str) -> Option<LockHolder> {
+    let mut contents = String::new();
+    File::open(lock_file(dir, name))
+        .ok()?
+        .read_to_string(&mut contents)
+        .ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// List the locks that are currently held, by name
+pub fn held(dir: &Path) -> Result<Vec<(String, Option<LockHolder>)>> {
+    let mut locks = Vec::new();
+    if !dir.is_dir() {
+        return Ok(locks);
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "lock") {
+            continue;
+        }
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let file = File::open(&path)?;
+        match file.try_lock_shared() {
+            Ok(()) => {
+                let _ = file.unlock();
+            }
+            Err(TryLockError::WouldBlock) => {
+                let holder = holder(dir, &name);
+                locks.push((name, holder));
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+    }
+    locks.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(locks)
+}
+
+/// Print held locks as a table
+pub fn print_locks(locks: &[(String, Option<LockHolder>)]) {
+    if locks.is_empty() {
+        println!("No locks are held.");
+        return;
+    }
+
+    println!("{:<20}  {:>7}  {:<19}  COMMAND", "LOCK", "PID", "SINCE");
+    for (name, holder) in locks {
+        match holder {
+            Some(holder) => println!(
+                "{:<20}  {:>7}  {:<19}  {}",
+                name,
+                holder.pid,
+                format_since(&holder.since),
+                holder.command
+            ),
+            None => println!("{:<20}  {:>7}  {:<19}  -", name, "?", "-"),
+        }
+    }
+}
+
+/// Describe a holder for messages
+pub fn describe(holder: &Option<LockHolder>) -> String {
+    match holder {
+        Some(holder) => format!(
+            "'{}' (pid {}) since {}",
+            holder.command,
+            holder.pid,
+            format_since(&holder.since)
+        ),
+        None => "another process".to_string(),
+    }
+}
+
+fn format_since(since: &str) -> String {
+    DateTime::parse_from_rfc3339(since)
+        .map(|t| {
+            t.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|_| since.to_string())
+}
+
+fn lock_file(dir: &Path, name: &str) -> PathBuf {
+    // Lock names are user supplied, keep them to a single safe file name
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-_.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    dir.join(format!("{}.lock", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lock_is_exclusive_until_dropped() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+
+        let lock = try_acquire(dir, "db", "db.migrate").unwrap().unwrap();
+        assert!(try_acquire(dir, "db", "db.seed").unwrap().is_none());
+        assert!(try_acquire(dir, "cache", "cache.clear").unwrap().is_some());
+
+        let held_locks = held(dir).unwrap();
+        assert_eq!(held_locks.len(), 1);
+        let holder = held_locks[0].1.clone().unwrap();
+        assert_eq!(held_locks[0].0, "db");
+        assert_eq!(holder.command, "db.migrate");
+        assert_eq!(holder.pid, std::process::id());
+
+        drop(lock);
+        assert!(held(dir).unwrap().is_empty());
+        assert!(try_acquire(dir, "db", "db.seed").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_acquire_stops_when_interrupted() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        let _lock = try_acquire(dir, "db", "db.migrate").unwrap().unwrap();
+
+        let mut waited_on = None;
+        let result = acquire(dir, "db", "db.seed", |h| waited_on = h, || true).unwrap();
+        assert!(result.is_none());
+        assert_eq!(waited_on.unwrap().command, "db.migrate");
+    }
+
+    #[test]
+    fn test_lock_names_are_sanitised() {
+        let dir = Path::new("/locks");
+        assert_eq!(lock_file(dir, "db"), dir.join("db.lock"));
+        assert_eq!(lock_file(dir, "../../etc/x"), dir.join(".._.._etc_x.lock"));
+    }
+}
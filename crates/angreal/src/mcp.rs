@@ -127,6 +127,7 @@ Before running ANY build, test, lint, docs, or deploy command:
 
 - `angreal <command>` — run a task
 - `angreal <command> --help` — get help for a specific task
+- `angreal --output json <command>` — run a task and get its outcome and result as JSON on stdout
 - `angreal tree` — list all available tasks
 "#,
         tree = tree
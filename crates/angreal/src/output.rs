@@ -0,0 +1,222 @@
+//! Structured task results
+//!
+//! With `angreal --output json <command>` the outcome of a task, including
+//! whatever it returned, is printed as a single JSON document on stdout so
+//! that scripts and agents don't have to parse human output.
+
+use log::error;
+use pyo3::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+
+/// How the outcome of a task is reported
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Whatever the task prints, the default
+    #[default]
+    Text,
+    /// A JSON document describing the outcome
+    Json,
+}
+
+impl OutputFormat {
+    /// Parse the value of `--output`
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "Unknown output format '{}', expected 'text' or 'json'",
+                other
+            )),
+        }
+    }
+}
+
+/// The outcome of running a task
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct TaskOutcome {
+    pub command: String,
+    pub success: bool,
+    pub exit_code: i32,
+    /// Whether the task was skipped because its inputs were unchanged
+    pub up_to_date: bool,
+    pub duration_ms: u64,
+    /// The value returned by the task, `null` if it returned nothing
+    pub result: Value,
+    pub error: Option<TaskError>,
+}
+
+/// An exception raised by a task
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TaskError {
+    /// The exception type, e.g. `ValueError`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub message: String,
+}
+
+impl TaskError {
+    pub fn from_pyerr(py: Python<'_>, err: &PyErr) -> Self {
+        Self {
+            kind: err
+                .get_type(py)
+                .name()
+                .map(|n| n.to_string())
+                .unwrap_or_else(|_| "Exception".to_string()),
+            message: err.value(py).to_string(),
+        }
+    }
+}
+
+impl TaskOutcome {
+    /// Print the outcome as JSON on stdout
+    pub fn print_json(&self) {
+        match serde_json::to_string(self) {
+            Ok(json) => println!("{}", json),
+            Err(e) => error!("Unable to serialise the task result: {}", e),
+        }
+    }
+}
+
+/// Stdout sent to stderr while a task runs, so only the JSON document reaches it
+///
+/// Python's `sys.stdout` is swapped for `sys.stderr`, and on unix file
+/// descriptor 1 is pointed at stderr too, so child processes started by the
+/// task, such as `subprocess.run` or the git and uv integrations, can't write
+/// to stdout either.
+pub struct StdoutRedirect {
+    previous: Option<Py<PyAny>>,
+    #[cfg(unix)]
+    saved_fd: Option<libc::c_int>,
+}
+
+impl StdoutRedirect {
+    /// Start sending stdout to stderr
+    pub fn start(py: Python<'_>) -> Self {
+        flush_stdout(py);
+        let previous = (|| {
+            let sys = py.import("sys").ok()?;
+            let previous = sys.getattr("stdout").ok()?;
+            sys.setattr("stdout", sys.getattr("stderr").ok()?).ok()?;
+            Some(previous.unbind())
+        })();
+        Self {
+            previous,
+            #[cfg(unix)]
+            saved_fd: unsafe {
+                let saved = libc::dup(libc::STDOUT_FILENO);
+                if saved >= 0 && libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) >= 0 {
+                    Some(saved)
+                } else {
+                    if saved >= 0 {
+                        libc::close(saved);
+                    }
+                    None
+                }
+            },
+        }
+    }
+
+    /// Put stdout back, flushing whatever the task wrote to stderr first
+    pub fn finish(self, py: Python<'_>) {
+        flush_stdout(py);
+        #[cfg(unix)]
+        if let Some(saved) = self.saved_fd {
+            unsafe {
+                libc::dup2(saved, libc::STDOUT_FILENO);
+                libc::close(saved);
+            }
+        }
+        if let (Some(previous), Ok(sys)) = (self.previous, py.import("sys")) {
+            let _ = sys.setattr("stdout", previous);
+        }
+    }
+}
+
+/// Flush everything buffered for stdout, by python and by us
+fn flush_stdout(py: Python<'_>) {
+    if let Ok(sys) = py.import("sys") {
+        for stream in ["stdout", "stderr"] {
+            if let Ok(stream) = sys.getattr(stream) {
+                let _ = stream.call_method0("flush");
+            }
+        }
+    }
+    let _ = std::io::Write::flush(&mut std::io::stdout());
+}
+
+/// Convert a task's return value to JSON
+///
+/// Dataclass instances are converted with `dataclasses.asdict`. Returns an
+/// error for values that can't be represented in JSON.
+pub fn result_to_json(value: &Bound<'_, PyAny>) -> PyResult<Value> {
+    let dataclasses = value.py().import("dataclasses")?;
+    let is_instance = !value.is_instance_of::<pyo3::types::PyType>()
+        && dataclasses
+            .call_method1("is_dataclass", (value,))?
+            .is_truthy()?;
+    let value = if is_instance {
+        dataclasses.call_method1("asdict", (value,))?
+    } else {
+        value.clone()
+    };
+    pythonize::depythonize::<Value>(&value)
+        .map_err(|e| pyo3::exceptions::PyTypeError::new_err(e.to_string()))
+}
+
+/// The `success` field of a structured result, if it has one
+pub fn success_field(result: &Value) -> Option<bool> {
+    result.get("success").and_then(Value::as_bool)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_result_to_json() {
+        Python::attach(|py| {
+            let module = PyModule::from_code(
+                py,
+                c"import dataclasses\n\
+@dataclasses.dataclass\n\
+class Report:\n    success: bool\n    files: list\n\
+report = Report(success=False, files=['a.py'])\n\
+mapping = {'count': 3, 'nested': {'ok': True}}\n",
+                c"output_test.py",
+                c"output_test",
+            )
+            .unwrap();
+
+            let report = result_to_json(&module.getattr("report").unwrap()).unwrap();
+            assert_eq!(report, json!({"success": false, "files": ["a.py"]}));
+            assert_eq!(success_field(&report), Some(false));
+
+            let mapping = result_to_json(&module.getattr("mapping").unwrap()).unwrap();
+            assert_eq!(mapping, json!({"count": 3, "nested": {"ok": true}}));
+            assert_eq!(success_field(&mapping), None);
+
+            // The class itself isn't a result
+            assert!(result_to_json(&module.getattr("Report").unwrap()).is_err());
+        });
+    }
+
+    #[test]
+    fn test_outcome_json_shape() {
+        let outcome = TaskOutcome {
+            command: "lint".to_string(),
+            exit_code: 56,
+            error: Some(TaskError {
+                kind: "ValueError".to_string(),
+                message: "bad".to_string(),
+            }),
+            ..Default::default()
+        };
+        let value = serde_json::to_value(&outcome).unwrap();
+        assert_eq!(value["error"]["type"], "ValueError");
+        assert_eq!(value["result"], Value::Null);
+        assert_eq!(value["success"], false);
+    }
+}
@@ -0,0 +1,247 @@
+//! Interactive task picker
+//!
+//! Running a bare `angreal` inside a project from a terminal opens a fuzzy
+//! finder over the project's tasks. The chosen task's required arguments are
+//! prompted for, and the equivalent command line is echoed so it can be
+//! copied next time. Without a terminal, or with `ANGREAL_NO_PICKER` set,
+//! angreal prints its help as before.
+
+use anyhow::Result;
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{FuzzySelect, Input};
+use std::ffi::OsStr;
+use std::io::IsTerminal;
+
+use crate::builder::command_tree::CommandNode;
+use crate::builder::select_args;
+use crate::builder::tree_output::format_arg_signature_pub;
+use crate::dry_run::shell_quote;
+use crate::task::{generate_path_key_from_parts, AngrealArg, ANGREAL_TASKS};
+
+/// Environment variable that turns the picker off
+pub const NO_PICKER_ENV: &str = "ANGREAL_NO_PICKER";
+
+/// A task that can be picked
+#[derive(Debug, Clone, PartialEq)]
+pub struct PickerEntry {
+    /// The words that invoke the task, e.g. `["docker", "build"]`
+    pub words: Vec<String>,
+    /// The key the task's arguments are registered under
+    pub registry_key: String,
+    pub about: Option<String>,
+    pub long_about: Option<String>,
+}
+
+impl PickerEntry {
+    /// The line shown in the picker
+    fn label(&self, args: &[AngrealArg]) -> String {
+        let mut label = self.words.join(" ");
+        if let Some(about) = &self.about {
+            label.push_str(&format!("  - {}", about));
+        }
+        let signature = format_arg_signature_pub(args);
+        if !signature.is_empty() {
+            label.push_str(&format!("  {}", signature));
+        }
+        label
+    }
+}
+
+/// Whether the picker should be shown instead of help
+pub fn is_enabled() -> bool {
+    std::env::var_os(NO_PICKER_ENV).is_none()
+        && std::io::stdin().is_terminal()
+        && std::io::stdout().is_terminal()
+}
+
+/// Flatten a command tree into pickable tasks, sorted by their words
+pub fn entries(root: &CommandNode) -> Vec<PickerEntry> {
+    let mut entries = Vec::new();
+    collect(root, &mut Vec::new(), &mut entries);
+    entries.sort_by(|a, b| a.words.cmp(&b.words));
+    entries
+}
+
+fn collect(node: &CommandNode, words: &mut Vec<String>, entries: &mut Vec<PickerEntry>) {
+    for child in node.children.values() {
+        words.push(child.name.clone());
+        if let Some(command) = &child.command {
+            let registry_key =
+                command
+                    .registry_key
+                    .clone()
+                    .unwrap_or_else(|| match &command.group {
+                        Some(groups) => generate_path_key_from_parts(groups, &command.name),
+                        None => command.name.clone(),
+                    });
+            entries.push(PickerEntry {
+                words: words.clone(),
+                registry_key,
+                about: command.about.clone(),
+                long_about: command.long_about.clone(),
+            });
+        }
+        collect(child, words, entries);
+        words.pop();
+    }
+}
+
+/// Build the argv that runs a task with the given argument values
+///
+/// `values` pairs each argument with what the user entered; arguments
+/// taking several values are split on whitespace.
+pub fn task_argv(entry: &PickerEntry, values: &[(AngrealArg, String)]) -> Vec<String> {
+    let mut argv = entry.words.clone();
+    for (arg, value) in values {
+        let parts: Vec<String> = if arg.multiple_values.unwrap_or(false) {
+            value.split_whitespace().map(String::from).collect()
+        } else {
+            vec![value.clone()]
+        };
+        match (&arg.long, arg.short) {
+            (Some(long), _) if arg.require_equals.unwrap_or(false) => {
+                argv.push(format!("--{}={}", long, parts.join(" ")));
+            }
+            (Some(long), _) => {
+                argv.push(format!("--{}", long));
+                argv.extend(parts);
+            }
+            (None, Some(short)) => {
+                argv.push(format!("-{}", short));
+                argv.extend(parts);
+            }
+            (None, None) => argv.extend(parts),
+        }
+    }
+    argv
+}
+
+/// Show the picker, returning the argv of the chosen task
+///
+/// Returns `None` if the user backed out with Esc.
+pub fn pick() -> Result<Option<Vec<String>>> {
+    let mut root = CommandNode::new_group("angreal".to_string(), None);
+    for (_, cmd) in ANGREAL_TASKS.lock().unwrap().iter() {
+        root.add_command(cmd.clone());
+    }
+    let entries = entries(&root);
+    if entries.is_empty() {
+        return Ok(None);
+    }
+
+    let theme = ColorfulTheme::default();
+    let labels: Vec<String> = entries
+        .iter()
+        .map(|e| e.label(&select_args(&e.registry_key)))
+        .collect();
+    let selected = FuzzySelect::with_theme(&theme)
+        .with_prompt("Run a task")
+        .items(&labels)
+        .default(0)
+        .interact_opt()?;
+    let entry = match selected {
+        Some(index) => &entries[index],
+        None => return Ok(None),
+    };
+
+    if let Some(long_about) = &entry.long_about {
+        eprintln!("\n{}\n", long_about.trim());
+    }
+
+    let mut values = Vec::new();
+    for arg in select_args(&entry.registry_key) {
+        if !arg.required.unwrap_or(false) || arg.is_flag.unwrap_or(false) {
+            continue;
+        }
+        let prompt = match &arg.help {
+            Some(help) => format!("{} ({})", arg.name, help),
+            None => arg.name.clone(),
+        };
+        let value: String = Input::with_theme(&theme)
+            .with_prompt(prompt)
+            .validate_with(|input: &String| -> Result<(), &str> {
+                if input.trim().is_empty() {
+                    Err("a value is required")
+                } else {
+                    Ok(())
+                }
+            })
+            .interact_text()?;
+        values.push((arg, value));
+    }
+
+    let argv = task_argv(entry, &values);
+    let line: Vec<String> = argv.iter().map(|a| shell_quote(OsStr::new(a))).collect();
+    eprintln!("Running: angreal {}", line.join(" "));
+    Ok(Some(argv))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arg(name: &str, long: Option<&str>, short: Option<char>) -> AngrealArg {
+        AngrealArg {
+            name: name.to_string(),
+            command_name: "build".to_string(),
+            command_path: "docker.build".to_string(),
+            is_flag: Some(false),
+            long: long.map(String::from),
+            short,
+            takes_value: Some(true),
+            default_value: None,
+            require_equals: None,
+            multiple_values: None,
+            number_of_values: None,
+            max_values: None,
+            min_values: None,
+            python_type: Some("str".to_string()),
+            long_help: None,
+            help: None,
+            required: Some(true),
+        }
+    }
+
+    #[test]
+    fn test_task_argv() {
+        let entry = PickerEntry {
+            words: vec!["docker".to_string(), "build".to_string()],
+            registry_key: "docker.build".to_string(),
+            about: Some("Build images".to_string()),
+            long_about: None,
+        };
+
+        let mut equals = arg("platform", Some("platform"), None);
+        equals.require_equals = Some(true);
+        let mut many = arg("services", None, None);
+        many.multiple_values = Some(true);
+
+        let argv = task_argv(
+            &entry,
+            &[
+                (arg("tag", Some("tag"), Some('t')), "v1 beta".to_string()),
+                (arg("file", None, Some('f')), "Dockerfile".to_string()),
+                (equals, "linux/amd64".to_string()),
+                (many, "web worker".to_string()),
+            ],
+        );
+        assert_eq!(
+            argv,
+            vec![
+                "docker",
+                "build",
+                "--tag",
+                "v1 beta",
+                "-f",
+                "Dockerfile",
+                "--platform=linux/amd64",
+                "web",
+                "worker"
+            ]
+        );
+        assert_eq!(
+            entry.label(&[arg("tag", Some("tag"), None)]),
+            "docker build  - Build images  [--tag=<str>]"
+        );
+    }
+}
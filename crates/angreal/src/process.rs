@@ -0,0 +1,154 @@
+//! Child processes spawned by the integrations
+//!
+//! Integrations run external tools (docker compose, uv, flox) through these
+//! helpers rather than `Command::output` directly so that the processes are
+//! tracked while they run and can be terminated when a task times out.
+
+use log::debug;
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::io;
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::dry_run::command_line;
+use crate::timings;
+
+/// Pids of the child processes currently running
+static CHILDREN: Lazy<Mutex<HashSet<u32>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Run a command to completion capturing its output, like `Command::output`
+pub fn output(cmd: &mut Command) -> io::Result<Output> {
+    let _span = timings::span("integration", command_line(cmd));
+    let child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let pid = child.id();
+    CHILDREN.lock().unwrap().insert(pid);
+    let result = child.wait_with_output();
+    CHILDREN.lock().unwrap().remove(&pid);
+    result
+}
+
+/// Run a command to completion with inherited stdio, like `Command::status`
+pub fn status(cmd: &mut Command) -> io::Result<ExitStatus> {
+    let _span = timings::span("integration", command_line(cmd));
+    let mut child = cmd.spawn()?;
+    let pid = child.id();
+    CHILDREN.lock().unwrap().insert(pid);
+    let result = child.wait();
+    CHILDREN.lock().unwrap().remove(&pid);
+    result
+}
+
+/// The number of tracked child processes still running
+pub fn running() -> usize {
+    CHILDREN.lock().unwrap().len()
+}
+
+/// Terminate every tracked child process
+///
+/// Processes are asked to stop first and killed if they are still running
+/// after `grace`.
+pub fn terminate_children(grace: Duration) {
+    let pids: Vec<u32> = CHILDREN.lock().unwrap().iter().copied().collect();
+    terminate(&pids, grace);
+}
+
+/// Pass a signal the task received on to every tracked child process
+///
+/// While an integration waits for a child, python can't raise
+/// `KeyboardInterrupt`, so the child has to stop before the task can.
+#[cfg(unix)]
+pub fn forward_signal(signum: libc::c_int) {
+    for pid in CHILDREN.lock().unwrap().iter() {
+        debug!("Forwarding signal {} to child process {}", signum, pid);
+        // SAFETY: sending a signal has no memory safety implications
+        unsafe {
+            libc::kill(*pid as libc::pid_t, signum);
+        }
+    }
+}
+
+fn terminate(pids: &[u32], grace: Duration) {
+    let still_running = |pid: &u32| CHILDREN.lock().unwrap().contains(pid);
+
+    for pid in pids {
+        debug!("Terminating child process {}", pid);
+        signal(*pid, false);
+    }
+
+    let step = Duration::from_millis(50);
+    let mut waited = Duration::ZERO;
+    while pids.iter().any(still_running) && waited < grace {
+        thread::sleep(step);
+        waited += step;
+    }
+
+    for pid in pids.iter().filter(|p| still_running(p)) {
+        debug!("Killing child process {}", pid);
+        signal(*pid, true);
+    }
+}
+
+#[cfg(unix)]
+fn signal(pid: u32, kill: bool) {
+    let sig = if kill { libc::SIGKILL } else { libc::SIGTERM };
+    // SAFETY: sending a signal has no memory safety implications
+    unsafe {
+        libc::kill(pid as libc::pid_t, sig);
+    }
+}
+
+#[cfg(windows)]
+fn signal(pid: u32, _kill: bool) {
+    // Windows has no graceful equivalent of SIGTERM for console processes
+    let _ = Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_untracks_finished_children() {
+        let mut cmd = Command::new("git");
+        cmd.arg("--version");
+        let output = output(&mut cmd).unwrap();
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("git version"));
+
+        // Other tests spawn children concurrently, so look for this child's
+        // pid rather than expecting running() to be zero
+        #[cfg(unix)]
+        {
+            let shell = super::output(Command::new("sh").args(["-c", "echo $$"])).unwrap();
+            let pid: u32 = String::from_utf8_lossy(&shell.stdout)
+                .trim()
+                .parse()
+                .unwrap();
+            assert!(!CHILDREN.lock().unwrap().contains(&pid));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id();
+        CHILDREN.lock().unwrap().insert(pid);
+
+        terminate(&[pid], Duration::from_millis(100));
+        CHILDREN.lock().unwrap().remove(&pid);
+
+        assert!(!child.wait().unwrap().success());
+    }
+}
@@ -0,0 +1,306 @@
+//! Where a project's template came from
+//!
+//! `angreal init` records the template it rendered in a `[_angreal]` table at
+//! the end of the project's `.angreal/angreal.toml`, which is how
+//! `angreal update` finds and re-renders it later:
+//!
+//! ```toml
+//! [_angreal]
+//! source = "https://github.com/angreal/python.git"
+//! ref = "v2.1.0"
+//! subdir = "python-lib"
+//! commit = "8f2c1e0d6a4b3c2f1e0d9c8b7a6f5e4d3c2b1a09"
+//! angreal_version = "2.8.0"
+//! rendered_at = "2024-05-02T10:12:03Z"
+//! ```
+//!
+//! The commit is only recorded for templates that live in a git repository,
+//! the ref only for templates pinned to a tag, branch or commit, the subdir
+//! only for templates in a sub-directory of their repository, and the sha256
+//! only for archives given with a checksum.
+//! `angreal info` shows the table, and tasks can read it with
+//! `angreal.get_context()["_angreal"]`.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{SecondsFormat, Utc};
+use git2::Repository;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+use crate::init::TemplateSource;
+use crate::utils::is_angreal_project;
+
+/// The table provenance is stored under
+pub const PROVENANCE_KEY: &str = "_angreal";
+
+/// The template a project was rendered from
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Provenance {
+    /// The template as given to `angreal init`, local paths made absolute
+    pub source: String,
+    /// The tag, branch or commit the template is pinned to
+    #[serde(rename = "ref")]
+    pub reference: Option<String>,
+    /// The template's directory within its repository
+    pub subdir: Option<String>,
+    /// The checksum an archive template was verified against
+    pub sha256: Option<String>,
+    /// The commit the template was at when it was rendered
+    pub commit: Option<String>,
+    /// The version of angreal that rendered it
+    pub angreal_version: Option<String>,
+    /// When it was last rendered, by `angreal init` or `angreal update`
+    pub rendered_at: Option<String>,
+}
+
+impl Provenance {
+    /// Describe a template given as `source` and found at `template`
+    pub fn new(source: &TemplateSource, template: &Path) -> Self {
+        let location = &source.location;
+        let location = if Path::new(location).is_dir() {
+            fs::canonicalize(location)
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_else(|_| location.to_string())
+        } else {
+            location.to_string()
+        };
+        Self {
+            source: location,
+            reference: source.reference.clone(),
+            subdir: source.subdir.clone(),
+            sha256: source.sha256.clone(),
+            // An extracted archive isn't part of a repository, whatever
+            // ~/.angrealrc might be in
+            commit: if source.is_archive() {
+                None
+            } else {
+                template_commit(template)
+            },
+            angreal_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            rendered_at: Some(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+        }
+    }
+
+    /// Where to find the template again
+    pub fn template_source(&self) -> TemplateSource {
+        TemplateSource {
+            location: self.source.clone(),
+            reference: self.reference.clone(),
+            subdir: self.subdir.clone(),
+            sha256: self.sha256.clone(),
+        }
+    }
+
+    /// Read the provenance from a project's `angreal.toml`, if it has any
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        let config = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+            .parse::<Table>()
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Self::from_config(&config)
+    }
+
+    /// Read the provenance from a parsed `angreal.toml`
+    pub fn from_config(config: &Table) -> Result<Option<Self>> {
+        let table = match config.get(PROVENANCE_KEY) {
+            None => return Ok(None),
+            Some(Value::Table(table)) => table,
+            Some(_) => return Err(anyhow!("[{}] must be a table", PROVENANCE_KEY)),
+        };
+        let source = table
+            .get("source")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("[{}] is missing the template source", PROVENANCE_KEY))?;
+        let string = |key: &str| table.get(key).and_then(Value::as_str).map(String::from);
+        Ok(Some(Self {
+            source: source.to_string(),
+            reference: string("ref"),
+            subdir: string("subdir"),
+            sha256: string("sha256"),
+            commit: string("commit"),
+            angreal_version: string("angreal_version"),
+            rendered_at: string("rendered_at"),
+        }))
+    }
+
+    /// The `[_angreal]` table
+    pub fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table.insert("source".to_string(), Value::from(self.source.as_str()));
+        let fields = [
+            ("ref", &self.reference),
+            ("subdir", &self.subdir),
+            ("sha256", &self.sha256),
+            ("commit", &self.commit),
+            ("angreal_version", &self.angreal_version),
+            ("rendered_at", &self.rendered_at),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                table.insert(key.to_string(), Value::from(value.as_str()));
+            }
+        }
+        table
+    }
+
+    /// Record the provenance in a project's `angreal.toml`
+    ///
+    /// Any previous `[_angreal]` table is replaced, the rest of the file is
+    /// left as it is.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        fs::write(path, self.replace_in(&contents))
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    fn replace_in(&self, contents: &str) -> String {
+        let header = format!("[{}]", PROVENANCE_KEY);
+        let mut kept = String::new();
+        let mut skipping = false;
+        for line in contents.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') {
+                skipping = trimmed == header;
+            }
+            if !skipping {
+                kept.push_str(line);
+                kept.push('\n');
+            }
+        }
+        let table = toml::to_string(&Value::Table(self.to_table())).unwrap();
+        format!("{}\n\n{}\n{}", kept.trim_end(), header, table)
+    }
+}
+
+/// What `angreal info` reports about a project
+#[derive(Debug, Serialize)]
+pub struct ProjectInfo {
+    pub root: PathBuf,
+    /// The template the project came from, if it was recorded
+    pub template: Option<Provenance>,
+    /// The version of angreal running now
+    pub angreal_version: String,
+}
+
+impl ProjectInfo {
+    /// Gather the information for the current project
+    pub fn load() -> Result<Self> {
+        let angreal_dir = is_angreal_project()?;
+        let root = angreal_dir
+            .parent()
+            .ok_or_else(|| anyhow!("Unable to find the project root"))?
+            .to_path_buf();
+        let answers = angreal_dir.join("angreal.toml");
+        let template = if answers.is_file() {
+            Provenance::read(&answers)?
+        } else {
+            None
+        };
+        Ok(Self {
+            root,
+            template,
+            angreal_version: env!("CARGO_PKG_VERSION").to_string(),
+        })
+    }
+
+    /// Render the information for people
+    pub fn format_text(&self) -> String {
+        let mut lines = vec![format!("Project:          {}", self.root.display())];
+        match &self.template {
+            Some(template) => {
+                let unknown = || "unknown".to_string();
+                lines.push(format!("Template:         {}", template.source));
+                if let Some(subdir) = &template.subdir {
+                    lines.push(format!("Template subdir:  {}", subdir));
+                }
+                if let Some(reference) = &template.reference {
+                    lines.push(format!("Template ref:     {}", reference));
+                }
+                if let Some(sha256) = &template.sha256 {
+                    lines.push(format!("Template sha256:  {}", sha256));
+                }
+                lines.push(format!(
+                    "Template commit:  {}",
+                    template.commit.clone().unwrap_or_else(unknown)
+                ));
+                lines.push(format!(
+                    "Rendered at:      {}",
+                    template.rendered_at.clone().unwrap_or_else(unknown)
+                ));
+                lines.push(format!(
+                    "Rendered with:    angreal {}",
+                    template.angreal_version.clone().unwrap_or_else(unknown)
+                ));
+            }
+            None => lines.push("Template:         not recorded".to_string()),
+        }
+        lines.push(format!("Angreal:          {}", self.angreal_version));
+        lines.join("\n") + "\n"
+    }
+}
+
+/// The commit a template directory is checked out at, if it's in a git repository
+pub fn template_commit(template: &Path) -> Option<String> {
+    let repo = Repository::discover(template).ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(commit.id().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_replaces_only_provenance() {
+        let provenance = Provenance {
+            source: "https://github.com/angreal/python.git".to_string(),
+            reference: Some("v2.1.0".to_string()),
+            subdir: Some("python-lib".to_string()),
+            sha256: None,
+            commit: Some("abc123".to_string()),
+            angreal_version: Some("2.8.0".to_string()),
+            rendered_at: Some("2024-05-02T10:12:03Z".to_string()),
+        };
+        let contents = "# answers\nproject_name = \"demo\"\n\n[_angreal]\nsource = \"old\"\n\n[tasks.env]\nA = \"1\"\n";
+        let replaced = provenance.replace_in(contents);
+        assert!(
+            replaced.starts_with("# answers\nproject_name = \"demo\"\n\n[tasks.env]\nA = \"1\"\n")
+        );
+
+        let config: Table = replaced.parse().unwrap();
+        assert_eq!(Provenance::from_config(&config).unwrap(), Some(provenance));
+        assert_eq!(config["tasks"]["env"]["A"].as_str(), Some("1"));
+    }
+
+    #[test]
+    fn test_missing_provenance() {
+        let config: Table = "project_name = \"demo\"".parse().unwrap();
+        assert_eq!(Provenance::from_config(&config).unwrap(), None);
+        let config: Table = "[_angreal]\ncommit = \"abc\"".parse().unwrap();
+        assert!(Provenance::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_format_info() {
+        let info = ProjectInfo {
+            root: PathBuf::from("/projects/demo"),
+            template: Some(Provenance {
+                source: "/templates/python".to_string(),
+                ..Default::default()
+            }),
+            angreal_version: "2.8.0".to_string(),
+        };
+        assert_eq!(
+            info.format_text(),
+            "Project:          /projects/demo\n\
+             Template:         /templates/python\n\
+             Template commit:  unknown\n\
+             Rendered at:      unknown\n\
+             Rendered with:    angreal unknown\n\
+             Angreal:          2.8.0\n"
+        );
+    }
+}
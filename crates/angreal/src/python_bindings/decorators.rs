@@ -5,12 +5,16 @@
 //! - @group
 //! - @command
 //! - @argument
+//! - @before, @after and @on_error lifecycle hooks
 //! - command_group helper function
 
-use crate::task::AngrealGroup;
+use crate::hooks::{register_hook, HookKind};
+use crate::task::{AngrealGroup, CommandOptions, ExceptionTypes, TaskCwd, TaskLock};
+use pyo3::exceptions::{PyBaseException, PyTypeError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::PyDict;
+use pyo3::types::{PyDict, PyTuple, PyType};
 use pyo3::wrap_pyfunction;
+use std::time::Duration;
 use version_compare::{compare, Cmp};
 
 /// Check if the current angreal version meets the specified requirement
@@ -58,9 +62,13 @@ pub fn register_decorators(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()>
     m.add_function(wrap_pyfunction!(command_group, m)?)?;
     m.add_function(wrap_pyfunction!(command, m)?)?;
     m.add_function(wrap_pyfunction!(argument, m)?)?;
+    m.add_function(wrap_pyfunction!(before, m)?)?;
+    m.add_function(wrap_pyfunction!(after, m)?)?;
+    m.add_function(wrap_pyfunction!(on_error, m)?)?;
     m.add_class::<GroupDecorator>()?;
     m.add_class::<CommandDecorator>()?;
     m.add_class::<ArgumentDecorator>()?;
+    m.add_class::<HookDecorator>()?;
     Ok(())
 }
 
@@ -152,6 +160,7 @@ pub struct CommandDecorator {
     about: Option<String>,
     long_about: Option<String>,
     tool: Option<crate::task::ToolDescription>,
+    options: CommandOptions,
 }
 
 #[pymethods]
@@ -201,6 +210,10 @@ impl CommandDecorator {
                 py.None(), // group (empty initially)
                 tool_py,
             ))?;
+            command
+                .cast::<crate::task::AngrealCommand>()?
+                .borrow_mut()
+                .set_options(self.options.clone());
 
             // Set the __command attribute on the function
             func.setattr(py, "__command", command)?;
@@ -360,14 +373,138 @@ pub fn command(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<CommandDecorator>
         })
         .transpose()?;
 
+    let options = command_options(kwargs)?;
+
     Ok(CommandDecorator {
         name,
         about,
         long_about,
         tool,
+        options,
     })
 }
 
+/// Extract the keywords of `@command(...)` that control how it is executed
+fn command_options(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<CommandOptions> {
+    let get = |key: &str| kwargs.and_then(|d| d.get_item(key).ok().flatten());
+
+    let timeout = get("timeout")
+        .filter(|v| !v.is_none())
+        .map(|v| extract_duration(&v))
+        .transpose()?;
+
+    let inputs = get("inputs")
+        .map(|v| extract_strings(&v))
+        .transpose()?
+        .unwrap_or_default();
+
+    let outputs = get("outputs")
+        .map(|v| extract_strings(&v))
+        .transpose()?
+        .unwrap_or_default();
+
+    let env_files = get("env_files")
+        .map(|v| extract_strings(&v))
+        .transpose()?
+        .unwrap_or_default();
+
+    let env = match get("env").filter(|v| !v.is_none()) {
+        None => Vec::new(),
+        Some(v) => v
+            .cast::<PyDict>()?
+            .iter()
+            .map(|(name, value)| Ok((name.extract::<String>()?, value.str()?.to_string())))
+            .collect::<PyResult<Vec<_>>>()?,
+    };
+
+    let retries = get("retries")
+        .filter(|v| !v.is_none())
+        .map(|v| v.extract::<u32>())
+        .transpose()?
+        .unwrap_or_default();
+
+    let retry_delay = get("retry_delay")
+        .filter(|v| !v.is_none())
+        .map(|v| extract_duration(&v))
+        .transpose()?
+        .unwrap_or_default();
+
+    let retry_on = get("retry_on")
+        .filter(|v| !v.is_none())
+        .map(|v| extract_exception_types(&v))
+        .transpose()?;
+
+    let lock = match get("lock").filter(|v| !v.is_none()) {
+        None => None,
+        Some(v) => match v.extract::<bool>() {
+            Ok(true) => Some(TaskLock::Command),
+            Ok(false) => None,
+            Err(_) => Some(TaskLock::Named(v.extract::<String>()?)),
+        },
+    };
+
+    let cwd = get("cwd")
+        .filter(|v| !v.is_none())
+        .map(|v| v.extract::<String>().map(|cwd| TaskCwd::parse(&cwd)))
+        .transpose()?
+        .unwrap_or_default();
+
+    Ok(CommandOptions {
+        timeout,
+        inputs,
+        outputs,
+        env_files,
+        env,
+        retries,
+        retry_delay,
+        retry_on,
+        lock,
+        cwd,
+    })
+}
+
+/// Extract an exception class or a list of them as a tuple
+fn extract_exception_types(value: &Bound<'_, PyAny>) -> PyResult<ExceptionTypes> {
+    let py = value.py();
+    let classes: Vec<Bound<'_, PyAny>> = if value.is_instance_of::<PyType>() {
+        vec![value.clone()]
+    } else {
+        value.extract()?
+    };
+    for class in &classes {
+        let is_exception = class
+            .cast::<PyType>()
+            .map(|t| t.is_subclass_of::<PyBaseException>())
+            .unwrap_or(Ok(false))?;
+        if !is_exception {
+            return Err(PyTypeError::new_err(format!(
+                "retry_on expects exception classes, got {}",
+                class.repr()?
+            )));
+        }
+    }
+    Ok(ExceptionTypes(
+        PyTuple::new(py, classes)?.into_any().unbind(),
+    ))
+}
+
+/// Extract a string or a list of them
+fn extract_strings(value: &Bound<'_, PyAny>) -> PyResult<Vec<String>> {
+    if let Ok(pattern) = value.extract::<String>() {
+        return Ok(vec![pattern]);
+    }
+    value.extract::<Vec<String>>()
+}
+
+/// Extract a duration given either as a number of seconds or a string like "10m"
+fn extract_duration(value: &Bound<'_, PyAny>) -> PyResult<Duration> {
+    let parsed = match value.extract::<f64>() {
+        Ok(seconds) => crate::timeout::parse_duration(&seconds.to_string()),
+        Err(_) => crate::timeout::parse_duration(&value.extract::<String>()?),
+    };
+    parsed.map_err(PyValueError::new_err)
+}
+
 /// Create an argument decorator that adds command-line arguments to commands
 ///
 /// This function returns a Python decorator that can be applied to commands
@@ -438,3 +575,59 @@ impl ArgumentDecorator {
         })
     }
 }
+
+/// A Python callable that registers the decorated function as a lifecycle hook
+#[pyclass]
+pub struct HookDecorator {
+    kind: HookKind,
+    pattern: String,
+}
+
+#[pymethods]
+impl HookDecorator {
+    #[pyo3(signature = (func,))]
+    fn __call__(&self, func: Py<PyAny>) -> PyResult<Py<PyAny>> {
+        Python::attach(|py| {
+            register_hook(self.kind, &self.pattern, func.clone_ref(py))
+                .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+            Ok(func)
+        })
+    }
+}
+
+/// Register a hook that runs before every command matching `pattern`
+///
+/// The hook is called with `(command_path, kwargs)`. Raising from the hook
+/// aborts the command.
+#[pyfunction]
+#[pyo3(signature = (pattern = "*"))]
+pub fn before(pattern: &str) -> HookDecorator {
+    HookDecorator {
+        kind: HookKind::Before,
+        pattern: pattern.to_string(),
+    }
+}
+
+/// Register a hook that runs after every command matching `pattern` returns
+///
+/// The hook is called with `(command_path, kwargs, result)`.
+#[pyfunction]
+#[pyo3(signature = (pattern = "*"))]
+pub fn after(pattern: &str) -> HookDecorator {
+    HookDecorator {
+        kind: HookKind::After,
+        pattern: pattern.to_string(),
+    }
+}
+
+/// Register a hook that runs when a command matching `pattern` raises
+///
+/// The hook is called with `(command_path, kwargs, exception)`.
+#[pyfunction]
+#[pyo3(signature = (pattern = "*"))]
+pub fn on_error(pattern: &str) -> HookDecorator {
+    HookDecorator {
+        kind: HookKind::OnError,
+        pattern: pattern.to_string(),
+    }
+}
@@ -9,6 +9,7 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
+use std::time::Duration;
 
 /// Monotonic counter for generating unique temporary registry keys.
 /// This prevents collisions when two commands share the same base name
@@ -230,6 +231,88 @@ pub struct AngrealCommand {
     /// This prevents collisions when two commands share the same base name
     /// before group decorators have run (e.g. top-level "build" vs "docs build").
     pub registry_key: Option<String>,
+    /// How the command is executed, set through `@command(...)` keywords
+    pub options: CommandOptions,
+}
+
+/// Execution options for a command
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommandOptions {
+    /// Interrupt the command if it runs longer than this
+    pub timeout: Option<Duration>,
+    /// Globs of the files the command reads, relative to the project root
+    pub inputs: Vec<String>,
+    /// Globs of the files the command produces, relative to the project root
+    pub outputs: Vec<String>,
+    /// Dotenv files loaded while the command runs, relative to the project root
+    pub env_files: Vec<String>,
+    /// Environment variables set while the command runs
+    pub env: Vec<(String, String)>,
+    /// How many more times the command is run after it fails
+    pub retries: u32,
+    /// How long to wait between attempts
+    pub retry_delay: Duration,
+    /// The exceptions that are retried, any `Exception` when unset
+    pub retry_on: Option<ExceptionTypes>,
+    /// The lock held while the command runs
+    pub lock: Option<TaskLock>,
+    /// The directory the command runs in
+    pub cwd: TaskCwd,
+}
+
+/// The working directory a command runs in
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum TaskCwd {
+    /// Wherever angreal was invoked from, the default
+    #[default]
+    Invocation,
+    /// The project root, the directory containing `.angreal`
+    Project,
+    /// A directory relative to the project root
+    Path(String),
+}
+
+impl TaskCwd {
+    /// Parse the `cwd` keyword of `@command`
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "invocation" => Self::Invocation,
+            "project" => Self::Project,
+            path => Self::Path(path.to_string()),
+        }
+    }
+}
+
+/// The lock a command holds while it runs
+#[derive(Clone, Debug, PartialEq)]
+pub enum TaskLock {
+    /// A lock of the command's own, from `lock=True`
+    Command,
+    /// A lock shared by every command naming it, from `lock="name"`
+    Named(String),
+}
+
+/// A tuple of python exception classes, as accepted by `isinstance`
+#[derive(Debug)]
+pub struct ExceptionTypes(pub Py<PyAny>);
+
+impl Clone for ExceptionTypes {
+    fn clone(&self) -> Self {
+        Python::attach(|py| Self(self.0.clone_ref(py)))
+    }
+}
+
+impl PartialEq for ExceptionTypes {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_ptr() == other.0.as_ptr()
+    }
+}
+
+impl CommandOptions {
+    /// Whether the command may be skipped when its inputs haven't changed
+    pub fn is_incremental(&self) -> bool {
+        !self.inputs.is_empty() || !self.outputs.is_empty()
+    }
 }
 
 impl Clone for AngrealCommand {
@@ -242,10 +325,23 @@ impl Clone for AngrealCommand {
             group: self.group.clone(),
             tool: self.tool.clone(),
             registry_key: self.registry_key.clone(),
+            options: self.options.clone(),
         })
     }
 }
 
+impl AngrealCommand {
+    /// Set the execution options, keeping the registered copy in sync
+    pub fn set_options(&mut self, options: CommandOptions) {
+        if let Some(key) = &self.registry_key {
+            if let Some(registered) = ANGREAL_TASKS.lock().unwrap().get_mut(key) {
+                registered.options = options.clone();
+            }
+        }
+        self.options = options;
+    }
+}
+
 /// Methods exposed to the python API
 #[pymethods]
 impl AngrealCommand {
@@ -301,6 +397,7 @@ impl AngrealCommand {
             func,
             tool,
             registry_key: Some(registry_key.clone()),
+            options: CommandOptions::default(),
         };
 
         ANGREAL_TASKS
@@ -571,6 +668,7 @@ mod tests {
                 func: py.None(),
                 tool: None,
                 registry_key: None,
+                options: CommandOptions::default(),
             };
 
             let cmd2 = AngrealCommand {
@@ -581,6 +679,7 @@ mod tests {
                 func: py.None(),
                 tool: None,
                 registry_key: None,
+                options: CommandOptions::default(),
             };
 
             // Register both commands
@@ -658,6 +757,7 @@ mod tests {
                 func: py.None(),
                 tool: None,
                 registry_key: None,
+                options: CommandOptions::default(),
             };
 
             let cmd2 = AngrealCommand {
@@ -668,6 +768,7 @@ mod tests {
                 func: py.None(),
                 tool: None,
                 registry_key: None,
+                options: CommandOptions::default(),
             };
 
             let path1 = generate_command_path_key(&cmd1);
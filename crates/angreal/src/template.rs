@@ -0,0 +1,301 @@
+//! Template rendering settings
+//!
+//! A template's `angreal.toml` can control how its files are rendered with a
+//! `[template]` table:
+//!
+//! ```toml
+//! [template]
+//! exclude = ["**/__pycache__", "**/*.pyc"]
+//! copy_only = ["**/*.png", "**/*.j2", "{{ project_name }}/.github/**"]
+//! include_dotfiles = true
+//! ```
+//!
+//! Globs are matched against paths relative to the template directory, as
+//! they appear in the template (before their names are rendered).
+//!
+//! Binary files are always copied byte for byte, whether or not they match
+//! `copy_only`.
+//!
+//! Whether a path is written at all can depend on the answers given when the
+//! template is rendered. A `[conditions]` table maps globs to Tera expressions,
+//! and paths matching an expression that is false are skipped along with
+//! everything inside them:
+//!
+//! ```toml
+//! [conditions]
+//! "{{ project_name }}/docs" = "use_docs"
+//! "{{ project_name }}/.github" = "ci == 'github'"
+//! ```
+//!
+//! A path whose name renders to nothing, such as
+//! `{% if use_docker %}Dockerfile{% endif %}`, is skipped too.
+
+use anyhow::{anyhow, bail, Context, Result};
+use glob::Pattern;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+use tera::{Context as TeraContext, Tera};
+use toml::{Table, Value};
+
+/// How a template's files are rendered
+#[derive(Debug, Default)]
+pub struct TemplateSettings {
+    /// Paths that are never written
+    pub exclude: Vec<Pattern>,
+    /// Files copied as they are instead of being rendered through Tera
+    pub copy_only: Vec<Pattern>,
+    /// Render top-level paths whose names start with a dot
+    pub include_dotfiles: bool,
+    /// Paths only written when a Tera expression is true
+    pub conditions: Vec<(Pattern, String)>,
+}
+
+impl TemplateSettings {
+    /// Load the settings from the `angreal.toml` in a template directory
+    ///
+    /// A template without an `angreal.toml` or a `[template]` table gets the
+    /// defaults, as does one with an answer named `template`.
+    pub fn load(src: &Path) -> Result<Self> {
+        let path = src.join("angreal.toml");
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let config = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+            .parse::<Table>()
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        // Anything other than a table is an answer that shares the name
+        let mut settings = match config.get("template") {
+            Some(Value::Table(table)) => Self::from_table(table)?,
+            _ => Self::default(),
+        };
+        settings.conditions = match config.get("conditions") {
+            Some(Value::Table(table)) => conditions(table)?,
+            _ => Vec::new(),
+        };
+        Ok(settings)
+    }
+
+    /// Read the settings from a `[template]` table
+    pub fn from_table(table: &Table) -> Result<Self> {
+        let include_dotfiles = match table.get("include_dotfiles") {
+            None => false,
+            Some(value) => value
+                .as_bool()
+                .ok_or_else(|| anyhow!("template.include_dotfiles must be true or false"))?,
+        };
+        Ok(Self {
+            exclude: patterns(table, "exclude")?,
+            copy_only: patterns(table, "copy_only")?,
+            include_dotfiles,
+            conditions: Vec::new(),
+        })
+    }
+
+    /// Whether a template path, or a directory containing it, is excluded
+    pub fn is_excluded(&self, rel_path: &str) -> bool {
+        Path::new(rel_path)
+            .ancestors()
+            .filter(|p| !p.as_os_str().is_empty())
+            .any(|p| {
+                let p = p.to_string_lossy();
+                self.exclude.iter().any(|pattern| pattern.matches(&p))
+            })
+    }
+
+    /// Whether a template file is copied without being rendered
+    pub fn is_copy_only(&self, rel_path: &str) -> bool {
+        self.copy_only.iter().any(|p| p.matches(rel_path))
+    }
+
+    /// Whether a rendered path is a top-level dotfile that should be skipped
+    pub fn skips_dotfile(&self, rendered: &str) -> bool {
+        !self.include_dotfiles && rendered.starts_with('.')
+    }
+
+    /// Whether a template path is written, given what its name rendered to
+    pub fn renders(&self, rel_path: &str, rendered: &str, context: &TeraContext) -> Result<bool> {
+        if self.is_excluded(rel_path) || self.skips_dotfile(rendered) {
+            return Ok(false);
+        }
+        if rendered.split('/').any(|name| name.trim().is_empty()) {
+            return Ok(false);
+        }
+        for ancestor in Path::new(rel_path).ancestors() {
+            let ancestor = ancestor.to_string_lossy();
+            for (pattern, condition) in &self.conditions {
+                if pattern.matches(&ancestor) && !evaluate(condition, context)? {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Evaluate a Tera expression as a condition
+pub fn evaluate(condition: &str, context: &TeraContext) -> Result<bool> {
+    let template = format!("{{% if {} %}}true{{% endif %}}", condition);
+    let rendered = Tera::one_off(&template, context, false)
+        .map_err(|e| anyhow!("Unable to evaluate condition '{}': {:?}", condition, e))?;
+    Ok(rendered == "true")
+}
+
+fn conditions(table: &Table) -> Result<Vec<(Pattern, String)>> {
+    table
+        .iter()
+        .map(|(glob, condition)| {
+            let condition = condition
+                .as_str()
+                .ok_or_else(|| anyhow!("The condition for '{}' must be a string", glob))?;
+            let pattern = Pattern::new(glob)
+                .map_err(|e| anyhow!("Invalid glob '{}' in [conditions]: {}", glob, e))?;
+            Ok((pattern, condition.to_string()))
+        })
+        .collect()
+}
+
+/// How much of a file is sniffed to decide whether it is binary
+const SNIFF_LEN: u64 = 8192;
+
+/// Whether a file looks binary and so can't be rendered as a template
+///
+/// A file is binary if its first few kilobytes contain a null byte or aren't
+/// valid UTF-8, which covers images, fonts, archives and compiled files.
+pub fn is_binary(path: &Path) -> io::Result<bool> {
+    let mut head = Vec::new();
+    File::open(path)?.take(SNIFF_LEN).read_to_end(&mut head)?;
+    Ok(looks_binary(&head))
+}
+
+/// Whether the start of a file looks binary
+pub fn looks_binary(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => false,
+        // A character cut off at the end of the sniffed bytes is fine
+        Err(e) => e.error_len().is_some(),
+    }
+}
+
+fn patterns(table: &Table, key: &str) -> Result<Vec<Pattern>> {
+    let values = match table.get(key) {
+        None => return Ok(Vec::new()),
+        Some(Value::Array(values)) => values,
+        Some(_) => bail!("template.{} must be a list of globs", key),
+    };
+    values
+        .iter()
+        .map(|value| {
+            let glob = value
+                .as_str()
+                .ok_or_else(|| anyhow!("template.{} must be a list of globs", key))?;
+            Pattern::new(glob)
+                .map_err(|e| anyhow!("Invalid glob '{}' in template.{}: {}", glob, key, e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_from_table() {
+        let table: Table = r#"
+exclude = ["**/__pycache__", "**/*.pyc"]
+copy_only = ["**/*.png", "{{ name }}/.github/**"]
+include_dotfiles = true
+"#
+        .parse()
+        .unwrap();
+        let settings = TemplateSettings::from_table(&table).unwrap();
+
+        assert!(settings.is_excluded("{{ name }}/pkg/__pycache__"));
+        assert!(settings.is_excluded("{{ name }}/pkg/__pycache__/mod.cpython-312.pyc"));
+        assert!(settings.is_excluded("{{ name }}/mod.pyc"));
+        assert!(!settings.is_excluded("{{ name }}/mod.py"));
+
+        assert!(settings.is_copy_only("{{ name }}/docs/logo.png"));
+        assert!(settings.is_copy_only("{{ name }}/.github/workflows/ci.yml"));
+        assert!(!settings.is_copy_only("{{ name }}/README.md"));
+
+        assert!(!settings.skips_dotfile(".envrc"));
+        assert!(TemplateSettings::default().skips_dotfile(".envrc"));
+        assert!(!TemplateSettings::default().skips_dotfile("project/.gitignore"));
+    }
+
+    #[test]
+    fn test_conditions() {
+        let config: Table = r#"
+"{{ name }}/docs" = "use_docs"
+"{{ name }}/.github/**" = "ci == 'github'"
+"#
+        .parse()
+        .unwrap();
+        let settings = TemplateSettings {
+            conditions: conditions(&config).unwrap(),
+            ..Default::default()
+        };
+        let mut context = TeraContext::new();
+        context.insert("use_docs", &false);
+        context.insert("ci", "github");
+
+        let renders =
+            |rel: &str, rendered: &str| settings.renders(rel, rendered, &context).unwrap();
+        assert!(renders("{{ name }}/README.md", "demo/README.md"));
+        assert!(!renders("{{ name }}/docs", "demo/docs"));
+        assert!(!renders("{{ name }}/docs/index.md", "demo/docs/index.md"));
+        assert!(renders(
+            "{{ name }}/.github/workflows/ci.yml",
+            "demo/.github/workflows/ci.yml"
+        ));
+        // Names that render to nothing are dropped
+        assert!(!renders(
+            "{{ name }}/{% if use_docs %}mkdocs.yml{% endif %}",
+            "demo/"
+        ));
+        assert!(!renders("{{ name }}/{{ nothing }}/x", "demo//x"));
+
+        assert!(evaluate("ci == 'github' and not use_docs", &context).unwrap());
+        // Undefined variables are false, like in any Tera `if`
+        assert!(!evaluate("undefined_variable", &context).unwrap());
+        assert!(evaluate("use_docs ==", &context).is_err());
+    }
+
+    #[test]
+    fn test_looks_binary() {
+        assert!(!looks_binary(b"# {{ project_name }}\n"));
+        assert!(!looks_binary("caf\u{e9}".as_bytes()));
+        // A multi-byte character split by the sniff limit
+        assert!(!looks_binary(&"caf\u{e9}".as_bytes()[..4]));
+        assert!(looks_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+        assert!(looks_binary(b"\xff\xfe\xfd plain"));
+    }
+
+    #[test]
+    fn test_load_ignores_answers_named_like_settings() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join("angreal.toml"),
+            "template = \"x\"\nconditions = \"none\"\n",
+        )
+        .unwrap();
+        let settings = TemplateSettings::load(tmp.path()).unwrap();
+        assert!(settings.conditions.is_empty());
+        assert!(!settings.is_excluded("{{ name }}/README.md"));
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        let table: Table = "exclude = \"*.pyc\"".parse().unwrap();
+        assert!(TemplateSettings::from_table(&table).is_err());
+        let table: Table = "copy_only = [\"[\"]".parse().unwrap();
+        assert!(TemplateSettings::from_table(&table).is_err());
+        let table: Table = "include_dotfiles = \"yes\"".parse().unwrap();
+        assert!(TemplateSettings::from_table(&table).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! Task timeouts and cancellation
+//!
+//! A running task is interrupted by raising `KeyboardInterrupt` in it, either
+//! because the user sent SIGINT/SIGTERM or because a [`Watchdog`] fired. If a
+//! timed out task doesn't stop within the grace period, the child processes
+//! spawned through [`crate::process`] are terminated, and if that doesn't
+//! unblock it angreal exits.
+
+use log::{error, warn};
+use pyo3::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::process;
+
+/// Exit code reported when a task is stopped by its timeout
+pub const TIMEOUT_EXIT_CODE: i32 = 124;
+/// Exit code reported when a task is interrupted by SIGINT/SIGTERM
+pub const INTERRUPT_EXIT_CODE: i32 = 130;
+
+/// How long a timed out task gets to handle `KeyboardInterrupt`
+const GRACE_PERIOD: Duration = Duration::from_secs(10);
+/// How long child processes get to exit after SIGTERM
+pub const CHILD_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Parse a human readable duration such as `90s`, `10m` or `1h 30m`
+///
+/// A bare number is taken as seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        if seconds.is_finite() && seconds >= 0.0 {
+            return Ok(Duration::from_secs_f64(seconds));
+        }
+    }
+    humantime::parse_duration(value).map_err(|e| format!("Invalid duration '{}': {}", value, e))
+}
+
+/// Format a duration the way it would be written for `timeout=`
+pub fn format_duration(duration: Duration) -> String {
+    humantime::format_duration(duration).to_string()
+}
+
+/// A handle to the thread that was running the task, used to interrupt it
+#[derive(Clone, Copy)]
+struct TaskThread {
+    #[cfg(unix)]
+    id: libc::pthread_t,
+}
+
+// SAFETY: a pthread_t is only an identifier, it is never dereferenced
+unsafe impl Send for TaskThread {}
+
+impl TaskThread {
+    fn current() -> Self {
+        Self {
+            // SAFETY: pthread_self is always safe to call
+            #[cfg(unix)]
+            id: unsafe { libc::pthread_self() },
+        }
+    }
+
+    /// Raise `KeyboardInterrupt` in the task thread
+    fn interrupt(self) {
+        // Delivering a real SIGINT to the thread also breaks it out of blocking
+        // system calls such as `time.sleep` or `subprocess.wait`, which
+        // PyErr_SetInterrupt alone wouldn't do.
+        #[cfg(unix)]
+        // SAFETY: the thread is the interpreter's main thread, which outlives
+        // the watchdog
+        unsafe {
+            libc::pthread_kill(self.id, libc::SIGINT);
+        }
+        #[cfg(not(unix))]
+        // SAFETY: PyErr_SetInterrupt may be called without holding the GIL
+        unsafe {
+            pyo3::ffi::PyErr_SetInterrupt();
+        }
+    }
+}
+
+/// Interrupts the current thread if it's still running after a timeout
+pub struct Watchdog {
+    done: Sender<()>,
+    handle: JoinHandle<()>,
+    timed_out: Arc<AtomicBool>,
+}
+
+impl Watchdog {
+    /// Start watching the calling thread, which must be the thread running
+    /// the task
+    pub fn start(command_path: &str, timeout: Duration) -> Self {
+        let (done, finished) = mpsc::channel::<()>();
+        let timed_out = Arc::new(AtomicBool::new(false));
+        let task_thread = TaskThread::current();
+        let command_path = command_path.to_string();
+        let flag = timed_out.clone();
+
+        let handle = thread::spawn(move || {
+            if finished.recv_timeout(timeout) != Err(RecvTimeoutError::Timeout) {
+                return;
+            }
+
+            flag.store(true, Ordering::SeqCst);
+            warn!(
+                "'{}' timed out after {}, interrupting it.",
+                command_path,
+                format_duration(timeout)
+            );
+            task_thread.interrupt();
+
+            if finished.recv_timeout(GRACE_PERIOD) != Err(RecvTimeoutError::Timeout) {
+                return;
+            }
+
+            warn!(
+                "'{}' did not stop within {}, terminating {} child process(es).",
+                command_path,
+                format_duration(GRACE_PERIOD),
+                process::running()
+            );
+            process::terminate_children(CHILD_GRACE_PERIOD);
+
+            if finished.recv_timeout(GRACE_PERIOD) != Err(RecvTimeoutError::Timeout) {
+                return;
+            }
+
+            error!("'{}' could not be stopped, exiting.", command_path);
+            std::process::exit(TIMEOUT_EXIT_CODE);
+        });
+
+        Self {
+            done,
+            handle,
+            timed_out,
+        }
+    }
+
+    /// Stop watching, returns whether the timeout fired
+    pub fn finish(self) -> bool {
+        let _ = self.done.send(());
+        let _ = self.handle.join();
+        self.timed_out.load(Ordering::SeqCst)
+    }
+}
+
+/// Signal handlers installed while a task runs, see [`install_signal_handlers`]
+pub struct SignalHandlers {
+    previous: Vec<(Py<PyAny>, Py<PyAny>)>,
+    #[cfg(unix)]
+    forwarder: Option<SignalForwarder>,
+}
+
+/// Route SIGINT and SIGTERM to `KeyboardInterrupt` while a task runs
+///
+/// The signals are also passed on to the integrations' child processes, since
+/// python only raises the exception once the call waiting for them returns.
+/// Returns the previous handlers so they can be put back with
+/// [`restore_signal_handlers`]. Python only allows this from the main thread,
+/// elsewhere nothing is changed.
+pub fn install_signal_handlers(py: Python<'_>) -> Option<SignalHandlers> {
+    let install = || -> PyResult<Vec<(Py<PyAny>, Py<PyAny>)>> {
+        let signal = py.import("signal")?;
+        let handler = signal.getattr("default_int_handler")?;
+        let mut previous = Vec::new();
+        for name in ["SIGINT", "SIGTERM"] {
+            let signum = signal.getattr(name)?;
+            let old = signal.call_method1("signal", (&signum, &handler))?;
+            previous.push((signum.unbind(), old.unbind()));
+        }
+        Ok(previous)
+    };
+    let previous = install().ok()?;
+    Some(SignalHandlers {
+        previous,
+        #[cfg(unix)]
+        forwarder: SignalForwarder::start(py)
+            .map_err(|e| warn!("Signals won't reach child processes: {}", e))
+            .ok(),
+    })
+}
+
+/// Put back the signal handlers replaced by [`install_signal_handlers`]
+pub fn restore_signal_handlers(py: Python<'_>, handlers: Option<SignalHandlers>) {
+    let Some(handlers) = handlers else {
+        return;
+    };
+    #[cfg(unix)]
+    if let Some(forwarder) = handlers.forwarder {
+        forwarder.stop(py);
+    }
+    if let Ok(signal) = py.import("signal") {
+        for (signum, handler) in handlers.previous {
+            if handler.is_none(py) {
+                // Handlers installed outside of python can't be restored
+                continue;
+            }
+            let _ = signal.call_method1("signal", (signum, handler));
+        }
+    }
+}
+
+/// Passes the signals python receives on to the tracked child processes
+///
+/// Python's own handler only sets a flag, but it also writes the signal
+/// number to the wakeup fd, which a thread here reads from.
+#[cfg(unix)]
+struct SignalForwarder {
+    previous_fd: Py<PyAny>,
+    write_fd: libc::c_int,
+    handle: JoinHandle<()>,
+}
+
+#[cfg(unix)]
+impl SignalForwarder {
+    fn start(py: Python<'_>) -> PyResult<Self> {
+        let mut fds = [0 as libc::c_int; 2];
+        // SAFETY: fds has room for the two descriptors pipe writes
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        let [read_fd, write_fd] = fds;
+        // SAFETY: both descriptors were just created and are owned here
+        let close = || unsafe {
+            libc::close(read_fd);
+            libc::close(write_fd);
+        };
+        // Python requires the wakeup fd not to block
+        // SAFETY: write_fd is a valid descriptor
+        unsafe {
+            let flags = libc::fcntl(write_fd, libc::F_GETFL);
+            libc::fcntl(write_fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+        }
+        let previous_fd = match py
+            .import("signal")
+            .and_then(|signal| signal.call_method1("set_wakeup_fd", (write_fd,)))
+        {
+            Ok(previous_fd) => previous_fd.unbind(),
+            Err(e) => {
+                close();
+                return Err(e);
+            }
+        };
+
+        let handle = thread::spawn(move || {
+            let mut buf = [0u8; 16];
+            loop {
+                // SAFETY: buf is valid for buf.len() bytes
+                let n = unsafe { libc::read(read_fd, buf.as_mut_ptr().cast(), buf.len()) };
+                if n < 0
+                    && std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted
+                {
+                    continue;
+                }
+                if n <= 0 {
+                    break;
+                }
+                for signum in &buf[..n as usize] {
+                    process::forward_signal(*signum as libc::c_int);
+                }
+            }
+            // SAFETY: the read end is only used by this thread
+            unsafe {
+                libc::close(read_fd);
+            }
+        });
+
+        Ok(Self {
+            previous_fd,
+            write_fd,
+            handle,
+        })
+    }
+
+    /// Put back the previous wakeup fd and wait for the thread to finish
+    fn stop(self, py: Python<'_>) {
+        if let Ok(signal) = py.import("signal") {
+            let _ = signal.call_method1("set_wakeup_fd", (self.previous_fd,));
+        }
+        // Closing the write end ends the thread's read loop
+        // SAFETY: the write end is owned here and no longer python's wakeup fd
+        unsafe {
+            libc::close(self.write_fd);
+        }
+        let _ = self.handle.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("1.5").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_duration("10m").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_duration("1h 30m").unwrap(), Duration::from_secs(5400));
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("-5").is_err());
+    }
+
+    #[test]
+    fn test_watchdog_finished_in_time() {
+        let watchdog = Watchdog::start("test", Duration::from_secs(30));
+        let started = Instant::now();
+        assert!(!watchdog.finish());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
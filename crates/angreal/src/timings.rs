@@ -0,0 +1,201 @@
+//! Timing of angreal's startup and task execution
+//!
+//! Spans are recorded for every run, which costs next to nothing, and are
+//! reported with `angreal --timings <command>`. `--timings=trace.json` also
+//! writes them as a Chrome trace that can be opened in `chrome://tracing` or
+//! Perfetto.
+
+use anyhow::{Context, Result};
+use log::error;
+use once_cell::sync::Lazy;
+use serde_json::json;
+use std::cell::Cell;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// When angreal started, spans are relative to it
+static STARTED: Lazy<Instant> = Lazy::new(Instant::now);
+
+static SPANS: Lazy<Mutex<Vec<Span>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+thread_local! {
+    /// How many spans are currently open, used to indent nested spans
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A finished span
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    /// What kind of work the span covers, e.g. `load` or `integration`
+    pub category: &'static str,
+    pub name: String,
+    /// Offset from the start of angreal
+    pub start: Duration,
+    pub duration: Duration,
+    /// The number of spans that enclose this one
+    pub depth: usize,
+}
+
+/// An open span, recorded when dropped
+pub struct SpanGuard {
+    category: &'static str,
+    name: String,
+    started: Instant,
+    depth: usize,
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        DEPTH.with(|depth| depth.set(depth.get().saturating_sub(1)));
+        let span = Span {
+            category: self.category,
+            name: std::mem::take(&mut self.name),
+            start: self.started.saturating_duration_since(*STARTED),
+            duration: self.started.elapsed(),
+            depth: self.depth,
+        };
+        SPANS.lock().unwrap().push(span);
+    }
+}
+
+/// Mark the start of angreal, call as early as possible
+pub fn start() {
+    Lazy::force(&STARTED);
+}
+
+/// Open a span that lasts until the returned guard is dropped
+pub fn span(category: &'static str, name: impl Into<String>) -> SpanGuard {
+    Lazy::force(&STARTED);
+    SpanGuard {
+        category,
+        name: name.into(),
+        started: Instant::now(),
+        depth: DEPTH.with(|depth| depth.replace(depth.get() + 1)),
+    }
+}
+
+/// Time a closure
+pub fn time<T>(category: &'static str, name: impl Into<String>, f: impl FnOnce() -> T) -> T {
+    let _span = span(category, name);
+    f()
+}
+
+/// The spans recorded so far, in the order they started
+pub fn spans() -> Vec<Span> {
+    let mut spans = SPANS.lock().unwrap().clone();
+    spans.sort_by_key(|s| (s.start, s.depth));
+    spans
+}
+
+/// Print the report and write the trace if one was asked for
+pub fn finish(trace: Option<&Path>) {
+    let spans = spans();
+    eprint!("{}", format_report(&spans, STARTED.elapsed()));
+    if let Some(path) = trace {
+        match write_chrome_trace(&spans, path) {
+            Ok(()) => eprintln!("Wrote trace to {}", path.display()),
+            Err(e) => error!("{}", e),
+        }
+    }
+}
+
+/// Render spans as an indented table
+pub fn format_report(spans: &[Span], total: Duration) -> String {
+    let mut report = String::from("\nTimings:\n");
+    for span in spans {
+        report.push_str(&format!(
+            "{:>10}  {:<12} {}{}\n",
+            format_ms(span.duration),
+            span.category,
+            "  ".repeat(span.depth),
+            span.name
+        ));
+    }
+    report.push_str(&format!("{:>10}  total\n", format_ms(total)));
+    report
+}
+
+/// Write spans in the Chrome trace event format
+pub fn write_chrome_trace(spans: &[Span], path: &Path) -> Result<()> {
+    let pid = std::process::id();
+    let events: Vec<_> = spans
+        .iter()
+        .map(|span| {
+            json!({
+                "name": span.name,
+                "cat": span.category,
+                "ph": "X",
+                "ts": span.start.as_micros() as u64,
+                "dur": span.duration.as_micros() as u64,
+                "pid": pid,
+                "tid": 1,
+            })
+        })
+        .collect();
+    let trace = json!({ "traceEvents": events, "displayTimeUnit": "ms" });
+    fs::write(path, serde_json::to_string_pretty(&trace)?)
+        .with_context(|| format!("Failed to write trace to {}", path.display()))
+}
+
+fn format_ms(duration: Duration) -> String {
+    format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn span_at(name: &str, start_ms: u64, duration_ms: u64, depth: usize) -> Span {
+        Span {
+            category: "test",
+            name: name.to_string(),
+            start: Duration::from_millis(start_ms),
+            duration: Duration::from_millis(duration_ms),
+            depth,
+        }
+    }
+
+    #[test]
+    fn test_nested_spans_are_recorded() {
+        {
+            let _outer = span("test", "timings-outer");
+            time("test", "timings-inner", || ());
+        }
+        let spans = spans();
+        let outer = spans.iter().find(|s| s.name == "timings-outer").unwrap();
+        let inner = spans.iter().find(|s| s.name == "timings-inner").unwrap();
+        assert_eq!(inner.depth, outer.depth + 1);
+        assert!(inner.start >= outer.start);
+        assert!(inner.duration <= outer.duration);
+    }
+
+    #[test]
+    fn test_format_report() {
+        let spans = vec![
+            span_at("build", 5, 120, 0),
+            span_at("docker compose up", 10, 100, 1),
+        ];
+        let report = format_report(&spans, Duration::from_millis(130));
+        assert!(report.contains("120.0ms  test         build\n"));
+        assert!(report.contains("100.0ms  test           docker compose up\n"));
+        assert!(report.contains("130.0ms  total\n"));
+    }
+
+    #[test]
+    fn test_chrome_trace() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("trace.json");
+        write_chrome_trace(&[span_at("build", 5, 120, 0)], &path).unwrap();
+
+        let trace: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let event = &trace["traceEvents"][0];
+        assert_eq!(event["name"], "build");
+        assert_eq!(event["ph"], "X");
+        assert_eq!(event["ts"], 5000);
+        assert_eq!(event["dur"], 120_000);
+    }
+}
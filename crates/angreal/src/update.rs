@@ -0,0 +1,412 @@
+//! Re-applying a newer version of a project's template
+//!
+//! `angreal update` renders the template a project was created from twice
+//! with the answers stored in `.angreal/angreal.toml`: once at the commit
+//! recorded by `angreal init` and once at its latest version. What changed
+//! between the two is merged into the project file by file:
+//!
+//! - files the project hasn't touched take the template's new version
+//! - files changed on both sides are merged line by line, with conflict
+//!   markers where both changed the same lines
+//! - files deleted in the project stay deleted
+//!
+//! Templates that aren't in a git repository have no recorded commit, so
+//! every file that differs from the template is treated as changed on both
+//! sides.
+
+use anyhow::{anyhow, Context, Result};
+use diffy::{ConflictStyle, MergeOptions};
+use git2::build::CheckoutBuilder;
+use git2::{Oid, Repository};
+use log::{debug, warn};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use walkdir::WalkDir;
+
+use crate::dry_run;
+use crate::init::resolve_template;
+use crate::provenance::{template_commit, Provenance};
+use crate::template::looks_binary;
+use crate::utils::{extract_key_defaults, is_angreal_project, render_dir, repl_context_from_toml};
+
+/// The project's answers, which an update never overwrites
+const ANSWERS_FILE: &str = ".angreal/angreal.toml";
+
+/// What an update does to one file
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Nothing to do
+    Keep,
+    /// Take the template's version, the project hadn't changed the file
+    Write(Vec<u8>),
+    /// Both sides changed the file and their changes were merged
+    Merge(Vec<u8>),
+    /// Both sides changed the same lines, the file gets conflict markers
+    Conflict(Vec<u8>),
+    /// The template dropped a file the project hadn't changed
+    Remove,
+    /// The template changed the file but the project's version is kept
+    Skip(&'static str),
+}
+
+/// An update to a project file
+#[derive(Debug)]
+pub struct FileUpdate {
+    /// Relative to the project root
+    pub path: PathBuf,
+    pub action: Action,
+}
+
+/// The outcome of `angreal update`
+#[derive(Debug, Default)]
+pub struct UpdateReport {
+    /// The template commit the project was on
+    pub from: Option<String>,
+    /// The template commit the project is now on
+    pub to: Option<String>,
+    pub files: Vec<FileUpdate>,
+}
+
+impl UpdateReport {
+    /// Whether any file was left with conflict markers or kept unmerged
+    pub fn has_conflicts(&self) -> bool {
+        self.files
+            .iter()
+            .any(|f| matches!(f.action, Action::Conflict(_) | Action::Skip(_)))
+    }
+
+    /// Print what the update did
+    pub fn print(&self) {
+        if self.files.is_empty() {
+            println!("The project is up to date with its template.");
+            return;
+        }
+        for file in &self.files {
+            let path = file.path.display();
+            match &file.action {
+                Action::Keep => {}
+                Action::Write(_) => println!("  updated   {}", path),
+                Action::Merge(_) => println!("  merged    {}", path),
+                Action::Conflict(_) => println!("  conflict  {}", path),
+                Action::Remove => println!("  removed   {}", path),
+                Action::Skip(reason) => println!("  skipped   {} ({})", path, reason),
+            }
+        }
+        if dry_run::is_dry_run() {
+            return;
+        }
+        if let (Some(from), Some(to)) = (&self.from, &self.to) {
+            println!("Updated from {} to {}.", short(from), short(to));
+        }
+        if self.has_conflicts() {
+            println!("Resolve the conflicts and skipped files above before committing.");
+        }
+    }
+}
+
+/// Update the current project to the latest version of its template
+///
+/// A `reference` moves the project to that tag, branch or commit of the
+/// template, otherwise it stays on the one it's pinned to, if any.
+pub fn update(reference: Option<&str>) -> Result<UpdateReport> {
+    let angreal_dir = is_angreal_project()?;
+    let root = angreal_dir
+        .parent()
+        .ok_or_else(|| anyhow!("Unable to find the project root"))?
+        .to_path_buf();
+    let answers = angreal_dir.join("angreal.toml");
+    let provenance = Provenance::read(&answers)?.ok_or_else(|| {
+        anyhow!(
+            "{} doesn't record the template the project was created from",
+            answers.display()
+        )
+    })?;
+
+    debug!("Updating from template {}", provenance.source);
+    let mut source = provenance.template_source();
+    if let Some(reference) = reference {
+        source.reference = Some(reference.to_string());
+    }
+    let template = resolve_template(&source);
+    let latest = template_commit(&template);
+    let mut report = UpdateReport {
+        from: provenance.commit.clone(),
+        to: latest.clone(),
+        files: Vec::new(),
+    };
+    if latest.is_some() && latest == provenance.commit {
+        return Ok(report);
+    }
+
+    let scratch = TempDir::new().context("Failed to create a scratch directory")?;
+    let theirs = scratch.path().join("latest");
+    render(&template, &answers, &theirs)?;
+
+    let base = match &provenance.commit {
+        Some(commit) => match checkout(&template, commit, &scratch.path().join("checkout")) {
+            Ok(old_template) => {
+                let base = scratch.path().join("base");
+                render(&old_template, &answers, &base)?;
+                Some(base)
+            }
+            Err(e) => {
+                warn!(
+                    "Unable to check out the template at {}, changes will be treated as conflicts: {:#}",
+                    short(commit),
+                    e
+                );
+                None
+            }
+        },
+        None => None,
+    };
+
+    let mut paths = files(&theirs);
+    if let Some(base) = &base {
+        paths.extend(files(base));
+    }
+    paths.remove(Path::new(ANSWERS_FILE));
+
+    for path in paths {
+        let action = plan(
+            base.as_ref().and_then(|b| read(&b.join(&path))).as_deref(),
+            read(&root.join(&path)).as_deref(),
+            read(&theirs.join(&path)).as_deref(),
+        );
+        if action == Action::Keep {
+            continue;
+        }
+        apply(&root, &theirs, &path, &action)?;
+        report.files.push(FileUpdate { path, action });
+    }
+
+    // A template without commits is only known to have been re-rendered when
+    // something was applied
+    if (latest.is_some() || !report.files.is_empty()) && !dry_run::is_dry_run() {
+        Provenance::new(&source, &template).write(&answers)?;
+    }
+    Ok(report)
+}
+
+/// Decide what to do with a file given its contents in the template the
+/// project was rendered from (`base`), the project (`ours`) and the latest
+/// template (`theirs`)
+pub fn plan(base: Option<&[u8]>, ours: Option<&[u8]>, theirs: Option<&[u8]>) -> Action {
+    if theirs == base || ours == theirs {
+        return Action::Keep;
+    }
+    if ours == base {
+        return match theirs {
+            Some(theirs) => Action::Write(theirs.to_vec()),
+            None => Action::Remove,
+        };
+    }
+    let (ours, theirs) = match (ours, theirs) {
+        (Some(ours), Some(theirs)) => (ours, theirs),
+        (None, _) => return Action::Skip("deleted in the project"),
+        (_, None) => return Action::Skip("removed from the template"),
+    };
+    let texts = (text(base.unwrap_or_default()), text(ours), text(theirs));
+    let (base, ours, theirs) = match texts {
+        (Some(base), Some(ours), Some(theirs)) => (base, ours, theirs),
+        _ => return Action::Skip("binary file changed on both sides"),
+    };
+    match MergeOptions::new()
+        .set_conflict_style(ConflictStyle::Merge)
+        .merge(base, ours, theirs)
+    {
+        Ok(merged) => Action::Merge(merged.into_bytes()),
+        Err(conflicted) => Action::Conflict(label_conflicts(&conflicted).into_bytes()),
+    }
+}
+
+/// Name the sides of conflict markers after where they came from
+fn label_conflicts(merged: &str) -> String {
+    merged
+        .split_inclusive('\n')
+        .map(|line| {
+            // A side without a final newline runs into the closing marker
+            let marker = line.trim_end_matches('\n');
+            if marker == "<<<<<<< ours" {
+                line.replacen("ours", "project", 1)
+            } else if marker.ends_with(">>>>>>> theirs") {
+                format!(
+                    "{}template{}",
+                    &marker[..marker.len() - 6],
+                    &line[marker.len()..]
+                )
+            } else {
+                line.to_string()
+            }
+        })
+        .collect()
+}
+
+fn apply(root: &Path, theirs: &Path, path: &Path, action: &Action) -> Result<()> {
+    let destination = root.join(path);
+    let contents = match action {
+        Action::Keep | Action::Skip(_) => return Ok(()),
+        Action::Remove => {
+            if dry_run::is_dry_run() {
+                dry_run::report(&format!("remove {}", destination.display()));
+                return Ok(());
+            }
+            return fs::remove_file(&destination)
+                .with_context(|| format!("Failed to remove {}", destination.display()));
+        }
+        Action::Write(contents) | Action::Merge(contents) | Action::Conflict(contents) => contents,
+    };
+    if dry_run::is_dry_run() {
+        dry_run::report(&format!("write {}", destination.display()));
+        return Ok(());
+    }
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&destination, contents)
+        .with_context(|| format!("Failed to write {}", destination.display()))?;
+    if let Ok(metadata) = fs::metadata(theirs.join(path)) {
+        let _ = fs::set_permissions(&destination, metadata.permissions());
+    }
+    Ok(())
+}
+
+/// Render a template in place into `dst` with the project's answers
+///
+/// Variables the template added since the project was created get their
+/// defaults.
+fn render(template: &Path, answers: &Path, dst: &Path) -> Result<()> {
+    let mut context = repl_context_from_toml(template.join("angreal.toml"), false);
+    for (key, value) in extract_key_defaults(answers.to_path_buf())? {
+        context.insert(key, &value);
+    }
+    fs::create_dir_all(dst)?;
+    dry_run::suspended(|| render_dir(template, context, dst, true, true))?;
+    Ok(())
+}
+
+/// Check out a template as it was at `commit`, returning its directory
+fn checkout(template: &Path, commit: &str, dst: &Path) -> Result<PathBuf> {
+    let repo = Repository::discover(template)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("The template's repository has no working directory"))?;
+    let subdir = fs::canonicalize(template)?
+        .strip_prefix(fs::canonicalize(workdir)?)?
+        .to_path_buf();
+    let commit = repo.find_commit(Oid::from_str(commit)?)?;
+
+    fs::create_dir_all(dst)?;
+    let mut options = CheckoutBuilder::new();
+    options.target_dir(dst).update_index(false).force();
+    repo.checkout_tree(commit.as_object(), Some(&mut options))?;
+    Ok(dst.join(subdir))
+}
+
+/// The files under a directory, relative to it
+fn files(dir: &Path) -> BTreeSet<PathBuf> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.path().strip_prefix(dir).ok().map(Path::to_path_buf))
+        .collect()
+}
+
+/// The contents of a file that can be merged line by line
+fn text(contents: &[u8]) -> Option<&str> {
+    if looks_binary(contents) {
+        return None;
+    }
+    std::str::from_utf8(contents).ok()
+}
+
+fn read(path: &Path) -> Option<Vec<u8>> {
+    fs::read(path).ok()
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..commit.len().min(8)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan_str(base: Option<&str>, ours: Option<&str>, theirs: Option<&str>) -> Action {
+        plan(
+            base.map(str::as_bytes),
+            ours.map(str::as_bytes),
+            theirs.map(str::as_bytes),
+        )
+    }
+
+    #[test]
+    fn test_plan() {
+        // Unchanged in the template, or the project already has the change
+        assert_eq!(
+            plan_str(Some("a\n"), Some("b\n"), Some("a\n")),
+            Action::Keep
+        );
+        assert_eq!(
+            plan_str(Some("a\n"), Some("b\n"), Some("b\n")),
+            Action::Keep
+        );
+
+        // Untouched in the project
+        assert_eq!(
+            plan_str(Some("a\n"), Some("a\n"), Some("b\n")),
+            Action::Write(b"b\n".to_vec())
+        );
+        assert_eq!(
+            plan_str(None, None, Some("new\n")),
+            Action::Write(b"new\n".to_vec())
+        );
+        assert_eq!(plan_str(Some("a\n"), Some("a\n"), None), Action::Remove);
+
+        assert_eq!(
+            plan_str(Some("a\n"), None, Some("b\n")),
+            Action::Skip("deleted in the project")
+        );
+        assert_eq!(
+            plan_str(Some("a\n"), Some("b\n"), None),
+            Action::Skip("removed from the template")
+        );
+        assert_eq!(
+            plan(Some(b"\0a"), Some(b"\0b"), Some(b"\0c")),
+            Action::Skip("binary file changed on both sides")
+        );
+    }
+
+    #[test]
+    fn test_plan_merges() {
+        let base = "one\ntwo\nthree\n";
+        assert_eq!(
+            plan_str(
+                Some(base),
+                Some("ONE\ntwo\nthree\n"),
+                Some("one\ntwo\nTHREE\n")
+            ),
+            Action::Merge(b"ONE\ntwo\nTHREE\n".to_vec())
+        );
+
+        let conflicted = match plan_str(
+            Some(base),
+            Some("one\nours\nthree\n"),
+            Some("one\ntheirs\nthree\n"),
+        ) {
+            Action::Conflict(contents) => String::from_utf8(contents).unwrap(),
+            action => panic!("expected a conflict, got {:?}", action),
+        };
+        assert_eq!(
+            conflicted,
+            "one\n<<<<<<< project\nours\n=======\ntheirs\n>>>>>>> template\nthree\n"
+        );
+        assert_eq!(
+            label_conflicts("<<<<<<< ours\na=======\nb>>>>>>> theirs\n"),
+            "<<<<<<< project\na=======\nb>>>>>>> template\n"
+        );
+    }
+}
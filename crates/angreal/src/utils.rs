@@ -1,12 +1,11 @@
 //! Filesystem utilities
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context as _, Result};
 
 use glob::glob;
 use std::convert::TryInto;
 use std::env;
 use std::ops::Not;
 use std::path::{Path, PathBuf};
-use std::process::exit;
 use std::vec::Vec;
 use tera::Context;
 use toml::{map::Map, Table, Value};
@@ -15,8 +14,6 @@ use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList, PyModule};
 use pyo3::PyResult;
 use std::fs;
-use std::fs::File;
-use std::io::Write;
 
 use tera::Tera;
 use text_io::read;
@@ -26,6 +23,8 @@ use version_compare::Version;
 
 use walkdir::WalkDir;
 
+use crate::template::{is_binary, looks_binary, TemplateSettings};
+
 use log::{debug, error, info, warn};
 use pythonize::pythonize;
 
@@ -165,12 +164,10 @@ fn is_templated_segment(segment: &str) -> bool {
 /// into the destination. Exactly one top-level templated directory is required;
 /// zero or more than one is a hard error (the template is ambiguous for
 /// in-place use).
-fn in_place_root(src: &Path) -> String {
+fn in_place_root(src: &Path) -> Result<String> {
     let mut roots: Vec<String> = Vec::new();
-    let entries = fs::read_dir(src).unwrap_or_else(|e| {
-        error!("Failed to read template directory {}: {}", src.display(), e);
-        exit(1);
-    });
+    let entries = fs::read_dir(src)
+        .with_context(|| format!("Failed to read template directory {}", src.display()))?;
     for entry in entries.flatten() {
         if entry.path().is_dir() {
             let name = entry.file_name().to_string_lossy().to_string();
@@ -181,23 +178,17 @@ fn in_place_root(src: &Path) -> String {
     }
 
     match roots.len() {
-        1 => roots.pop().unwrap(),
-        0 => {
-            error!(
-                "--in-place requires the template to have exactly one top-level templated directory (e.g. `{{{{ project_name }}}}`), but none was found in {}.",
-                src.display()
-            );
-            exit(1);
-        }
-        n => {
-            error!(
-                "--in-place requires exactly one top-level templated directory, but found {} in {}: {}. In-place rendering cannot determine which directory to strip.",
-                n,
-                src.display(),
-                roots.join(", ")
-            );
-            exit(1);
-        }
+        1 => Ok(roots.pop().unwrap()),
+        0 => Err(anyhow!(
+            "--in-place requires the template to have exactly one top-level templated directory (e.g. `{{{{ project_name }}}}`), but none was found in {}.",
+            src.display()
+        )),
+        n => Err(anyhow!(
+            "--in-place requires exactly one top-level templated directory, but found {} in {}: {}. In-place rendering cannot determine which directory to strip.",
+            n,
+            src.display(),
+            roots.join(", ")
+        )),
     }
 }
 
@@ -221,119 +212,183 @@ fn dest_rel_path(rendered: &str, in_place: bool) -> Option<PathBuf> {
     }
 }
 
-// Render a templated directory to a destination given a tera context
-pub fn render_dir(
-    src: &Path,
-    context: Context,
-    dst: &Path,
-    force: bool,
-    in_place: bool,
-) -> Vec<String> {
-    let mut rendered_paths: Vec<String> = Vec::new();
-    // we create a Tera instance for an empty directory so we can extend it with our template later
-    let mut tmp_dir = env::temp_dir();
-    tmp_dir.push(Path::new("angreal_tmp"));
+/// A directory or file a template renders to
+#[derive(Debug, Clone)]
+pub struct PlannedPath {
+    /// The template path it's rendered from, relative to the template
+    pub template: String,
+    /// Where it's written, relative to the destination
+    pub path: PathBuf,
+    /// The file's contents, `None` for a directory
+    pub contents: Option<Vec<u8>>,
+    /// Whether the file is copied without going through Tera
+    pub copy_only: bool,
+    /// Whether something already exists at the destination
+    pub exists: bool,
+}
 
-    if tmp_dir.is_dir().not() {
-        debug!("Creating tmpdir at {:?}", tmp_dir);
-        fs::create_dir(&tmp_dir).unwrap();
+impl PlannedPath {
+    pub fn is_dir(&self) -> bool {
+        self.contents.is_none()
     }
+}
 
-    tmp_dir.push(Path::new("*"));
-    let mut tera = Tera::new(tmp_dir.to_str().unwrap()).unwrap();
+/// Everything a template would write to a destination, in the order it's written
+#[derive(Debug, Clone)]
+pub struct RenderPlan {
+    pub dst: PathBuf,
+    /// Directories first, then files
+    pub paths: Vec<PlannedPath>,
+}
 
-    tmp_dir.pop();
-    if tmp_dir.is_dir() {
-        debug!("Destroying tmpdir at {:?}", tmp_dir);
-        fs::remove_dir_all(&tmp_dir).unwrap();
+impl RenderPlan {
+    /// The paths that already exist in the destination
+    pub fn collisions(&self) -> Vec<&PlannedPath> {
+        self.paths.iter().filter(|p| p.exists).collect()
     }
 
-    // We glob our template directory
-    let mut template_src = <&std::path::Path>::clone(&src).to_path_buf();
-    template_src.push(Path::new("**/*"));
+    /// The top-level directories that already exist in the destination
+    pub fn root_collisions(&self) -> Vec<&PlannedPath> {
+        self.paths
+            .iter()
+            .filter(|p| p.exists && p.is_dir() && p.path.components().count() == 1)
+            .collect()
+    }
+
+    /// Describe the plan for people, flagging what would be overwritten
+    ///
+    /// Without `force` anything that already exists is in the way. With
+    /// `diff`, files that exist and would change are followed by a unified
+    /// diff of the change.
+    pub fn format_text(&self, force: bool, diff: bool) -> String {
+        let mut out = String::new();
+        let mut counts = [0; 4];
+        for planned in &self.paths {
+            let existing = if planned.exists && !planned.is_dir() {
+                fs::read(self.dst.join(&planned.path)).ok()
+            } else {
+                None
+            };
+            let (index, label) = match (planned.exists, &existing) {
+                (false, _) => (0, "create"),
+                (true, _) if !force => (2, "conflict"),
+                (true, _) if planned.is_dir() => (3, "exists"),
+                (true, Some(existing)) if Some(existing) == planned.contents.as_ref() => {
+                    (3, "unchanged")
+                }
+                (true, _) => (1, "overwrite"),
+            };
+            counts[index] += 1;
+            let suffix = if planned.is_dir() { "/" } else { "" };
+            out.push_str(&format!(
+                "{:<10} {}{}\n",
+                label,
+                planned.path.display(),
+                suffix
+            ));
+            if let (true, Some(existing), Some(contents)) = (diff, &existing, &planned.contents) {
+                if existing != contents {
+                    out.push_str(&unified_diff(&planned.path, existing, contents));
+                }
+            }
+        }
+        out.push_str(&format!(
+            "{} to create, {} to overwrite, {} in the way, {} left as they are\n",
+            counts[0], counts[1], counts[2], counts[3]
+        ));
+        if counts[2] > 0 {
+            out.push_str(
+                "Nothing is written while paths are in the way, unless `--force` is used.\n",
+            );
+        }
+        out
+    }
+}
+
+/// A unified diff from a file's current contents to its rendered contents
+fn unified_diff(path: &Path, existing: &[u8], rendered: &[u8]) -> String {
+    let (existing, rendered) = match (std::str::from_utf8(existing), std::str::from_utf8(rendered))
+    {
+        (Ok(existing), Ok(rendered))
+            if !looks_binary(existing.as_bytes()) && !looks_binary(rendered.as_bytes()) =>
+        {
+            (existing, rendered)
+        }
+        _ => return format!("Binary files a/{0} and b/{0} differ\n", path.display()),
+    };
+    diffy::DiffOptions::new()
+        .set_original_filename(format!("a/{}", path.display()))
+        .set_modified_filename(format!("b/{}", path.display()))
+        .create_patch(existing, rendered)
+        .to_string()
+}
+
+/// Render a templated directory in memory, working out where each path goes in `dst`
+pub fn plan_dir(src: &Path, context: &Context, dst: &Path, in_place: bool) -> Result<RenderPlan> {
+    let mut tera = Tera::default();
 
-    // And build our full prefix
-    let _template_name = <&std::path::Path>::clone(&src).file_name().unwrap();
+    // We glob our template directory
+    let template_src = src.join("**/*");
 
     // In-place mode strips the single top-level templated directory. Validate it
     // up front so we fail fast on ambiguous templates before writing anything.
     if in_place {
-        let _ = in_place_root(src);
+        in_place_root(src)?;
     }
 
-    for file in glob(template_src.to_str().unwrap()).expect("Failed to read glob pattern") {
-        let file_path = file.as_ref().unwrap();
-        let rel_path = file_path.strip_prefix(src).unwrap().to_str().unwrap();
-
-        if file.as_ref().unwrap().is_file() && rel_path.starts_with("{{") && rel_path.contains("}}")
-        {
+    let settings = TemplateSettings::load(src)?;
+    // Whether a template path is written, conditions that can't be evaluated
+    // stop the render before anything is written
+    let renders = |template: &str, rendered: &str| settings.renders(template, rendered, context);
+
+    // Template files in the order they're written, copy-only files never go
+    // through Tera since they may contain syntax that isn't meant for it
+    let mut template_files: Vec<(String, bool)> = Vec::new();
+    let pattern = template_src
+        .to_str()
+        .ok_or_else(|| anyhow!("{} isn't a valid UTF-8 path", src.display()))?;
+    for file in glob(pattern)? {
+        let file_path = file?;
+        let rel_path = template_path(&file_path, src)?;
+
+        if file_path.is_file() && is_templated_segment(rel_path) {
+            if settings.is_excluded(rel_path) {
+                debug!("Excluding {:?} from the template.", rel_path);
+                continue;
+            }
+            if settings.is_copy_only(rel_path) || is_binary(&file_path).unwrap_or(false) {
+                debug!("Copying {:?} without rendering.", rel_path);
+                template_files.push((rel_path.to_string(), true));
+                continue;
+            }
             debug!(
                 "Adding template with relative path {:?} to tera instance.",
                 rel_path
             );
 
-            tera.add_template_file(file.as_ref().unwrap().to_str().unwrap(), Some(rel_path))
-                .unwrap();
+            tera.add_template_file(&file_path, Some(rel_path))
+                .with_context(|| format!("Failed to parse the template {}", rel_path))?;
+            template_files.push((rel_path.to_string(), false));
         }
     }
 
-    // In-place rendering has no single root directory whose existence guards
-    // collisions, so without --force we check every target up front and refuse
-    // to proceed if any already exists in the destination.
-    if in_place && force.not() {
-        let mut collisions: Vec<String> = Vec::new();
-        for entry in WalkDir::new(src)
-            .into_iter()
-            .filter_entry(|e| e.file_type().is_dir())
-        {
-            let entry = entry.unwrap();
-            let rel = entry.path().strip_prefix(src).unwrap().to_str().unwrap();
-            if is_templated_segment(rel) {
-                let real_path = Tera::one_off(rel, &context, false).unwrap();
-                if let Some(dest_rel) = dest_rel_path(&real_path, true) {
-                    if dst.join(&dest_rel).exists() {
-                        collisions.push(dest_rel.to_string_lossy().to_string());
-                    }
-                }
-            }
-        }
-        for template in tera.get_template_names() {
-            if template == "angreal.toml" || template.starts_with('.') {
-                continue;
-            }
-            let path = Tera::one_off(template, &context, false).unwrap();
-            if let Some(dest_rel) = dest_rel_path(&path, true) {
-                if dst.join(&dest_rel).exists() {
-                    collisions.push(dest_rel.to_string_lossy().to_string());
-                }
-            }
-        }
-        if collisions.is_empty().not() {
-            error!(
-                "{} already exist(s) in {}. Will not proceed unless `--force`/force=True is used.",
-                collisions.join(", "),
-                dst.display()
-            );
-            exit(1);
-        }
-    }
+    let mut paths: Vec<PlannedPath> = Vec::new();
 
-    // build our directory structure first
+    // directories first
     let walker = WalkDir::new(src).into_iter();
     for entry in walker.filter_entry(|e| e.file_type().is_dir()) {
-        let path_template = entry.unwrap().clone();
-        let path_postfix = path_template.path();
-        let path_template = path_postfix.strip_prefix(src).unwrap().to_str().unwrap();
+        let entry = entry?;
+        let path_template = template_path(entry.path(), src)?;
 
         // we only render directories that start with a templated path, this is usually a single "root" directory that forms the top level directory of a project.
-        if is_templated_segment(path_template) {
-            let real_path = Tera::one_off(path_template, &context, false).unwrap();
-
-            // The dot-file skip applies to the rendered template path (e.g. a
-            // top-level dot directory), so evaluate it before stripping.
-            if real_path.starts_with('.') {
-                //skip any sort of top level dot files - extend with an exclusion glob in the future
-                // todo: exclusion glob
+        if is_templated_segment(path_template) && settings.is_excluded(path_template).not() {
+            let real_path = render_path(path_template, context)?;
+
+            // Dot-file skips and conditions apply to the rendered template
+            // path (e.g. a top-level dot directory), so evaluate them before
+            // stripping.
+            if renders(path_template, &real_path)?.not() {
+                debug!("Skipping directory {:?}", path_template);
                 continue;
             }
 
@@ -343,55 +398,192 @@ pub fn render_dir(
                 None => continue,
             };
 
-            if dst.join(&dest_rel).is_dir() & force.not() {
-                error!(
-                    "{} already exists. Will not proceed unless `--force`/force=True is used.",
-                    dest_rel.display()
-                )
-            }
-
-            let destination = dst.join(&dest_rel);
-            debug!("Creating directory {:?}", destination);
-            // create_dir_all is idempotent: in-place renders into an existing
-            // directory and may re-create parents on a --force overwrite.
-            fs::create_dir_all(&destination).unwrap();
-            rendered_paths.push(destination.to_string_lossy().to_string());
+            paths.push(PlannedPath {
+                template: path_template.to_string(),
+                exists: dst.join(&dest_rel).exists(),
+                path: dest_rel,
+                contents: None,
+                copy_only: false,
+            });
         }
     }
 
-    // render templates
-    for template in tera.get_template_names() {
-        if template == "angreal.toml" {
-            // never render the angreal.toml
-            // todo: exclusion glob
+    // then the files
+    for (template, copy_only) in template_files {
+        let path = render_path(&template, context)?;
+        if renders(&template, &path)?.not() {
+            debug!("Skipping {:?}", template);
             continue;
         }
 
-        if template.starts_with('.') {
-            // we don't render dot files either
-            // todo: exclusion glob
-            continue;
-        }
-
-        let rendered = tera.render(template, &context).unwrap();
-        let path = Tera::one_off(template, &context, false).unwrap();
-
         // In-place mode strips the root component; `None` is the root itself.
         let dest_rel = match dest_rel_path(&path, in_place) {
             Some(p) => p,
             None => continue,
         };
 
-        let destination = dst.join(&dest_rel);
-        debug!("Rendering file at {:?}", destination);
-        let mut output = File::create(&destination).unwrap();
-        write!(output, "{}", rendered.as_str()).unwrap();
+        let contents = if copy_only {
+            fs::read(src.join(&template))
+                .with_context(|| format!("Failed to read the template {}", template))?
+        } else {
+            tera.render(&template, context)
+                .with_context(|| format!("Failed to render the template {}", template))?
+                .into_bytes()
+        };
+        paths.push(PlannedPath {
+            exists: dst.join(&dest_rel).exists(),
+            template,
+            path: dest_rel,
+            contents: Some(contents),
+            copy_only,
+        });
+    }
+
+    Ok(RenderPlan {
+        dst: dst.to_path_buf(),
+        paths,
+    })
+}
+
+/// A path within a template, relative to the template's root
+fn template_path<'a>(path: &'a Path, src: &Path) -> Result<&'a str> {
+    let relative = path.strip_prefix(src)?;
+    relative
+        .to_str()
+        .ok_or_else(|| anyhow!("{} isn't a valid UTF-8 path", relative.display()))
+}
+
+/// Render a templated path, e.g. `{{ name }}/README.md`
+fn render_path(template: &str, context: &Context) -> Result<String> {
+    Tera::one_off(template, context, false)
+        .with_context(|| format!("Failed to render the path {}", template))
+}
+
+// Render a templated directory to a destination given a tera context
+//
+// Without force, an in-place render refuses to overwrite anything, otherwise
+// only a top-level directory the template renders that already exists is in the
+// way.
+pub fn render_dir(
+    src: &Path,
+    context: Context,
+    dst: &Path,
+    force: bool,
+    in_place: bool,
+) -> Result<Vec<String>> {
+    let plan = plan_dir(src, &context, dst, in_place)?;
+    if force.not() {
+        let collisions = if in_place {
+            plan.collisions()
+        } else {
+            plan.root_collisions()
+        };
+        refuse_collisions(&collisions, dst)?;
+    }
+    write_plan(src, &plan, dst)
+}
+
+/// Render a templated directory for `dst`, writing it to `target` instead
+///
+/// Collisions are checked against `dst`, so a template can be rendered into an
+/// empty staging directory and moved into place once it's complete. Without
+/// `force`, any path that already exists in `dst` is in the way.
+pub fn render_dir_into(
+    src: &Path,
+    context: Context,
+    dst: &Path,
+    target: &Path,
+    force: bool,
+    in_place: bool,
+) -> Result<Vec<String>> {
+    let plan = plan_dir(src, &context, dst, in_place)?;
+    // Every target is checked up front, before anything is written
+    if force.not() {
+        refuse_collisions(&plan.collisions(), dst)?;
+    }
+    write_plan(src, &plan, target)
+}
+
+fn refuse_collisions(collisions: &[&PlannedPath], dst: &Path) -> Result<()> {
+    if collisions.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "{} already exist(s) in {}. Will not proceed unless `--force`/force=True is used.",
+        collisions
+            .iter()
+            .map(|p| p.path.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(", "),
+        dst.display()
+    ))
+}
+
+/// Write a rendered template to `target`
+fn write_plan(src: &Path, plan: &RenderPlan, target: &Path) -> Result<Vec<String>> {
+    let mut rendered_paths: Vec<String> = Vec::new();
+    for planned in &plan.paths {
+        let destination = target.join(&planned.path);
+        let contents = match &planned.contents {
+            Some(contents) => contents,
+            None => {
+                if crate::dry_run::is_dry_run() {
+                    crate::dry_run::report(&format!("create directory {}", destination.display()));
+                    rendered_paths.push(destination.to_string_lossy().to_string());
+                    continue;
+                }
+                debug!("Creating directory {:?}", destination);
+                // create_dir_all is idempotent: in-place renders into an existing
+                // directory and may re-create parents on a --force overwrite.
+                fs::create_dir_all(&destination)
+                    .with_context(|| format!("Failed to create {}", destination.display()))?;
+                rendered_paths.push(destination.to_string_lossy().to_string());
+                continue;
+            }
+        };
+
+        if crate::dry_run::is_dry_run() {
+            let verb = if planned.copy_only { "copy" } else { "render" };
+            crate::dry_run::report(&format!(
+                "{} {} to {}",
+                verb,
+                planned.template,
+                destination.display()
+            ));
+            rendered_paths.push(destination.to_string_lossy().to_string());
+            continue;
+        }
+        debug!("Writing file at {:?}", destination);
+        fs::write(&destination, contents)
+            .with_context(|| format!("Failed to write {}", destination.display()))?;
+        copy_executable_bit(&src.join(&planned.template), &destination);
         rendered_paths.push(destination.to_string_lossy().to_string());
     }
 
-    rendered_paths
+    Ok(rendered_paths)
+}
+
+/// Keep the executable bit on scripts, leaving the rest of the mode to the umask
+#[cfg(unix)]
+fn copy_executable_bit(template: &Path, destination: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    let executable = match fs::metadata(template) {
+        Ok(metadata) => metadata.permissions().mode() & 0o111,
+        Err(_) => return,
+    };
+    if executable == 0 {
+        return;
+    }
+    if let Ok(metadata) = fs::metadata(destination) {
+        let mut permissions = metadata.permissions();
+        permissions.set_mode(permissions.mode() | executable);
+        let _ = fs::set_permissions(destination, permissions);
+    }
 }
 
+#[cfg(not(unix))]
+fn copy_executable_bit(_template: &Path, _destination: &Path) {}
+
 pub fn check_up_to_date() -> Result<()> {
     let client = reqwest::blocking::Client::new();
     let response_result = client
@@ -494,7 +686,8 @@ pub fn render_directory(
         }
     }
 
-    let x = render_dir(src, ctx, dst, force, false);
+    let x = render_dir(src, ctx, dst, force, false)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("{:#}", e)))?;
     Ok(pythonize_this!(x))
     // src: &Path, context: Context, dst: &Path, force: bool
 }
@@ -626,6 +819,67 @@ pub fn is_angreal_project() -> Result<PathBuf> {
     }
 }
 
+/// Get the project's local cache directory (`.angreal/.cache`)
+///
+/// The directory is created on first use along with a `.gitignore` so that
+/// nothing stored in it (history, fingerprints, locks) is committed.
+pub fn project_cache_dir() -> Result<PathBuf> {
+    let cache_dir = is_angreal_project()?.join(".cache");
+    if !cache_dir.is_dir() {
+        fs::create_dir_all(&cache_dir)?;
+        fs::write(cache_dir.join(".gitignore"), "*\n")?;
+    }
+    Ok(cache_dir)
+}
+
+/// Imports a python file as a named module
+///
+/// The module is named after the file stem and registered in `sys.modules`
+/// with `__file__` set, so tracebacks, pickling and sibling imports behave as
+/// they would for a normal python import. The file's directory is added to
+/// `sys.path` if it isn't already present.
+pub fn import_python_file<'py>(py: Python<'py>, file: &Path) -> PyResult<Bound<'py, PyModule>> {
+    use std::ffi::CString;
+
+    let to_cstring = |s: &str| {
+        CString::new(s).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid C string: {}", e))
+        })
+    };
+
+    let contents = fs::read_to_string(file).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyIOError, _>(format!(
+            "Unable to read {}: {}",
+            file.display(),
+            e
+        ))
+    })?;
+    let module_name = file
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    // Allow the file to search for modules it might be importing
+    if let Some(dir) = file.parent().and_then(|d| d.to_str()) {
+        let sys = py.import("sys")?;
+        let path_attr = sys.getattr("path")?;
+        let syspath = path_attr.cast::<PyList>()?;
+        if !syspath.contains(dir)? {
+            syspath.insert(0, dir)?;
+        }
+    }
+
+    let contents_cstr = to_cstring(&contents)?;
+    let file_cstr = to_cstring(&file.to_string_lossy())?;
+    let module_cstr = to_cstring(&module_name)?;
+    PyModule::from_code(
+        py,
+        contents_cstr.as_c_str(),
+        file_cstr.as_c_str(),
+        module_cstr.as_c_str(),
+    )
+}
+
 /// Loads a python file as a pyo3 PyModule
 ///
 /// # Example
@@ -636,25 +890,8 @@ pub fn is_angreal_project() -> Result<PathBuf> {
 /// load_python(PathBuf::new("python_file.py"))?;
 /// ```
 pub fn load_python(file: PathBuf) -> Result<(), PyErr> {
-    let mut dir = file.clone();
-    dir.pop();
-
-    let dir = dir.to_str();
-    let contents = fs::read_to_string(file.clone()).unwrap();
-
     let r_value = Python::attach(|py| -> PyResult<()> {
-        // Allow the file to search for modules it might be importing
-        let sys = py.import("sys")?;
-        let path_attr = sys.getattr("path")?;
-        let syspath = path_attr.cast::<PyList>()?;
-        syspath.insert(0, dir)?;
-
-        // Import the file.
-        use std::ffi::CString;
-        let contents_cstr = CString::new(contents.as_str()).map_err(|e| {
-            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid C string: {}", e))
-        })?;
-        let result = PyModule::from_code(py, contents_cstr.as_c_str(), c"", c"");
+        let result = import_python_file(py, &file);
 
         match result {
             Ok(_result) => {
@@ -710,10 +947,13 @@ pub fn extract_key_defaults(toml_path: PathBuf) -> Result<Map<String, Value>> {
     let mut defaults = Map::new();
 
     // Process each key-value pair in the root level (skipping prompt and validation sections)
-    for (k, v) in extract
-        .iter()
-        .filter(|(key, _)| *key != "prompt" && *key != "validation")
-    {
+    // Settings tables added since are only skipped when they are tables, an
+    // answer that happens to share their name is kept
+    for (k, v) in extract.iter().filter(|(key, value)| match key.as_str() {
+        "prompt" | "validation" => false,
+        "template" | "conditions" | "_angreal" | "tasks" => !value.is_table(),
+        _ => true,
+    }) {
         defaults.insert(k.clone(), v.clone());
     }
 
@@ -883,6 +1123,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_load_python_registers_named_module() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let file = root.join("tests/common/test_assets/no_func_task.py");
+        crate::utils::load_python(file.clone()).unwrap();
+
+        Python::attach(|py| {
+            let modules = py.import("sys").unwrap().getattr("modules").unwrap();
+            let module = modules.get_item("no_func_task").unwrap();
+            let module_file: String = module.getattr("__file__").unwrap().extract().unwrap();
+            assert_eq!(PathBuf::from(module_file), file);
+
+            let sys_path = py.import("sys").unwrap().getattr("path").unwrap();
+            let dir = file.parent().unwrap().to_str().unwrap();
+            let count: usize = sys_path
+                .call_method1("count", (dir,))
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(count, 1);
+        });
+
+        crate::utils::load_python(file.clone()).unwrap();
+        let err =
+            crate::utils::load_python(root.join("tests/common/test_assets/bad_import_task.py"))
+                .unwrap_err();
+        let formatted = crate::error_formatter::PythonErrorFormatter::new(err).format();
+        assert!(formatted.contains("bad_import_task.py"));
+    }
+
     #[test]
     fn test_is_angreal_project() {
         let starting_dir = std::env::current_dir().unwrap();
@@ -969,6 +1239,22 @@ mod tests {
         // Ensure prompt and validation sections are not included
         assert!(defaults.get("prompt").is_none());
         assert!(defaults.get("validation").is_none());
+        assert!(defaults.get("tasks").is_none());
+    }
+
+    #[test]
+    fn test_extract_key_defaults_keeps_answers_named_like_settings() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let toml = tmp.path().join("angreal.toml");
+        fs::write(
+            &toml,
+            "template = \"x\"\ntasks = 3\n\n[conditions]\n\"docs\" = \"use_docs\"\n",
+        )
+        .unwrap();
+        let defaults = extract_key_defaults(toml).unwrap();
+        assert_eq!(defaults.get("template").unwrap().as_str(), Some("x"));
+        assert_eq!(defaults.get("tasks").unwrap().as_integer(), Some(3));
+        assert!(defaults.get("conditions").is_none());
     }
 
     #[test]
@@ -0,0 +1,274 @@
+//! Watch mode, re-running a task when files change
+//!
+//! `angreal watch <command> [args]` runs the command once and then again
+//! every time a relevant file in the project changes. Changes are debounced so
+//! that an editor saving several files triggers a single run, and paths
+//! ignored by the project's `.gitignore` never trigger one.
+
+use anyhow::{anyhow, Context, Result};
+use glob::Pattern;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use log::debug;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use pyo3::prelude::*;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use crate::timeout::INTERRUPT_EXIT_CODE;
+
+/// How often the watcher checks for Ctrl-C while waiting for changes
+const SIGNAL_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How changes are selected and reported
+pub struct WatchOptions {
+    /// Globs, relative to the project root, of the paths to watch. All paths
+    /// are watched when empty.
+    pub paths: Vec<String>,
+    /// Globs, relative to the project root, of paths to ignore
+    pub ignore: Vec<String>,
+    /// How long the filesystem has to be quiet before the task is re-run
+    pub debounce: Duration,
+    /// Clear the terminal before each run instead of printing a separator
+    pub clear: bool,
+}
+
+/// Decides which changed paths should trigger a run
+pub struct ChangeFilter {
+    root: PathBuf,
+    paths: Vec<Pattern>,
+    ignore: Vec<Pattern>,
+    gitignore: Gitignore,
+}
+
+impl ChangeFilter {
+    pub fn new(root: &Path, paths: &[String], ignore: &[String]) -> Result<Self> {
+        let root = root
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", root.display()))?;
+        let root = root.as_path();
+        let compile = |patterns: &[String]| -> Result<Vec<Pattern>> {
+            patterns
+                .iter()
+                .map(|p| Pattern::new(p).map_err(|e| anyhow!("Invalid pattern '{}': {}", p, e)))
+                .collect()
+        };
+
+        let mut builder = GitignoreBuilder::new(root);
+        for file in [root.join(".gitignore"), root.join(".git/info/exclude")] {
+            if file.is_file() {
+                if let Some(e) = builder.add(&file) {
+                    debug!("Unable to read {}: {}", file.display(), e);
+                }
+            }
+        }
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            paths: compile(paths)?,
+            ignore: compile(ignore)?,
+            gitignore: builder.build()?,
+        })
+    }
+
+    /// Whether a change to `path` should trigger a run
+    pub fn is_relevant(&self, path: &Path) -> bool {
+        let path = canonical(path);
+        let path = path.as_path();
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+
+        // angreal writes its own history and fingerprints while tasks run
+        if relative.starts_with(".git") || relative.starts_with(".angreal/.cache") {
+            return false;
+        }
+        if self
+            .gitignore
+            .matched_path_or_any_parents(relative, path.is_dir())
+            .is_ignore()
+        {
+            return false;
+        }
+
+        let relative = relative.to_string_lossy();
+        if self.ignore.iter().any(|p| p.matches(&relative)) {
+            return false;
+        }
+        self.paths.is_empty() || self.paths.iter().any(|p| p.matches(&relative))
+    }
+}
+
+/// A path with symlinks and `..` resolved, as the watcher reports it
+///
+/// A path that no longer exists is resolved through its parent.
+fn canonical(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => canonical(parent).join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Run `run` once, then again whenever a relevant file under `root` changes
+///
+/// Returns when the user interrupts the watcher or a run is interrupted.
+pub fn watch(root: &Path, options: &WatchOptions, mut run: impl FnMut() -> i32) -> Result<()> {
+    let root = &root
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", root.display()))?;
+    let filter = ChangeFilter::new(root, &options.paths, &options.ignore)?;
+
+    let (tx, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })
+    .context("Failed to start the file watcher")?;
+    watcher
+        .watch(root, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}", root.display()))?;
+
+    let mut changed: Option<PathBuf> = None;
+    loop {
+        if options.clear && std::io::stdout().is_terminal() {
+            print!("\x1B[2J\x1B[H");
+        } else if let Some(path) = &changed {
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            println!("\n--- {} changed, re-running ---\n", relative.display());
+        }
+        let _ = std::io::stdout().flush();
+
+        if run() == INTERRUPT_EXIT_CODE {
+            return Ok(());
+        }
+
+        // Changes made by the task itself shouldn't trigger another run,
+        // including the ones the watcher only reports once it has settled
+        if !settle(&events, options.debounce) {
+            return Ok(());
+        }
+
+        println!("Watching for changes, press Ctrl-C to stop.");
+        changed = match next_change(&events, &filter, options.debounce) {
+            Some(path) => Some(path),
+            None => return Ok(()),
+        };
+    }
+}
+
+/// Wait for a relevant change and for the filesystem to settle afterwards
+///
+/// Returns the first relevant path that changed, or `None` if the watcher was
+/// interrupted.
+fn next_change(
+    events: &Receiver<notify::Result<Event>>,
+    filter: &ChangeFilter,
+    debounce: Duration,
+) -> Option<PathBuf> {
+    let relevant = |event: notify::Result<Event>| -> Option<PathBuf> {
+        let event = event.ok()?;
+        if matches!(event.kind, EventKind::Access(_)) {
+            return None;
+        }
+        event.paths.into_iter().find(|p| filter.is_relevant(p))
+    };
+
+    let first = loop {
+        match events.recv_timeout(SIGNAL_CHECK_INTERVAL) {
+            Ok(event) => {
+                if let Some(path) = relevant(event) {
+                    break path;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if Python::attach(|py| py.check_signals()).is_err() {
+                    return None;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return None,
+        }
+    };
+    debug!("{} changed", first.display());
+
+    settle(events, debounce).then_some(first)
+}
+
+/// Discard events until nothing has changed for the debounce period
+///
+/// Returns `false` if the watcher stopped.
+fn settle(events: &Receiver<notify::Result<Event>>, debounce: Duration) -> bool {
+    loop {
+        match events.recv_timeout(debounce) {
+            Ok(_) => continue,
+            Err(RecvTimeoutError::Timeout) => return true,
+            Err(RecvTimeoutError::Disconnected) => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_change_filter() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+        fs::write(root.join(".gitignore"), "*.log\nbuild/\n").unwrap();
+
+        let filter =
+            ChangeFilter::new(root, &strings(&["src/**"]), &strings(&["src/generated/*"])).unwrap();
+        assert!(filter.is_relevant(&root.join("src/lib.rs")));
+        assert!(filter.is_relevant(&root.join("src/nested/mod.rs")));
+        assert!(!filter.is_relevant(&root.join("README.md")));
+        assert!(!filter.is_relevant(&root.join("src/generated/api.rs")));
+        assert!(!filter.is_relevant(&root.join("src/debug.log")));
+        assert!(!filter.is_relevant(Path::new("/elsewhere/src/lib.rs")));
+
+        let everything = ChangeFilter::new(root, &[], &[]).unwrap();
+        assert!(everything.is_relevant(&root.join("README.md")));
+        assert!(!everything.is_relevant(&root.join("build/out.txt")));
+        assert!(!everything.is_relevant(&root.join(".angreal/.cache/history.jsonl")));
+        assert!(!everything.is_relevant(&root.join(".git/index")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_change_filter_resolves_paths() {
+        let tmp = TempDir::new().unwrap();
+        let real = tmp.path().join("real");
+        fs::create_dir_all(real.join("src")).unwrap();
+        fs::write(real.join(".gitignore"), "build/\n").unwrap();
+        let link = tmp.path().join("link");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        // The root is given through a symlink and `..`, events come in resolved
+        let filter = ChangeFilter::new(
+            &link.join("src/.."),
+            &strings(&["src/**"]),
+            &strings(&["src/generated/*"]),
+        )
+        .unwrap();
+        let real = real.canonicalize().unwrap();
+        assert!(filter.is_relevant(&real.join("src/lib.rs")));
+        assert!(filter.is_relevant(&link.join("src/lib.rs")));
+        assert!(filter.is_relevant(&link.join("src/../src/deleted.rs")));
+        assert!(!filter.is_relevant(&real.join("src/generated/api.rs")));
+        assert!(!filter.is_relevant(&link.join("src/generated/api.rs")));
+        assert!(!filter.is_relevant(&real.join("README.md")));
+
+        let everything = ChangeFilter::new(&link, &[], &[]).unwrap();
+        assert!(!everything.is_relevant(&real.join("build/out.txt")));
+        assert!(!everything.is_relevant(&link.join("build/out.txt")));
+    }
+}
@@ -77,10 +77,8 @@ mod clone_tests {
         // List contents of current directory
         println!("Current directory contents:");
         if let Ok(entries) = std::fs::read_dir(".") {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    println!("  {:?}", entry.path());
-                }
+            for entry in entries.flatten() {
+                println!("  {:?}", entry.path());
             }
         }
 
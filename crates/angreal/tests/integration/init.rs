@@ -1,4 +1,5 @@
 use angreal::init::{create_home_dot_angreal, init, render_template};
+use angreal::utils::{plan_dir, render_dir};
 use std::env;
 use std::fs;
 use std::ops::Not;
@@ -12,6 +13,8 @@ fn test_init_from_git() {
         false,
         None,
         false,
+        None,
+        false,
     );
     let mut rendered_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     rendered_root.push(Path::new("angreal_test_project"));
@@ -29,6 +32,8 @@ fn test_init_long() {
         false,
         None,
         false,
+        None,
+        false,
     );
     // clean up rendered
     let mut rendered_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -36,7 +41,15 @@ fn test_init_long() {
     let _ = fs::remove_dir_all(&rendered_root);
     // use the long version
 
-    init("angreal/angreal_test_template", true, false, None, false);
+    init(
+        "angreal/angreal_test_template",
+        true,
+        false,
+        None,
+        false,
+        None,
+        false,
+    );
     let mut rendered_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     rendered_root.push(Path::new("angreal_test_project"));
     let _ = fs::remove_dir_all(&rendered_root);
@@ -55,13 +68,23 @@ fn test_init_values() {
         false,
         values_toml.to_str(),
         false,
+        None,
+        false,
     );
     // clean up rendered
     let mut rendered_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     rendered_root.push(Path::new("folder_name"));
     let _ = fs::remove_dir_all(&rendered_root);
     // use the long version
-    init("angreal/angreal_test_template", true, false, None, false);
+    init(
+        "angreal/angreal_test_template",
+        true,
+        false,
+        None,
+        false,
+        None,
+        false,
+    );
 
     let mut rendered_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     rendered_root.push(Path::new("angreal_test_project"));
@@ -72,7 +95,15 @@ fn test_init_values() {
 #[test]
 fn test_init_short() {
     // clone
-    init("angreal_test_template", true, false, None, false);
+    init(
+        "angreal_test_template",
+        true,
+        false,
+        None,
+        false,
+        None,
+        false,
+    );
 
     let mut rendered_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     rendered_root.push(Path::new("angreal_test_project"));
@@ -84,7 +115,7 @@ fn test_init_short() {
 fn test_render_template() {
     let mut template_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     template_root.push(Path::new("tests/common/test_assets/test_template"));
-    render_template(&template_root, false, true, None, false);
+    render_template(&template_root, false, true, None, false).unwrap();
 
     let mut angreal_toml = template_root.clone();
     angreal_toml.push("angreal.toml");
@@ -121,7 +152,7 @@ fn test_render_template_values() {
 
     let mut template_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     template_root.push(Path::new("tests/common/test_assets/test_template"));
-    render_template(&template_root, false, true, values_toml.to_str(), false);
+    render_template(&template_root, false, true, values_toml.to_str(), false).unwrap();
 
     let mut angreal_toml = template_root.clone();
     angreal_toml.push("angreal.toml");
@@ -212,7 +243,7 @@ fn test_in_place_strips_root() {
     let original = env::current_dir().unwrap();
     env::set_current_dir(&cwd).unwrap();
 
-    render_template(&template, false, true, None, true);
+    render_template(&template, false, true, None, true).unwrap();
 
     env::set_current_dir(&original).unwrap();
 
@@ -226,13 +257,35 @@ fn test_in_place_strips_root() {
     let _ = fs::remove_dir_all(&tmp);
 }
 
-// NOTE on error paths (TC-002 zero roots, TC-003 multiple roots, TC-004
-// collision without --force): `render_dir` aborts the process with `exit(1)`
-// in these cases, which cannot be caught in-process by `#[test]`. They are
-// covered end-to-end (with exit-code assertions) by the Python functional
-// suite in `py_tests/test_functional.py`, which drives the `angreal init`
-// CLI in a subprocess. The Rust tests here cover the success and --force
-// overwrite paths.
+/// TC-002/TC-003/TC-004: ambiguous templates and collisions without --force
+/// are errors, and nothing is written. They're also covered end-to-end by
+/// `py_tests/test_functional.py`.
+#[test]
+fn test_in_place_errors_before_writing() {
+    let tmp = env::temp_dir().join(format!("angreal_inplace_err_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&tmp);
+    let mut context = tera::Context::new();
+    context.insert("folder_variable", "folder_name");
+
+    let ambiguous = make_in_place_template(&tmp.join("ambiguous"), true);
+    let dst = tmp.join("dest");
+    fs::create_dir_all(&dst).unwrap();
+    let err = render_dir(&ambiguous, context.clone(), &dst, false, true).unwrap_err();
+    assert!(err.to_string().contains("found 2"), "{err}");
+    assert!(fs::read_dir(&dst).unwrap().next().is_none());
+
+    let template = make_in_place_template(&tmp, false);
+    fs::write(dst.join("README.rst"), "OLD CONTENT").unwrap();
+    let err = render_dir(&template, context, &dst, false, true).unwrap_err();
+    assert!(err.to_string().contains("README.rst"), "{err}");
+    assert!(dst.join("src").exists().not());
+    assert_eq!(
+        fs::read_to_string(dst.join("README.rst")).unwrap(),
+        "OLD CONTENT"
+    );
+
+    let _ = fs::remove_dir_all(&tmp);
+}
 
 /// TC-005: rendering in-place into a cwd that already contains one of the
 /// template's files overwrites it when --force is set.
@@ -250,7 +303,7 @@ fn test_in_place_force_overwrites_existing() {
     let original = env::current_dir().unwrap();
     env::set_current_dir(&cwd).unwrap();
 
-    render_template(&template, false, true, None, true);
+    render_template(&template, false, true, None, true).unwrap();
 
     env::set_current_dir(&original).unwrap();
 
@@ -262,3 +315,274 @@ fn test_in_place_force_overwrites_existing() {
 
     let _ = fs::remove_dir_all(&tmp);
 }
+
+/// `init()` runs in the staged project, before it's moved to its final path.
+#[test]
+fn test_init_runs_before_the_project_is_in_place() {
+    let tmp = env::temp_dir().join(format!("angreal_init_cwd_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&tmp);
+    let template = make_in_place_template(&tmp, false);
+    fs::write(
+        template
+            .join("{{ folder_variable }}")
+            .join(".angreal")
+            .join("init.py"),
+        "import os\n\ndef init():\n    with open('cwd.txt', 'w') as f:\n        f.write(os.getcwd())\n",
+    )
+    .unwrap();
+
+    let cwd = tmp.join("dest");
+    fs::create_dir_all(&cwd).unwrap();
+    let original = env::current_dir().unwrap();
+    env::set_current_dir(&cwd).unwrap();
+
+    init(
+        template.to_str().unwrap(),
+        false,
+        false,
+        None,
+        false,
+        None,
+        false,
+    );
+
+    env::set_current_dir(&original).unwrap();
+
+    let dot_angreal = cwd.join("folder_name").join(".angreal");
+    let init_cwd = PathBuf::from(fs::read_to_string(dot_angreal.join("cwd.txt")).unwrap());
+    assert!(init_cwd.ends_with("folder_name/.angreal"), "{init_cwd:?}");
+    assert_ne!(init_cwd, dot_angreal.canonicalize().unwrap());
+    // The staging directory it ran in is gone
+    assert!(init_cwd.exists().not());
+    assert_eq!(fs::read_dir(&cwd).unwrap().count(), 1);
+
+    let _ = fs::remove_dir_all(&tmp);
+}
+
+/// `[template]` settings exclude paths and copy files without rendering them.
+#[test]
+fn test_render_dir_template_settings() {
+    let tmp = env::temp_dir().join(format!("angreal_template_settings_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&tmp);
+    let template = tmp.join("template");
+    let inner = template.join("{{ name }}");
+    fs::create_dir_all(inner.join(".github/workflows")).unwrap();
+    fs::create_dir_all(inner.join("pkg/__pycache__")).unwrap();
+    fs::write(
+        template.join("angreal.toml"),
+        r#"name = "demo"
+
+[template]
+exclude = ["**/__pycache__"]
+copy_only = ["**/*.png", "{{ name }}/.github/**"]
+"#,
+    )
+    .unwrap();
+    fs::write(inner.join("README.md"), "# {{ name }}\n").unwrap();
+    fs::write(inner.join(".gitignore"), "*.pyc\n").unwrap();
+    fs::write(
+        inner.join(".github/workflows/ci.yml"),
+        "token: ${{ secrets.TOKEN }}\n",
+    )
+    .unwrap();
+    fs::write(inner.join("logo.png"), [0x89, b'P', b'N', b'G', 0xff, 0xfe]).unwrap();
+    fs::write(inner.join("pkg/__pycache__/mod.pyc"), "cache").unwrap();
+
+    let dst = tmp.join("out");
+    fs::create_dir_all(&dst).unwrap();
+    let mut context = tera::Context::new();
+    context.insert("name", "demo");
+    render_dir(&template, context, &dst, false, false).unwrap();
+
+    let project = dst.join("demo");
+    assert_eq!(
+        fs::read_to_string(project.join("README.md")).unwrap(),
+        "# demo\n"
+    );
+    assert!(project.join(".gitignore").is_file());
+    assert_eq!(
+        fs::read_to_string(project.join(".github/workflows/ci.yml")).unwrap(),
+        "token: ${{ secrets.TOKEN }}\n"
+    );
+    assert_eq!(
+        fs::read(project.join("logo.png")).unwrap(),
+        vec![0x89, b'P', b'N', b'G', 0xff, 0xfe]
+    );
+    assert!(project.join("pkg").is_dir());
+    assert!(project.join("pkg/__pycache__").exists().not());
+
+    let _ = fs::remove_dir_all(&tmp);
+}
+
+/// Binary files are copied as they are and rendered files keep their mode.
+#[test]
+fn test_render_dir_binary_files_and_permissions() {
+    let tmp = env::temp_dir().join(format!("angreal_template_binary_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&tmp);
+    let template = tmp.join("template");
+    let inner = template.join("{{ name }}");
+    fs::create_dir_all(&inner).unwrap();
+    fs::write(template.join("angreal.toml"), "name = \"demo\"\n").unwrap();
+
+    let font = [0x00, 0x01, 0x00, 0x00, b'{', b'{', 0xff, 0x80];
+    fs::write(inner.join("font.ttf"), font).unwrap();
+    fs::write(inner.join("run.sh"), "#!/bin/sh\necho {{ name }}\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(inner.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(inner.join("LICENSE"), "MIT\n").unwrap();
+        fs::set_permissions(inner.join("LICENSE"), fs::Permissions::from_mode(0o444)).unwrap();
+    }
+
+    let dst = tmp.join("out");
+    fs::create_dir_all(&dst).unwrap();
+    let mut context = tera::Context::new();
+    context.insert("name", "demo");
+    render_dir(&template, context, &dst, false, false).unwrap();
+
+    let project = dst.join("demo");
+    assert_eq!(fs::read(project.join("font.ttf")).unwrap(), font);
+    assert_eq!(
+        fs::read_to_string(project.join("run.sh")).unwrap(),
+        "#!/bin/sh\necho demo\n"
+    );
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(project.join("run.sh"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+        // Only the executable bit is copied, a read-only template file renders writable
+        let mode = fs::metadata(project.join("LICENSE"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_ne!(mode & 0o200, 0);
+    }
+
+    let _ = fs::remove_dir_all(&tmp);
+}
+
+/// Outside of `angreal init` and in-place renders, only an existing top-level
+/// directory stops a render without force.
+#[test]
+fn test_render_dir_only_refuses_an_existing_root() {
+    let tmp = env::temp_dir().join(format!("angreal_render_root_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&tmp);
+    let template = make_in_place_template(&tmp, false);
+    let mut context = tera::Context::new();
+    context.insert("folder_variable", "folder_name");
+
+    let dst = tmp.join("dest");
+    fs::create_dir_all(dst.join("other")).unwrap();
+    fs::write(dst.join("README.rst"), "unrelated").unwrap();
+    render_dir(&template, context.clone(), &dst, false, false).unwrap();
+    assert!(dst.join("folder_name").join("README.rst").is_file());
+    assert_eq!(
+        fs::read_to_string(dst.join("README.rst")).unwrap(),
+        "unrelated"
+    );
+
+    let err = render_dir(&template, context.clone(), &dst, false, false).unwrap_err();
+    assert!(err.to_string().contains("folder_name"), "{err}");
+    render_dir(&template, context, &dst, true, false).unwrap();
+
+    let _ = fs::remove_dir_all(&tmp);
+}
+
+/// Paths are skipped when their condition is false or their name renders empty.
+#[test]
+fn test_render_dir_conditions() {
+    let tmp = env::temp_dir().join(format!(
+        "angreal_template_conditions_{}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&tmp);
+    let template = tmp.join("template");
+    let inner = template.join("{{ name }}");
+    fs::create_dir_all(inner.join("docs/api")).unwrap();
+    fs::create_dir_all(inner.join("src")).unwrap();
+    fs::write(
+        template.join("angreal.toml"),
+        r#"name = "demo"
+use_docs = false
+use_docker = false
+
+[conditions]
+"{{ name }}/docs" = "use_docs"
+"{{ name }}/src/*.rs" = "name == 'demo'"
+"#,
+    )
+    .unwrap();
+    fs::write(inner.join("docs/index.md"), "# {{ name }}\n").unwrap();
+    fs::write(inner.join("docs/api/index.md"), "API\n").unwrap();
+    fs::write(inner.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(
+        inner.join("{% if use_docker %}Dockerfile{% endif %}"),
+        "FROM python\n",
+    )
+    .unwrap();
+
+    let dst = tmp.join("out");
+    fs::create_dir_all(&dst).unwrap();
+    let mut context = tera::Context::new();
+    context.insert("name", "demo");
+    context.insert("use_docs", &false);
+    context.insert("use_docker", &false);
+    let rendered = render_dir(&template, context, &dst, false, false).unwrap();
+
+    let project = dst.join("demo");
+    assert!(project.join("src/main.rs").is_file());
+    assert!(project.join("docs").exists().not());
+    assert!(project.join("Dockerfile").exists().not());
+    assert!(rendered.iter().all(|p| !p.contains("docs")));
+
+    let _ = fs::remove_dir_all(&tmp);
+}
+
+/// A plan renders in memory and flags the paths that already exist.
+#[test]
+fn test_plan_dir_flags_existing_paths() {
+    let tmp = env::temp_dir().join(format!("angreal_template_plan_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&tmp);
+    let template = make_in_place_template(&tmp, false);
+
+    let dst = tmp.join("out");
+    fs::create_dir_all(dst.join("folder_name/src")).unwrap();
+    fs::write(dst.join("folder_name/README.rst"), "# old\n").unwrap();
+    let mut context = tera::Context::new();
+    context.insert("folder_variable", "folder_name");
+    let plan = plan_dir(&template, &context, &dst, false).unwrap();
+
+    let readme = plan
+        .paths
+        .iter()
+        .find(|p| p.path == Path::new("folder_name/README.rst"))
+        .unwrap();
+    assert!(readme.exists);
+    assert_eq!(readme.contents.as_deref(), Some(&b"# folder_name\n"[..]));
+    let main = plan
+        .paths
+        .iter()
+        .find(|p| p.path == Path::new("folder_name/src/main.txt"))
+        .unwrap();
+    assert!(main.exists.not());
+    assert!(fs::read_dir(dst.join("folder_name/src"))
+        .unwrap()
+        .next()
+        .is_none());
+
+    let text = plan.format_text(true, true);
+    assert!(text.contains("exists     folder_name/src/\n"));
+    assert!(text.contains("overwrite  folder_name/README.rst\n"));
+    assert!(text.contains("-# old\n+# folder_name\n"));
+    assert!(text.contains("create     folder_name/src/main.txt\n"));
+    assert!(plan
+        .format_text(false, false)
+        .contains("conflict   folder_name/README.rst\n"));
+
+    let _ = fs::remove_dir_all(&tmp);
+}
@@ -0,0 +1,24 @@
+"""Tasks for testing working directory control."""
+import os
+
+import angreal
+
+
+@angreal.command(name="cwd-default", about="print the working directory")
+def cwd_default():
+    print(os.getcwd())
+
+
+@angreal.command(name="cwd-project", about="print the working directory", cwd="project")
+def cwd_project():
+    print(os.getcwd())
+
+
+@angreal.command(name="cwd-path", about="print the working directory", cwd=".angreal")
+def cwd_path():
+    print(os.getcwd())
+
+
+@angreal.command(name="cwd-missing", about="run in a missing directory", cwd="does-not-exist")
+def cwd_missing():
+    print("should not run")
@@ -0,0 +1,38 @@
+"""Tasks for testing dry-run mode."""
+import os
+import subprocess
+import tempfile
+
+import angreal
+from angreal.integrations.git import Git
+
+
+@angreal.command(name="dry-run-git", about="initialize a git repository")
+def dry_run_git():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        Git(working_dir=tmpdir).init()
+        created = os.path.exists(os.path.join(tmpdir, ".git"))
+    print(f"dry_run={angreal.is_dry_run()} created={created}")
+
+
+def _git(cwd, *args):
+    return subprocess.run(["git", *args], cwd=cwd, capture_output=True, text=True)
+
+
+@angreal.command(name="dry-run-git-raw", about="run raw git subcommands")
+def dry_run_git_raw():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        _git(tmpdir, "init", "-q")
+        with open(os.path.join(tmpdir, "a.txt"), "w") as f:
+            f.write("a")
+        git = Git(working_dir=tmpdir)
+        git("add", "a.txt")
+        git.execute("commit", ["-m", "raw"])
+        git("reset", "--hard")
+        _, _, status = git("status", "--short")
+
+        staged = _git(tmpdir, "diff", "--cached", "--name-only").stdout.split()
+        committed = _git(tmpdir, "rev-parse", "--verify", "-q", "HEAD").returncode == 0
+        untouched = os.path.exists(os.path.join(tmpdir, "a.txt"))
+    print(f"staged={staged} committed={committed} untouched={untouched}")
+    print(f"status={status.decode().strip()}")
@@ -0,0 +1,20 @@
+"""Tasks for testing per-command environment variables."""
+import os
+import subprocess
+
+import angreal
+
+
+@angreal.command(
+    name="env-show",
+    about="print variables set for the task",
+    env_files=["env_test.env", "env_test.missing"],
+    env={"ANGREAL_TEST_OVERRIDDEN": "from-decorator", "ANGREAL_TEST_NUMBER": 3},
+)
+def env_show():
+    for name in ("ANGREAL_TEST_FROM_FILE", "ANGREAL_TEST_OVERRIDDEN", "ANGREAL_TEST_NUMBER"):
+        print(f"{name}={os.environ.get(name)}")
+    child = subprocess.run(
+        ["sh", "-c", "echo child=$ANGREAL_TEST_FROM_FILE"], capture_output=True, text=True
+    )
+    print(child.stdout.strip())
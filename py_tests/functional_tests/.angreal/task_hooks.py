@@ -0,0 +1,53 @@
+"""Tasks for testing lifecycle hooks."""
+import sys
+
+import angreal
+
+hooks = angreal.command_group(name="hooks", about="lifecycle hook tests")
+
+
+@angreal.before("hooks.*")
+def announce(command_path, kwargs):
+    print(f"before {command_path} {kwargs}")
+
+
+@angreal.before("hooks.blocked")
+def veto(command_path, kwargs):
+    raise RuntimeError("blocked by hook")
+
+
+@angreal.after("hooks.*")
+def report(command_path, kwargs, result):
+    print(f"after {command_path} {result!r}")
+
+
+@angreal.on_error("hooks.*")
+def notify(command_path, kwargs, exception):
+    print(f"on_error {command_path} {type(exception).__name__}")
+
+
+@hooks()
+@angreal.command(name="ok", about="succeeds")
+@angreal.argument(name="value", long="value", takes_value=True)
+def hooks_ok(value=None):
+    print("running ok")
+    return 0
+
+
+@hooks()
+@angreal.command(name="fail", about="raises")
+def hooks_fail():
+    raise ValueError("task failed")
+
+
+@hooks()
+@angreal.command(name="blocked", about="vetoed by a before hook")
+def hooks_blocked():
+    print("should not run")
+
+
+@hooks()
+@angreal.command(name="exit", about="exits with sys.exit")
+@angreal.argument(name="code", long="code", takes_value=True)
+def hooks_exit(code=None):
+    sys.exit(int(code) if code else None)
@@ -0,0 +1,22 @@
+"""Tasks for testing incremental execution."""
+import os
+
+import angreal
+
+here = os.path.dirname(__file__)
+build_dir = os.path.join(here, "..", "incremental_build")
+
+
+@angreal.command(
+    name="incremental-build",
+    about="copy the incremental input to an output",
+    inputs=["incremental_build/input.txt"],
+    outputs=["incremental_build/output.txt"],
+)
+@angreal.argument(name="suffix", long="suffix", takes_value=True, required=False)
+def incremental_build(suffix=None):
+    with open(os.path.join(build_dir, "input.txt")) as f:
+        contents = f.read()
+    with open(os.path.join(build_dir, "output.txt"), "w") as f:
+        f.write(contents + (suffix or ""))
+    print("built")
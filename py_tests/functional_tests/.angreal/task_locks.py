@@ -0,0 +1,17 @@
+"""Tasks for testing run locks."""
+import time
+
+import angreal
+
+
+@angreal.command(name="lock-hold", about="hold the shared lock for a while", lock="lock-test")
+@angreal.argument(name="seconds", long="seconds", takes_value=True, python_type="float")
+def lock_hold(seconds):
+    print("holding", flush=True)
+    time.sleep(seconds)
+    print("released", flush=True)
+
+
+@angreal.command(name="lock-quick", about="take the shared lock briefly", lock="lock-test")
+def lock_quick():
+    print("quick ran")
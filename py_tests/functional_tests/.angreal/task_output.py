@@ -0,0 +1,34 @@
+"""Tasks for testing structured results."""
+import dataclasses
+import subprocess
+
+import angreal
+
+
+@dataclasses.dataclass
+class LintReport:
+    success: bool
+    problems: list
+
+
+@angreal.command(name="output-report", about="return a structured report")
+@angreal.argument(name="fail", long="fail", is_flag=True, takes_value=False)
+def output_report(fail=False):
+    print("checking files")
+    return LintReport(success=not fail, problems=["a.py:1"] if fail else [])
+
+
+@angreal.command(name="output-dict", about="return a dictionary")
+def output_dict():
+    return {"version": "1.2.3", "artifacts": ["dist/a.whl"]}
+
+
+@angreal.command(name="output-subprocess", about="run a child process")
+def output_subprocess():
+    subprocess.run(["echo", "x"], check=True)
+    return {"ran": True}
+
+
+@angreal.command(name="output-raise", about="raise an exception")
+def output_raise():
+    raise ValueError("something broke")
@@ -0,0 +1,43 @@
+"""Tasks for testing retry policies."""
+import os
+
+import angreal
+
+here = os.path.dirname(__file__)
+attempts_file = os.path.join(here, "..", "retry_attempts.txt")
+
+
+def _attempt():
+    count = 1
+    if os.path.exists(attempts_file):
+        with open(attempts_file) as f:
+            count = int(f.read()) + 1
+    with open(attempts_file, "w") as f:
+        f.write(str(count))
+    return count
+
+
+@angreal.command(
+    name="retry-flaky",
+    about="fail with ConnectionError until the given attempt",
+    retries=3,
+    retry_delay="0.1s",
+    retry_on=[ConnectionError],
+)
+@angreal.argument(name="succeed_on", long="succeed-on", takes_value=True, python_type="int")
+def retry_flaky(succeed_on):
+    attempt = _attempt()
+    if attempt < succeed_on:
+        raise ConnectionRefusedError(f"attempt {attempt}")
+    print(f"succeeded on attempt {attempt}")
+
+
+@angreal.command(name="retry-wrong-error", about="raise an error that isn't retried", retries=3, retry_on=ConnectionError)
+def retry_wrong_error():
+    _attempt()
+    raise ValueError("not retried")
+
+
+@angreal.command(name="retry-exit-code", about="return non-zero until attempt two", retries=1)
+def retry_exit_code():
+    return 0 if _attempt() >= 2 else 3
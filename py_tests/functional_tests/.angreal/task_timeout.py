@@ -0,0 +1,28 @@
+import time
+
+import angreal
+
+
+@angreal.command(name="timeout-sleep", about="sleep past its own timeout", timeout="1s")
+@angreal.argument(name="seconds", long="seconds", takes_value=True, required=False)
+def timeout_sleep(seconds=None):
+    time.sleep(float(seconds or 30))
+    print("finished sleeping")
+
+
+@angreal.command(name="timeout-cleanup", about="clean up when interrupted", timeout=1)
+def timeout_cleanup():
+    try:
+        time.sleep(30)
+    except KeyboardInterrupt:
+        print("cleaning up")
+        raise
+
+
+@angreal.command(name="timeout-integration", about="block in an integration's child")
+def timeout_integration():
+    from angreal.integrations.flox import Flox
+
+    # The test puts a stand-in flox on PATH that execs the command
+    Flox(path=".").run("sleep", ["30"])
+    print("finished sleeping")
@@ -0,0 +1,14 @@
+"""Tasks for testing the timings report."""
+import time
+
+import angreal
+
+
+@angreal.before("timings-*")
+def timings_setup(command_path, kwargs):
+    time.sleep(0.05)
+
+
+@angreal.command(name="timings-sleep", about="sleep for a moment")
+def timings_sleep():
+    time.sleep(0.2)
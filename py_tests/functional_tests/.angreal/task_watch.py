@@ -0,0 +1,8 @@
+"""Tasks for testing watch mode."""
+import angreal
+
+
+@angreal.command(name="watch-echo", about="print a message for every run")
+@angreal.argument(name="message", long="message", takes_value=True, required=False)
+def watch_echo(message=None):
+    print(f"ran {message or ''}".strip(), flush=True)
@@ -0,0 +1,80 @@
+"""Tests for rendering templates from .tar.gz and .zip archives."""
+import functools
+import hashlib
+import http.server
+import os
+import subprocess
+import tarfile
+import tempfile
+import threading
+import zipfile
+
+FILES = {
+    "python/angreal.toml": 'name = "demo"\n',
+    "python/{{ name }}/.angreal/task_noop.py": "import angreal\n",
+    "python/{{ name }}/README.md": "# {{ name }}\n",
+}
+
+
+def _archive(root, name):
+    src = os.path.join(root, "src")
+    for path, contents in FILES.items():
+        os.makedirs(os.path.dirname(os.path.join(src, path)), exist_ok=True)
+        with open(os.path.join(src, path), "w") as f:
+            f.write(contents)
+    archive = os.path.join(root, name)
+    if name.endswith(".zip"):
+        with zipfile.ZipFile(archive, "w") as z:
+            for path in FILES:
+                z.write(os.path.join(src, path), path)
+    else:
+        with tarfile.open(archive, "w:gz") as t:
+            t.add(os.path.join(src, "python"), "python")
+    with open(archive, "rb") as f:
+        return archive, hashlib.sha256(f.read()).hexdigest()
+
+
+def _init(root, template):
+    env = dict(os.environ, HOME=root)
+    return subprocess.run(
+        ["angreal", "init", "--defaults", template],
+        cwd=root, env=env, capture_output=True, text=True,
+    )
+
+
+def _readme(root):
+    with open(os.path.join(root, "demo", "README.md")) as f:
+        return f.read()
+
+
+def test_init_from_local_archives():
+    for name in ["python.tar.gz", "python.zip"]:
+        with tempfile.TemporaryDirectory() as root:
+            archive, sha256 = _archive(root, name)
+            rv = _init(root, f"{archive}#sha256={sha256}")
+            assert rv.returncode == 0, rv.stderr
+            assert _readme(root) == "# demo\n"
+            with open(os.path.join(root, "demo", ".angreal", "angreal.toml")) as f:
+                assert f'sha256 = "{sha256}"' in f.read()
+
+
+def test_init_from_http_archive():
+    with tempfile.TemporaryDirectory() as root:
+        _, sha256 = _archive(root, "python.tar.gz")
+        handler = functools.partial(
+            http.server.SimpleHTTPRequestHandler, directory=root
+        )
+        server = http.server.ThreadingHTTPServer(("127.0.0.1", 0), handler)
+        threading.Thread(target=server.serve_forever, daemon=True).start()
+        url = f"http://127.0.0.1:{server.server_port}/python.tar.gz"
+        try:
+            rv = _init(root, f"{url}#sha256={'0' * 64}")
+            assert rv.returncode != 0
+            assert "Checksum mismatch" in rv.stderr
+            assert not os.path.exists(os.path.join(root, "demo"))
+
+            rv = _init(root, f"{url}#sha256={sha256}")
+            assert rv.returncode == 0, rv.stderr
+            assert _readme(root) == "# demo\n"
+        finally:
+            server.shutdown()
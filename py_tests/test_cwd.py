@@ -0,0 +1,45 @@
+"""Tests for task working directory control."""
+import os
+import subprocess
+
+here = os.path.dirname(__file__)
+functional_test_folder = os.path.join(here, "functional_tests")
+invocation_folder = os.path.join(functional_test_folder, ".angreal")
+
+
+def _run(*args, cwd=functional_test_folder):
+    return subprocess.run(
+        ["angreal", *args],
+        cwd=cwd,
+        capture_output=True,
+        text=True,
+    )
+
+
+def _same(a, b):
+    return os.path.realpath(a) == os.path.realpath(b)
+
+
+def test_default_runs_in_invocation_directory():
+    rv = _run("cwd-default", cwd=invocation_folder)
+    assert rv.returncode == 0
+    assert _same(rv.stdout.strip(), invocation_folder)
+
+
+def test_project_runs_in_project_root():
+    rv = _run("cwd-project", cwd=invocation_folder)
+    assert rv.returncode == 0
+    assert _same(rv.stdout.strip(), functional_test_folder)
+
+
+def test_path_is_relative_to_project_root():
+    rv = _run("cwd-path")
+    assert rv.returncode == 0
+    assert _same(rv.stdout.strip(), invocation_folder)
+
+
+def test_missing_directory_fails():
+    rv = _run("cwd-missing")
+    assert rv.returncode == 1
+    assert "should not run" not in rv.stdout
+    assert "does-not-exist" in rv.stderr
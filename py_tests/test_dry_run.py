@@ -0,0 +1,41 @@
+"""Tests for the global --dry-run flag."""
+import os
+import subprocess
+
+here = os.path.dirname(__file__)
+functional_test_folder = os.path.join(here, "functional_tests")
+
+
+def _run(*args):
+    return subprocess.run(
+        ["angreal", *args],
+        cwd=functional_test_folder,
+        capture_output=True,
+        text=True,
+    )
+
+
+def test_integrations_act_without_dry_run():
+    """Without --dry-run the git integration creates the repository."""
+    rv = _run("dry-run-git")
+    assert rv.returncode == 0
+    assert "dry_run=False created=True" in rv.stdout
+
+
+def test_dry_run_reports_instead_of_acting():
+    """With --dry-run the git integration only reports what it would do."""
+    rv = _run("--dry-run", "dry-run-git")
+    assert rv.returncode == 0
+    assert "dry_run=True created=False" in rv.stdout
+    assert "[dry-run] git init" in rv.stderr
+
+
+def test_dry_run_covers_raw_git_subcommands():
+    """Raw git calls are reported too, only read-only ones still run."""
+    rv = _run("--dry-run", "dry-run-git-raw")
+    assert rv.returncode == 0, rv.stderr
+    assert "staged=[] committed=False untouched=True" in rv.stdout
+    assert "a.txt" in rv.stdout.splitlines()[-1]
+    assert "[dry-run] git add a.txt" in rv.stderr
+    assert "[dry-run] git commit -m raw" in rv.stderr
+    assert "[dry-run] git reset --hard" in rv.stderr
@@ -0,0 +1,24 @@
+"""Tests for env and env_files on commands."""
+import os
+import subprocess
+
+here = os.path.dirname(__file__)
+functional_test_folder = os.path.join(here, "functional_tests")
+
+
+def test_command_environment():
+    """Declared variables and dotenv files are visible to the task and its children."""
+    env = dict(os.environ)
+    env.pop("ANGREAL_TEST_FROM_FILE", None)
+    rv = subprocess.run(
+        ["angreal", "env-show"],
+        cwd=functional_test_folder,
+        capture_output=True,
+        text=True,
+        env=env,
+    )
+    assert rv.returncode == 0
+    assert "ANGREAL_TEST_FROM_FILE=from-file" in rv.stdout
+    assert "ANGREAL_TEST_OVERRIDDEN=from-decorator" in rv.stdout
+    assert "ANGREAL_TEST_NUMBER=3" in rv.stdout
+    assert "child=from-file" in rv.stdout
@@ -0,0 +1,57 @@
+"""Tests for angreal exec."""
+import os
+import subprocess
+import sys
+import tempfile
+
+
+def _project(root):
+    angreal_dir = os.path.join(root, ".angreal")
+    os.makedirs(angreal_dir)
+    os.makedirs(os.path.join(root, "sub"))
+    with open(os.path.join(angreal_dir, "angreal.toml"), "w") as f:
+        f.write(
+            'project_name = "demo"\nenv = "prod"\n\n'
+            '[tasks.env]\nGREETING = "hello {{ project_name }} {{ env }}"\n'
+        )
+    with open(os.path.join(angreal_dir, "task_noop.py"), "w") as f:
+        f.write("import angreal\n\n@angreal.command(name='noop')\ndef noop():\n    pass\n")
+
+
+def _exec(root, *command):
+    return subprocess.run(
+        ["angreal", "exec", "--", *command],
+        cwd=os.path.join(root, "sub"),
+        capture_output=True,
+        text=True,
+    )
+
+
+def test_exec_runs_in_project_root_with_env():
+    with tempfile.TemporaryDirectory() as root:
+        _project(root)
+        rv = _exec(root, 'pwd; echo "$GREETING"')
+        assert rv.returncode == 0
+        lines = rv.stdout.splitlines()
+        assert os.path.realpath(lines[0]) == os.path.realpath(root)
+        assert lines[1] == "hello demo prod"
+
+
+def test_exec_activates_venv():
+    with tempfile.TemporaryDirectory() as root:
+        _project(root)
+        subprocess.run(
+            [sys.executable, "-m", "venv", "--without-pip", os.path.join(root, ".venv")],
+            check=True,
+        )
+        rv = _exec(root, "python", "-c", "import sys; print(sys.prefix)")
+        assert rv.returncode == 0
+        venv = os.path.realpath(os.path.join(root, ".venv"))
+        assert os.path.realpath(rv.stdout.strip()) == venv
+
+
+def test_exec_exit_code():
+    with tempfile.TemporaryDirectory() as root:
+        _project(root)
+        rv = _exec(root, "sh", "-c", "exit 3")
+        assert rv.returncode == 3
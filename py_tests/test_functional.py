@@ -125,6 +125,113 @@ def test_init_in_place_collision_requires_force():
         shutil.rmtree(work, ignore_errors=True)
 
 
+def test_init_dry_run_shows_plan():
+    """`angreal init --dry-run` lists what it would write and writes nothing."""
+    work = tempfile.mkdtemp()
+    try:
+        template = _make_in_place_template(work)
+        dest = os.path.join(work, "dest")
+        os.makedirs(dest)
+        rv = subprocess.run(
+            ["angreal", "init", template, "-d", "--dry-run"],
+            cwd=dest, capture_output=True, text=True,
+        )
+        assert rv.returncode == 0, f"stdout={rv.stdout}\nstderr={rv.stderr}"
+        assert "create     folder_name/\n" in rv.stdout
+        assert "create     folder_name/src/main.txt\n" in rv.stdout
+        assert "run init() from folder_name/.angreal/init.py" in rv.stdout
+        assert os.listdir(dest) == []
+    finally:
+        shutil.rmtree(work, ignore_errors=True)
+
+
+def test_init_dry_run_flags_existing_files():
+    """Existing files are conflicts without --force, and diffs are optional."""
+    work = tempfile.mkdtemp()
+    try:
+        template = _make_in_place_template(work)
+        dest = os.path.join(work, "dest")
+        os.makedirs(dest)
+        with open(os.path.join(dest, "README.md"), "w") as f:
+            f.write("OLD CONTENT\n")
+
+        def init(*args):
+            return subprocess.run(
+                ["angreal", "init", template, "--in-place", "-d", *args],
+                cwd=dest, capture_output=True, text=True,
+            )
+
+        rv = init("--dry-run")
+        assert rv.returncode == 0, f"stdout={rv.stdout}\nstderr={rv.stderr}"
+        assert "conflict   README.md\n" in rv.stdout
+        assert "--- a/README.md" not in rv.stdout
+        assert "run init()" not in rv.stdout
+
+        rv = init("--dry-run", "--force", "--diff")
+        assert "overwrite  README.md\n" in rv.stdout
+        assert "-OLD CONTENT\n+# folder_name\n" in rv.stdout
+        with open(os.path.join(dest, "README.md")) as f:
+            assert f.read() == "OLD CONTENT\n"
+        assert sorted(os.listdir(dest)) == ["README.md"]
+    finally:
+        shutil.rmtree(work, ignore_errors=True)
+
+
+def test_init_refuses_existing_project_without_force():
+    """Rendering over an existing project aborts before writing anything."""
+    work = tempfile.mkdtemp()
+    try:
+        template = _make_in_place_template(work)
+        os.makedirs(os.path.join(work, "folder_name"))
+        with open(os.path.join(work, "folder_name", "README.md"), "w") as f:
+            f.write("OLD CONTENT")
+        rv = subprocess.run(
+            ["angreal", "init", template, "-d"],
+            cwd=work, capture_output=True, text=True,
+        )
+        assert rv.returncode != 0
+        assert os.listdir(os.path.join(work, "folder_name")) == ["README.md"]
+    finally:
+        shutil.rmtree(work, ignore_errors=True)
+
+
+def test_init_failure_leaves_nothing_behind():
+    """A failing init() or template aborts init without a half-written project."""
+    work = tempfile.mkdtemp()
+    try:
+        template = _make_in_place_template(work)
+        init_py = os.path.join(template, "{{ folder_variable }}", ".angreal", "init.py")
+        with open(init_py, "w") as f:
+            f.write("def init():\n    raise RuntimeError('boom')\n")
+        dest = os.path.join(work, "dest")
+        os.makedirs(dest)
+
+        rv = subprocess.run(
+            ["angreal", "init", template, "-d"],
+            cwd=dest, capture_output=True, text=True,
+        )
+        assert rv.returncode == 1
+        assert "boom" in rv.stdout
+        assert "the project wasn't created" in rv.stderr
+        assert os.listdir(dest) == []
+
+        with open(init_py, "w") as f:
+            f.write("def init():\n    pass\n")
+        readme = os.path.join(template, "{{ folder_variable }}", "README.md")
+        with open(readme, "w") as f:
+            f.write("{% if %}\n")
+        rv = subprocess.run(
+            ["angreal", "init", template, "-d"],
+            cwd=dest, capture_output=True, text=True,
+        )
+        assert rv.returncode == 1
+        assert "panicked" not in rv.stderr
+        assert "README.md" in rv.stderr
+        assert os.listdir(dest) == []
+    finally:
+        shutil.rmtree(work, ignore_errors=True)
+
+
 def test_group_1():
     """test a basic nested command with flag"""
     rv = subprocess.run([
@@ -0,0 +1,81 @@
+"""Tests for task history and rerun."""
+import json
+import os
+import shutil
+import subprocess
+
+here = os.path.dirname(__file__)
+functional_test_folder = os.path.join(here, "functional_tests")
+cache_folder = os.path.join(functional_test_folder, ".angreal", ".cache")
+
+
+def _run(*args, cwd=functional_test_folder):
+    return subprocess.run(
+        ["angreal", *args],
+        cwd=cwd,
+        capture_output=True,
+        text=True,
+    )
+
+
+def setup_function():
+    shutil.rmtree(cache_folder, ignore_errors=True)
+
+
+def teardown_function():
+    shutil.rmtree(cache_folder, ignore_errors=True)
+
+
+def test_task_invocations_are_recorded():
+    """Each task run is appended to the history file."""
+    _run("exit-zero")
+    _run("exit-sys-exit", "--code", "3")
+
+    with open(os.path.join(cache_folder, "history.jsonl")) as f:
+        entries = [json.loads(line) for line in f]
+
+    assert [e["command"] for e in entries] == ["exit-zero", "exit-sys-exit"]
+    assert entries[1]["args"] == ["exit-sys-exit", "--code", "3"]
+    assert entries[1]["exit_code"] == 3
+    assert entries[1]["id"] == 2
+
+
+def test_history_filters_failed():
+    """angreal history --failed only lists failing invocations."""
+    _run("exit-zero")
+    _run("exit-nonzero")
+
+    rv = _run("history", "--failed")
+    assert rv.returncode == 0
+    assert "angreal exit-nonzero" in rv.stdout
+    assert "angreal exit-zero" not in rv.stdout
+
+
+def test_rerun_replays_arguments_and_exit_code():
+    """angreal rerun replays the selected invocation."""
+    _run("exit-sys-exit", "--code", "3")
+    _run("exit-zero")
+
+    rv = _run("rerun", "1")
+    assert rv.returncode == 3
+    assert "Re-running #1: angreal exit-sys-exit --code 3" in rv.stderr
+
+
+def test_rerun_restores_working_directory():
+    """angreal rerun runs from the directory the invocation was made in."""
+    subdir = os.path.join(functional_test_folder, ".angreal")
+    _run("cwd-default", cwd=subdir)
+
+    with open(os.path.join(cache_folder, "history.jsonl")) as f:
+        entry = json.loads(f.readline())
+    assert os.path.realpath(entry["cwd"]) == os.path.realpath(subdir)
+
+    rv = _run("rerun", "1")
+    assert rv.returncode == 0
+    assert os.path.realpath(rv.stdout.strip()) == os.path.realpath(subdir)
+
+
+def test_rerun_without_history():
+    """angreal rerun fails cleanly when nothing has run yet."""
+    rv = _run("rerun")
+    assert rv.returncode == 1
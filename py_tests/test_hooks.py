@@ -0,0 +1,59 @@
+"""Tests for before/after/on_error lifecycle hooks."""
+import os
+import subprocess
+
+here = os.path.dirname(__file__)
+functional_test_folder = os.path.join(here, "functional_tests")
+
+
+def _run(*args):
+    return subprocess.run(
+        ["angreal", *args],
+        cwd=functional_test_folder,
+        capture_output=True,
+        text=True,
+    )
+
+
+def test_before_and_after_hooks_run_around_task():
+    """before and after hooks wrap a successful task."""
+    rv = _run("hooks", "ok", "--value", "x")
+    assert rv.returncode == 0
+    lines = rv.stdout.splitlines()
+    before = lines.index("before hooks.ok {'value': 'x'}")
+    assert lines[before + 1] == "running ok"
+    assert lines[before + 2] == "after hooks.ok 0"
+
+
+def test_on_error_hook_receives_exception():
+    """on_error hooks run when the task raises."""
+    rv = _run("hooks", "fail")
+    assert rv.returncode == 56
+    assert "on_error hooks.fail ValueError" in rv.stdout
+    assert "after hooks.fail" not in rv.stdout
+
+
+def test_on_error_hook_skips_clean_exit():
+    """sys.exit(0) isn't a failure, a non-zero exit is."""
+    rv = _run("hooks", "exit")
+    assert rv.returncode == 0
+    assert "on_error" not in rv.stdout
+
+    rv = _run("hooks", "exit", "--code", "3")
+    assert rv.returncode == 3
+    assert "on_error hooks.exit SystemExit" in rv.stdout
+
+
+def test_before_hook_can_veto_task():
+    """An exception in a before hook stops the task."""
+    rv = _run("hooks", "blocked")
+    assert rv.returncode == 56
+    assert "should not run" not in rv.stdout
+    assert "on_error hooks.blocked RuntimeError" in rv.stdout
+
+
+def test_hooks_ignore_unmatched_commands():
+    """Hooks scoped to a pattern don't fire for other commands."""
+    rv = _run("exit-zero")
+    assert rv.returncode == 0
+    assert "before" not in rv.stdout
@@ -0,0 +1,58 @@
+"""Tests for incremental task execution with inputs/outputs."""
+import os
+import shutil
+import subprocess
+
+import pytest
+
+here = os.path.dirname(__file__)
+functional_test_folder = os.path.join(here, "functional_tests")
+build_dir = os.path.join(functional_test_folder, "incremental_build")
+
+
+def _run(*args):
+    return subprocess.run(
+        ["angreal", *args],
+        cwd=functional_test_folder,
+        capture_output=True,
+        text=True,
+    )
+
+
+@pytest.fixture(autouse=True)
+def build_folder():
+    os.makedirs(build_dir, exist_ok=True)
+    with open(os.path.join(build_dir, "input.txt"), "w") as f:
+        f.write("v1")
+    yield
+    shutil.rmtree(build_dir)
+
+
+def test_unchanged_inputs_skip_the_task():
+    """A second run with the same inputs and arguments is skipped."""
+    assert "built" in _run("incremental-build").stdout
+    rv = _run("incremental-build")
+    assert rv.returncode == 0
+    assert "up to date" in rv.stdout
+    assert "built" not in rv.stdout
+
+
+def test_changed_inputs_rerun_the_task():
+    """Changing an input file runs the task again."""
+    _run("incremental-build")
+    with open(os.path.join(build_dir, "input.txt"), "w") as f:
+        f.write("v2")
+    assert "built" in _run("incremental-build").stdout
+
+
+def test_changed_arguments_rerun_the_task():
+    """Different arguments produce a different fingerprint."""
+    _run("incremental-build")
+    assert "built" in _run("incremental-build", "--suffix", "!").stdout
+
+
+def test_missing_outputs_rerun_the_task():
+    """A task whose outputs were removed runs again."""
+    _run("incremental-build")
+    os.remove(os.path.join(build_dir, "output.txt"))
+    assert "built" in _run("incremental-build").stdout
@@ -0,0 +1,59 @@
+"""Tests for project run locks."""
+import os
+import subprocess
+import time
+
+here = os.path.dirname(__file__)
+functional_test_folder = os.path.join(here, "functional_tests")
+
+
+def _run(*args):
+    return subprocess.run(
+        ["angreal", *args],
+        cwd=functional_test_folder,
+        capture_output=True,
+        text=True,
+    )
+
+
+def _hold(seconds):
+    proc = subprocess.Popen(
+        ["angreal", "lock-hold", "--seconds", str(seconds)],
+        cwd=functional_test_folder,
+        stdout=subprocess.PIPE,
+        text=True,
+    )
+    assert proc.stdout.readline().strip() == "holding"
+    return proc
+
+
+def test_no_wait_fails_while_locked():
+    holder = _hold(3)
+    try:
+        rv = _run("--no-wait", "lock-quick")
+        assert rv.returncode == 75
+        assert "lock-hold" in rv.stderr
+        assert "quick ran" not in rv.stdout
+
+        locks = _run("locks")
+        assert "lock-test" in locks.stdout
+        assert "lock-hold" in locks.stdout
+    finally:
+        holder.wait()
+
+
+def test_waits_for_lock():
+    holder = _hold(2)
+    started = time.time()
+    rv = _run("lock-quick")
+    holder.wait()
+    assert rv.returncode == 0
+    assert "quick ran" in rv.stdout
+    assert "waiting" in rv.stderr
+    assert time.time() - started >= 1
+
+
+def test_lock_released_after_run():
+    assert _run("lock-quick").returncode == 0
+    assert _run("--no-wait", "lock-quick").returncode == 0
+    assert "No locks are held." in _run("locks").stdout
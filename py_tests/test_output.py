@@ -0,0 +1,59 @@
+"""Tests for structured task results and --output json."""
+import json
+import os
+import subprocess
+
+here = os.path.dirname(__file__)
+functional_test_folder = os.path.join(here, "functional_tests")
+
+
+def _run(*args):
+    return subprocess.run(
+        ["angreal", *args],
+        cwd=functional_test_folder,
+        capture_output=True,
+        text=True,
+    )
+
+
+def test_dataclass_result_as_json():
+    """A returned dataclass is serialised and task output stays off stdout."""
+    rv = _run("--output", "json", "output-report")
+    assert rv.returncode == 0
+    outcome = json.loads(rv.stdout)
+    assert outcome["command"] == "output-report"
+    assert outcome["success"] is True
+    assert outcome["result"] == {"success": True, "problems": []}
+    assert "checking files" in rv.stderr
+
+
+def test_success_field_sets_exit_code():
+    """A result with success=False fails the task in either output mode."""
+    rv = _run("--output", "json", "output-report", "--fail")
+    assert rv.returncode == 1
+    assert json.loads(rv.stdout)["result"]["problems"] == ["a.py:1"]
+
+    assert _run("output-report", "--fail").returncode == 1
+
+
+def test_dict_result_as_json():
+    rv = _run("--output", "json", "output-dict")
+    assert rv.returncode == 0
+    assert json.loads(rv.stdout)["result"]["version"] == "1.2.3"
+
+
+def test_child_process_output_stays_off_stdout():
+    """Child processes write to stderr, leaving stdout a single JSON document."""
+    rv = _run("--output", "json", "output-subprocess")
+    assert rv.returncode == 0, rv.stderr
+    assert json.loads(rv.stdout)["result"] == {"ran": True}
+    assert "x\n" in rv.stderr
+
+
+def test_exception_as_json():
+    """An exception is reported in the error field."""
+    rv = _run("--output", "json", "output-raise")
+    assert rv.returncode != 0
+    outcome = json.loads(rv.stdout)
+    assert outcome["success"] is False
+    assert outcome["error"] == {"type": "ValueError", "message": "something broke"}
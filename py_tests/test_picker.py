@@ -0,0 +1,69 @@
+"""Tests for the interactive task picker."""
+import os
+import select
+import subprocess
+import sys
+import time
+
+import pytest
+
+here = os.path.dirname(__file__)
+functional_test_folder = os.path.join(here, "functional_tests")
+
+
+def _read(fd, seconds):
+    output = b""
+    end = time.time() + seconds
+    while time.time() < end:
+        ready, _, _ = select.select([fd], [], [], 0.1)
+        if ready:
+            try:
+                output += os.read(fd, 4096)
+            except OSError:
+                break
+    return output.decode(errors="replace")
+
+
+def _spawn_in_terminal(env=None):
+    import pty
+
+    pid, fd = pty.fork()
+    if pid == 0:
+        os.chdir(functional_test_folder)
+        if env:
+            os.environ.update(env)
+        os.execvp("angreal", ["angreal"])
+    return pid, fd
+
+
+@pytest.mark.skipif(sys.platform == "win32", reason="requires a pty")
+def test_picker_runs_selected_task():
+    pid, fd = _spawn_in_terminal()
+    _read(fd, 3)
+    os.write(fd, b"outputdict")
+    _read(fd, 1)
+    os.write(fd, b"\r")
+    output = _read(fd, 3)
+    _, status = os.waitpid(pid, 0)
+    assert "Running: angreal output-dict" in output
+    assert os.WEXITSTATUS(status) == 0
+
+
+@pytest.mark.skipif(sys.platform == "win32", reason="requires a pty")
+def test_picker_can_be_disabled():
+    pid, fd = _spawn_in_terminal({"ANGREAL_NO_PICKER": "1"})
+    output = _read(fd, 3)
+    os.waitpid(pid, 0)
+    assert "USAGE:" in output
+
+
+def test_help_without_terminal():
+    rv = subprocess.run(
+        ["angreal"],
+        cwd=functional_test_folder,
+        stdin=subprocess.DEVNULL,
+        capture_output=True,
+        text=True,
+    )
+    assert rv.returncode == 2
+    assert "USAGE:" in rv.stdout + rv.stderr
@@ -0,0 +1,55 @@
+"""Tests for retry policies on commands."""
+import os
+import subprocess
+
+import pytest
+
+here = os.path.dirname(__file__)
+functional_test_folder = os.path.join(here, "functional_tests")
+attempts_file = os.path.join(functional_test_folder, "retry_attempts.txt")
+
+
+def _run(*args):
+    return subprocess.run(
+        ["angreal", *args],
+        cwd=functional_test_folder,
+        capture_output=True,
+        text=True,
+    )
+
+
+def _attempts():
+    with open(attempts_file) as f:
+        return int(f.read())
+
+
+@pytest.fixture(autouse=True)
+def reset_attempts():
+    yield
+    if os.path.exists(attempts_file):
+        os.remove(attempts_file)
+
+
+def test_retried_until_success():
+    rv = _run("retry-flaky", "--succeed-on", "3")
+    assert rv.returncode == 0
+    assert "succeeded on attempt 3" in rv.stdout
+    assert "Attempt 1/4" in rv.stderr
+
+
+def test_gives_up_after_retries():
+    rv = _run("retry-flaky", "--succeed-on", "10")
+    assert rv.returncode != 0
+    assert _attempts() == 4
+
+
+def test_unmatched_exception_not_retried():
+    rv = _run("retry-wrong-error")
+    assert rv.returncode != 0
+    assert _attempts() == 1
+
+
+def test_non_zero_exit_retried():
+    rv = _run("retry-exit-code")
+    assert rv.returncode == 0
+    assert _attempts() == 2
@@ -0,0 +1,89 @@
+"""Tests for task timeouts and cancellation."""
+import os
+import signal
+import subprocess
+import tempfile
+import time
+
+here = os.path.dirname(__file__)
+functional_test_folder = os.path.join(here, "functional_tests")
+
+
+def _run(*args):
+    return subprocess.run(
+        ["angreal", *args],
+        cwd=functional_test_folder,
+        capture_output=True,
+        text=True,
+    )
+
+
+def test_task_within_timeout():
+    """A task that finishes before its timeout exits normally."""
+    rv = _run("timeout-sleep", "--seconds", "0.1")
+    assert rv.returncode == 0
+    assert "finished sleeping" in rv.stdout
+
+
+def test_task_timeout_exit_code():
+    """A task that runs past its timeout is interrupted with exit code 124."""
+    rv = _run("timeout-sleep")
+    assert rv.returncode == 124
+    assert "finished sleeping" not in rv.stdout
+    assert "timed out" in rv.stderr
+
+
+def test_task_sees_keyboard_interrupt():
+    """A timed out task gets a KeyboardInterrupt it can clean up after."""
+    rv = _run("timeout-cleanup")
+    assert rv.returncode == 124
+    assert "cleaning up" in rv.stdout
+
+
+def test_global_timeout_overrides_task():
+    """--timeout overrides the timeout set on the command."""
+    rv = _run("--timeout", "5s", "timeout-sleep", "--seconds", "2")
+    assert rv.returncode == 0
+    assert "finished sleeping" in rv.stdout
+
+
+def test_interrupt_reaches_integration_children():
+    """SIGTERM while an integration waits on a child stops the child too."""
+    with tempfile.TemporaryDirectory() as tmp:
+        pid_file = os.path.join(tmp, "child.pid")
+        flox = os.path.join(tmp, "flox")
+        with open(flox, "w") as f:
+            f.write(
+                "#!/bin/sh\n"
+                'while [ "$1" != "--" ]; do shift; done; shift\n'
+                f'echo $$ > "{pid_file}"\n'
+                'exec "$@"\n'
+            )
+        os.chmod(flox, 0o755)
+        env = dict(os.environ, PATH=f"{tmp}{os.pathsep}{os.environ['PATH']}")
+
+        proc = subprocess.Popen(
+            ["angreal", "timeout-integration"],
+            cwd=functional_test_folder,
+            env=env,
+            stdout=subprocess.PIPE,
+            stderr=subprocess.PIPE,
+            text=True,
+        )
+        deadline = time.time() + 10
+        while not os.path.exists(pid_file) and time.time() < deadline:
+            time.sleep(0.05)
+        time.sleep(0.2)
+        with open(pid_file) as f:
+            child = int(f.read())
+
+        proc.send_signal(signal.SIGTERM)
+        stdout, stderr = proc.communicate(timeout=10)
+        assert proc.returncode == 130, stderr
+        assert "finished sleeping" not in stdout
+        try:
+            os.kill(child, 0)
+            alive = True
+        except ProcessLookupError:
+            alive = False
+        assert not alive
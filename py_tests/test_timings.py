@@ -0,0 +1,68 @@
+"""Tests for the --timings report."""
+import json
+import os
+import shutil
+import subprocess
+import tempfile
+
+here = os.path.dirname(__file__)
+functional_test_folder = os.path.join(here, "functional_tests")
+cache_folder = os.path.join(functional_test_folder, ".angreal", ".cache")
+
+
+def _run(*args):
+    return subprocess.run(
+        ["angreal", *args],
+        cwd=functional_test_folder,
+        capture_output=True,
+        text=True,
+    )
+
+
+def test_timings_report():
+    rv = _run("--timings", "timings-sleep")
+    assert rv.returncode == 0
+    assert "Timings:" in rv.stderr
+    assert "load_python task_timings.py" in rv.stderr
+    assert "before timings_setup" in rv.stderr
+    assert "timings-sleep" in rv.stderr
+    assert "total" in rv.stderr
+    assert "Timings:" not in rv.stdout
+
+
+def test_no_report_without_flag():
+    rv = _run("timings-sleep")
+    assert rv.returncode == 0
+    assert "Timings:" not in rv.stderr
+
+
+def test_chrome_trace():
+    with tempfile.TemporaryDirectory() as tmp:
+        trace_file = os.path.join(tmp, "trace.json")
+        rv = _run(f"--timings={trace_file}", "timings-sleep")
+        assert rv.returncode == 0
+
+        with open(trace_file) as f:
+            trace = json.load(f)
+        task = [e for e in trace["traceEvents"] if e["cat"] == "task"]
+        assert task[0]["name"] == "timings-sleep"
+        assert task[0]["ph"] == "X"
+        assert task[0]["dur"] >= 200_000
+
+
+def test_rerun_honors_timings():
+    shutil.rmtree(cache_folder, ignore_errors=True)
+    try:
+        assert _run("timings-sleep").returncode == 0
+        rv = _run("--timings", "rerun")
+        assert rv.returncode == 0
+        assert "Timings:" in rv.stderr
+        assert "timings-sleep" in rv.stderr
+    finally:
+        shutil.rmtree(cache_folder, ignore_errors=True)
+
+
+def test_watch_rejects_timings():
+    rv = _run("--timings", "watch", "--", "timings-sleep")
+    assert rv.returncode == 1
+    assert "--timings can't be used with watch" in rv.stderr
@@ -0,0 +1,264 @@
+"""Tests for angreal update and info."""
+import json
+import os
+import re
+import subprocess
+import tempfile
+
+GIT = ["git", "-c", "user.name=angreal", "-c", "user.email=angreal@localhost"]
+
+
+def _write(path, contents):
+    os.makedirs(os.path.dirname(path), exist_ok=True)
+    with open(path, "w") as f:
+        f.write(contents)
+
+
+def _commit(template):
+    subprocess.run(GIT + ["add", "-A"], cwd=template, check=True)
+    subprocess.run(GIT + ["commit", "-qm", "template"], cwd=template, check=True)
+
+
+def _template(root):
+    template = os.path.join(root, "template")
+    project = os.path.join(template, "{{ name }}")
+    _write(os.path.join(template, "angreal.toml"), 'name = "demo"\n')
+    _write(os.path.join(project, ".angreal", "task_noop.py"), "import angreal\n")
+    _write(os.path.join(project, "README.md"), "# {{ name }}\n\nintro\n\nfooter\n")
+    _write(os.path.join(project, "setup.cfg"), "[metadata]\nname = {{ name }}\n")
+    subprocess.run(["git", "init", "-q", template], check=True)
+    _commit(template)
+    return template
+
+
+def _angreal(cwd, *args):
+    return subprocess.run(
+        ["angreal", *args], cwd=cwd, capture_output=True, text=True
+    )
+
+
+def test_init_records_template_for_info():
+    with tempfile.TemporaryDirectory() as root:
+        template = _template(root)
+        assert _angreal(root, "init", "--defaults", template).returncode == 0
+        project = os.path.join(root, "demo")
+
+        rv = _angreal(project, "--output", "json", "info")
+        assert rv.returncode == 0, rv.stderr
+        info = json.loads(rv.stdout)
+        assert info["template"]["source"] == os.path.realpath(template)
+        assert len(info["template"]["commit"]) == 40
+        assert info["template"]["angreal_version"] == info["angreal_version"]
+        assert info["template"]["rendered_at"].endswith("Z")
+
+        rv = _angreal(project, "info")
+        assert f"Template:         {os.path.realpath(template)}" in rv.stdout
+
+
+def test_update_merges_template_changes():
+    with tempfile.TemporaryDirectory() as root:
+        template = _template(root)
+        rv = _angreal(root, "init", "--defaults", template)
+        assert rv.returncode == 0, rv.stderr
+        project = os.path.join(root, "demo")
+        with open(os.path.join(project, ".angreal", "angreal.toml")) as f:
+            assert f'source = "{os.path.realpath(template)}"' in f.read()
+
+        _write(os.path.join(project, "README.md"), "# demo\n\nmy intro\n\nfooter\n")
+        templated = os.path.join(template, "{{ name }}")
+        readme = "# {{ name }}\n\nintro\n\nnew footer\n"
+        _write(os.path.join(templated, "README.md"), readme)
+        _write(os.path.join(templated, "LICENSE"), "MIT {{ name }}\n")
+        _commit(template)
+
+        rv = _angreal(project, "update")
+        assert rv.returncode == 0, rv.stderr
+        assert "merged    README.md" in rv.stdout
+        with open(os.path.join(project, "README.md")) as f:
+            assert f.read() == "# demo\n\nmy intro\n\nnew footer\n"
+        with open(os.path.join(project, "LICENSE")) as f:
+            assert f.read() == "MIT demo\n"
+
+        rv = _angreal(project, "update")
+        assert "up to date" in rv.stdout
+
+
+def test_update_reports_conflicts():
+    with tempfile.TemporaryDirectory() as root:
+        template = _template(root)
+        assert _angreal(root, "init", "--defaults", template).returncode == 0
+        project = os.path.join(root, "demo")
+
+        _write(os.path.join(project, "setup.cfg"), "[metadata]\nname = mine\n")
+        templated = os.path.join(template, "{{ name }}")
+        _write(os.path.join(templated, "setup.cfg"), "[metadata]\nname = theirs\n")
+        _commit(template)
+
+        rv = _angreal(project, "update")
+        assert rv.returncode == 1
+        assert "conflict  setup.cfg" in rv.stdout
+        with open(os.path.join(project, "setup.cfg")) as f:
+            assert f.read() == (
+                "[metadata]\n<<<<<<< project\nname = mine\n=======\n"
+                "name = theirs\n>>>>>>> template\n"
+            )
+
+
+def test_update_without_commits_records_the_render():
+    with tempfile.TemporaryDirectory() as root:
+        template = os.path.join(root, "template")
+        templated = os.path.join(template, "{{ name }}")
+        _write(os.path.join(template, "angreal.toml"), 'name = "demo"\n')
+        _write(os.path.join(templated, ".angreal", "task_noop.py"), "import angreal\n")
+        _write(os.path.join(templated, "README.md"), "# {{ name }}\n")
+        assert _angreal(root, "init", "--defaults", template).returncode == 0
+        project = os.path.join(root, "demo")
+        answers = os.path.join(project, ".angreal", "angreal.toml")
+        with open(answers) as f:
+            contents = f.read()
+        assert "commit" not in contents
+        stale = 'rendered_at = "2000-01-01T00:00:00Z"'
+        with open(answers, "w") as f:
+            f.write(re.sub(r'rendered_at = ".*"', stale, contents))
+
+        _write(os.path.join(templated, "LICENSE"), "MIT {{ name }}\n")
+        rv = _angreal(project, "update")
+        assert rv.returncode == 0, rv.stderr
+        assert os.path.isfile(os.path.join(project, "LICENSE"))
+        with open(answers) as f:
+            assert stale not in f.read()
+
+
+def test_pinned_template_updates_with_ref():
+    with tempfile.TemporaryDirectory() as root:
+        template = _template(root)
+        subprocess.run(GIT + ["tag", "v1"], cwd=template, check=True)
+        templated = os.path.join(template, "{{ name }}")
+        _write(os.path.join(templated, "LICENSE"), "MIT {{ name }}\n")
+        _commit(template)
+        subprocess.run(GIT + ["tag", "v2"], cwd=template, check=True)
+
+        env = dict(os.environ, HOME=root)
+        source = "file://" + os.path.realpath(template)
+        rv = subprocess.run(
+            ["angreal", "init", "--defaults", source + "@v1"],
+            cwd=root, env=env, capture_output=True, text=True,
+        )
+        assert rv.returncode == 0, rv.stderr
+        project = os.path.join(root, "demo")
+        assert not os.path.exists(os.path.join(project, "LICENSE"))
+
+        def update(*args):
+            return subprocess.run(
+                ["angreal", "update", *args],
+                cwd=project, env=env, capture_output=True, text=True,
+            )
+
+        assert "up to date" in update().stdout
+        rv = update("--ref", "v2")
+        assert rv.returncode == 0, rv.stderr
+        with open(os.path.join(project, "LICENSE")) as f:
+            assert f.read() == "MIT demo\n"
+        with open(os.path.join(project, ".angreal", "angreal.toml")) as f:
+            assert 'ref = "v2"' in f.read()
+
+
+def test_pinned_branch_picks_up_new_commits():
+    with tempfile.TemporaryDirectory() as root:
+        template = os.path.join(root, "template")
+        subprocess.run(["git", "init", "-q", "-b", "main", template], check=True)
+        _write(os.path.join(template, "angreal.toml"), 'name = "demo"\n')
+        templated = os.path.join(template, "{{ name }}")
+        _write(os.path.join(templated, ".angreal", "task_noop.py"), "import angreal\n")
+        _write(os.path.join(templated, "README.md"), "# {{ name }}\n")
+        _commit(template)
+
+        env = dict(os.environ, HOME=root)
+        source = "file://" + os.path.realpath(template)
+        rv = subprocess.run(
+            ["angreal", "init", "--defaults", source + "@main"],
+            cwd=root, env=env, capture_output=True, text=True,
+        )
+        assert rv.returncode == 0, rv.stderr
+        project = os.path.join(root, "demo")
+
+        _write(os.path.join(templated, "LICENSE"), "MIT {{ name }}\n")
+        _commit(template)
+        rv = subprocess.run(
+            ["angreal", "update", "--ref", "main"],
+            cwd=project, env=env, capture_output=True, text=True,
+        )
+        assert rv.returncode == 0, rv.stderr
+        with open(os.path.join(project, "LICENSE")) as f:
+            assert f.read() == "MIT demo\n"
+
+
+def test_cached_clone_can_be_pinned():
+    with tempfile.TemporaryDirectory() as root:
+        upstream = _template(root)
+        subprocess.run(GIT + ["tag", "v1"], cwd=upstream, check=True)
+        templated = os.path.join(upstream, "{{ name }}")
+        _write(os.path.join(templated, "LICENSE"), "MIT {{ name }}\n")
+        _commit(upstream)
+        cache = os.path.join(root, ".angrealrc")
+        os.makedirs(cache)
+        subprocess.run(
+            ["git", "clone", "-q", upstream, os.path.join(cache, "python")],
+            check=True,
+        )
+
+        env = dict(os.environ, HOME=root)
+        for args in (["python@v1"], ["--ref", "v1", "python"]):
+            workdir = tempfile.mkdtemp(dir=root)
+            rv = subprocess.run(
+                ["angreal", "init", "--defaults", *args],
+                cwd=workdir, env=env, capture_output=True, text=True,
+            )
+            assert rv.returncode == 0, rv.stderr
+            project = os.path.join(workdir, "demo")
+            assert os.path.isfile(os.path.join(project, "README.md"))
+            assert not os.path.exists(os.path.join(project, "LICENSE"))
+            with open(os.path.join(project, ".angreal", "angreal.toml")) as f:
+                assert 'ref = "v1"' in f.read()
+
+
+def test_template_in_subdirectory():
+    with tempfile.TemporaryDirectory() as root:
+        repo = os.path.join(root, "templates")
+        _write(os.path.join(repo, "README.md"), "templates\n")
+        template = os.path.join(repo, "python-lib")
+        _write(os.path.join(template, "angreal.toml"), 'name = "lib"\n')
+        project = os.path.join(template, "{{ name }}")
+        _write(os.path.join(project, ".angreal", "task_noop.py"), "import angreal\n")
+        _write(os.path.join(project, "README.md"), "# {{ name }}\n")
+        subprocess.run(["git", "init", "-q", "-b", "main", repo], check=True)
+        _commit(repo)
+
+        env = dict(os.environ, HOME=root)
+        source = "file://" + os.path.realpath(repo) + "#subdir=python-lib"
+        rv = subprocess.run(
+            ["angreal", "init", "--defaults", source],
+            cwd=root, env=env, capture_output=True, text=True,
+        )
+        assert rv.returncode == 0, rv.stderr
+        project = os.path.join(root, "lib")
+        with open(os.path.join(project, "README.md")) as f:
+            assert f.read() == "# lib\n"
+        with open(os.path.join(project, ".angreal", "angreal.toml")) as f:
+            assert 'subdir = "python-lib"' in f.read()
+
+        _write(os.path.join(template, "{{ name }}", "LICENSE"), "MIT {{ name }}\n")
+        _commit(repo)
+        rv = subprocess.run(
+            ["angreal", "update"],
+            cwd=project, env=env, capture_output=True, text=True,
+        )
+        assert rv.returncode == 0, rv.stderr
+        with open(os.path.join(project, "LICENSE")) as f:
+            assert f.read() == "MIT lib\n"
+
+        rv = subprocess.run(
+            ["angreal", "init", "--defaults", source.replace("python-lib", "nope")],
+            cwd=root, env=env, capture_output=True, text=True,
+        )
+        assert rv.returncode != 0
@@ -0,0 +1,32 @@
+"""Tests for angreal watch."""
+import os
+import signal
+import subprocess
+import time
+
+here = os.path.dirname(__file__)
+functional_test_folder = os.path.join(here, "functional_tests")
+watched_file = os.path.join(functional_test_folder, "watched.txt")
+
+
+def test_watch_reruns_on_change():
+    """The task runs once, then again after a watched file changes."""
+    proc = subprocess.Popen(
+        ["angreal", "watch", "--paths", "watched.txt", "watch-echo", "--message", "hello"],
+        cwd=functional_test_folder,
+        stdout=subprocess.PIPE,
+        stderr=subprocess.PIPE,
+        text=True,
+    )
+    try:
+        time.sleep(3)
+        with open(watched_file, "w") as f:
+            f.write("changed")
+        time.sleep(3)
+    finally:
+        proc.send_signal(signal.SIGINT)
+        stdout, _ = proc.communicate(timeout=10)
+        os.remove(watched_file)
+
+    assert stdout.count("ran hello") == 2
+    assert "watched.txt changed" in stdout